@@ -0,0 +1,81 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timings of building a `Bvh` and of raycasting through it, over random
+//! boxes in a unit cube at a few primitive counts.
+//!
+//! Run with `cargo bench --bench bvh`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use druid_wgpu::bvh::{Aabb, Bvh, Ray};
+use druid_wgpu::rng::Rng;
+
+const COUNTS: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// Rays cast per raycast iteration.
+const RAYS: usize = 1_000;
+
+/// `count` small boxes scattered through the unit cube, the same every run.
+fn boxes(count: usize) -> Vec<Aabb> {
+    let mut rng = Rng::new(1);
+    (0..count)
+        .map(|_| {
+            let min = [0; 3].map(|_| rng.next_f32());
+            let size = rng.range(0.001, 0.01);
+            Aabb::new(min, min.map(|value| value + size))
+        })
+        .collect()
+}
+
+/// Rays from outside the cube through random points in it.
+fn rays() -> Vec<Ray> {
+    let mut rng = Rng::new(2);
+    (0..RAYS)
+        .map(|_| {
+            let origin = [0.5, 0.5, -1.0];
+            let target = [0; 3].map(|_| rng.next_f32());
+            let direction = [0, 1, 2].map(|axis| target[axis] - origin[axis]);
+            Ray::new(origin, direction)
+        })
+        .collect()
+}
+
+fn bvh(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bvh");
+    let rays = rays();
+    for count in COUNTS {
+        let bounds = boxes(count);
+
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_function(BenchmarkId::new("build", count), |b| {
+            b.iter(|| Bvh::build(&bounds))
+        });
+
+        let bvh = Bvh::build(&bounds);
+        group.throughput(Throughput::Elements(RAYS as u64));
+        group.bench_function(BenchmarkId::new("raycast", count), |b| {
+            b.iter(|| {
+                rays.iter()
+                    .filter_map(|ray| {
+                        bvh.raycast(ray, |i| bounds[i as usize].intersect(ray, f32::INFINITY))
+                    })
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bvh);
+criterion_main!(benches);
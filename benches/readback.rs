@@ -0,0 +1,104 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Timings of each stage of the widget's readback path, from recording the
+//! frame to uploading it to piet, at a few sizes.
+//!
+//! The stages run through `druid_wgpu::bench::FrameReadback`, which uses
+//! the widget's own present pass, output buffer, `compat` layouts and
+//! `map_read`. Sizes include widths that are already aligned to 256 pixels
+//! and ones that need row padding stripped.
+//!
+//! Run with `cargo bench --bench readback`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use druid::piet::{Device, ImageFormat, RenderContext};
+use druid::ImageBuf;
+use druid_wgpu::bench::FrameReadback;
+
+const SIZES: [(u32, u32); 5] = [
+    (256, 256),
+    (1000, 700),
+    (1366, 768),
+    (1920, 1080),
+    (3840, 2160),
+];
+
+fn readback(c: &mut Criterion) {
+    let mut group = c.benchmark_group("readback");
+    for (width, height) in SIZES {
+        let id = format!("{}x{}", width, height);
+        let readback = FrameReadback::new(width, height).expect("no GPU to benchmark on");
+        group.throughput(Throughput::Bytes((4 * width * height) as u64));
+
+        group.bench_function(BenchmarkId::new("encode", &id), |b| {
+            b.iter(|| readback.encode())
+        });
+
+        group.bench_function(BenchmarkId::new("submit_and_map", &id), |b| {
+            b.iter_batched(
+                || readback.encode(),
+                |commands| {
+                    readback.submit_and_map(commands);
+                    readback.unmap();
+                },
+                BatchSize::SmallInput,
+            )
+        });
+
+        // Copying out of mapped memory, which can be slower than ordinary
+        // memory on some platforms.
+        readback.submit_and_map(readback.encode());
+        group.bench_function(BenchmarkId::new("copy_rows", &id), |b| {
+            b.iter(|| readback.copy_rows())
+        });
+        readback.unmap();
+
+        let pixels = readback.pixels();
+        group.bench_function(BenchmarkId::new("image_buf", &id), |b| {
+            b.iter_batched(
+                || pixels.clone(),
+                |pixels| {
+                    ImageBuf::from_raw(
+                        pixels,
+                        ImageFormat::RgbaPremul,
+                        width as usize,
+                        height as usize,
+                    )
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        let mut device = Device::new().expect("no piet device");
+        let mut target = device
+            .bitmap_target(width as usize, height as usize, 1.0)
+            .expect("couldn't create a bitmap target");
+        let mut ctx = target.render_context();
+        let frame = ImageBuf::from_raw(
+            pixels,
+            ImageFormat::RgbaPremul,
+            width as usize,
+            height as usize,
+        );
+        group.bench_function(BenchmarkId::new("piet_upload", &id), |b| {
+            b.iter(|| frame.to_image(&mut ctx))
+        });
+        let _ = ctx.finish();
+    }
+    group.finish();
+}
+
+criterion_group!(benches, readback);
+criterion_main!(benches);
@@ -0,0 +1,132 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A Mandelbrot explorer: drag to pan, scroll to zoom.
+
+use druid::widget::prelude::*;
+use druid::widget::{Controller, Flex, Label, Slider};
+use druid::{AppLauncher, Point, WidgetExt, WindowDesc};
+
+use druid_wgpu::effects::{self, split_center};
+use druid_wgpu::{Params, Playback, ViewportState, WgpuWidget};
+
+/// Pans and zooms the view by editing the `mandelbrot.*` parameters.
+///
+/// The center is also kept at full precision in `x` and `y`, since
+/// `mandelbrot.center` is only as precise as two `f32`s.
+#[derive(Default)]
+struct PanZoom {
+    drag_start: Option<Point>,
+}
+
+impl PanZoom {
+    fn move_center(params: &mut Params, x: f64, y: f64) {
+        params.set("x", x);
+        params.set("y", y);
+        params.set("mandelbrot.center", split_center(x, y));
+    }
+}
+
+impl<W: Widget<ViewportState>> Controller<ViewportState, W> for PanZoom {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut ViewportState,
+        env: &Env,
+    ) {
+        let params = &mut data.params;
+        let x = params.float("x").unwrap_or_default();
+        let y = params.float("y").unwrap_or_default();
+        let scale = params.float("mandelbrot.scale").unwrap_or(3.0);
+        // Set units per logical pixel.
+        let unit = scale / ctx.size().height.max(1.0);
+
+        match event {
+            Event::MouseDown(mouse) => {
+                self.drag_start = Some(mouse.pos);
+                ctx.set_active(true);
+            }
+            Event::MouseMove(mouse) => {
+                if let Some(start) = self.drag_start {
+                    let delta = mouse.pos - start;
+                    Self::move_center(params, x - delta.x * unit, y + delta.y * unit);
+                    self.drag_start = Some(mouse.pos);
+                }
+            }
+            Event::MouseUp(_) => {
+                self.drag_start = None;
+                ctx.set_active(false);
+            }
+            Event::Wheel(mouse) => {
+                // Zoom about the cursor, so the point under it stays put.
+                let factor = 1.1f64.powf(mouse.wheel_delta.y / 100.0);
+                let from_center = mouse.pos - ctx.size().to_rect().center();
+                let (dx, dy) = (from_center.x * unit, -from_center.y * unit);
+                Self::move_center(params, x + dx - dx * factor, y + dy - dy * factor);
+                params.set("mandelbrot.scale", scale * factor);
+                ctx.set_handled();
+            }
+            _ => (),
+        }
+
+        child.event(ctx, event, data, env);
+    }
+}
+
+fn controls() -> impl Widget<ViewportState> {
+    Flex::column()
+        .with_child(Label::dynamic(|data: &Params, _| {
+            format!(
+                "Zoom: {:.1e}\nIterations: {:.0}",
+                3.0 / data.float("mandelbrot.scale").unwrap_or(3.0),
+                data.float("mandelbrot.iterations").unwrap_or_default()
+            )
+        }))
+        .with_spacer(8.0)
+        .with_child(
+            Slider::new()
+                .with_range(16.0, 4096.0)
+                .lens(Params::float_lens("mandelbrot.iterations"))
+                .expand_width(),
+        )
+        .padding(8.0)
+        .fix_width(200.0)
+        .lens(ViewportState::params)
+}
+
+pub fn main() {
+    let viewport = pollster::block_on(WgpuWidget::new())
+        .with_post_effect(effects::mandelbrot())
+        .controller(PanZoom::default());
+
+    let window = WindowDesc::new(
+        Flex::row()
+            .with_flex_child(viewport, 1.0)
+            .with_child(controls()),
+    )
+    .title("Mandelbrot");
+
+    let mut params = Params::new()
+        .with("mandelbrot.enabled", true)
+        .with("mandelbrot.scale", 3.0)
+        .with("mandelbrot.iterations", 512i64);
+    PanZoom::move_center(&mut params, -0.5, 0.0);
+
+    AppLauncher::with_window(window)
+        .log_to_console()
+        .launch(ViewportState::new(Playback::new(0.0), params))
+        .expect("launch failed");
+}
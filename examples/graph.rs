@@ -0,0 +1,72 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Force-directed layout of a random graph: drag nodes around, scroll to
+//! zoom.
+
+use druid::widget::{Button, Flex, Label};
+use druid::{AppLauncher, Widget, WidgetExt, WindowDesc};
+
+use druid_wgpu::graph::{GraphState, GraphView};
+use druid_wgpu::rng::Rng;
+
+const NODES: u32 = 2000;
+
+/// A spanning tree, so the graph is connected, plus some random chords.
+fn random_edges(rng: &mut Rng) -> Vec<(u32, u32)> {
+    let mut edges: Vec<(u32, u32)> = (1..NODES)
+        .map(|node| ((rng.next_f32() * node as f32) as u32, node))
+        .collect();
+    for _ in 0..NODES / 4 {
+        let a = rng.next_u32() % NODES;
+        let b = rng.next_u32() % NODES;
+        if a != b {
+            edges.push((a, b));
+        }
+    }
+    edges
+}
+
+fn controls() -> impl Widget<GraphState> {
+    Flex::row()
+        .with_child(
+            Button::dynamic(|data: &GraphState, _| {
+                if data.running { "Pause" } else { "Run" }.into()
+            })
+            .on_click(|_ctx, data: &mut GraphState, _env| data.running = !data.running),
+        )
+        .with_spacer(8.0)
+        .with_child(Label::dynamic(|data: &GraphState, _| match data.selected {
+            Some(node) => format!("Selected node {}", node),
+            None => "Click a node to select it".into(),
+        }))
+        .padding(8.0)
+}
+
+pub fn main() {
+    let edges = random_edges(&mut Rng::new(1));
+    let graph = pollster::block_on(GraphView::new(NODES, &edges));
+
+    let window = WindowDesc::new(
+        Flex::column()
+            .with_flex_child(graph, 1.0)
+            .with_child(controls()),
+    )
+    .title("Graph layout");
+
+    AppLauncher::with_window(window)
+        .log_to_console()
+        .launch(GraphState::new())
+        .expect("launch failed");
+}
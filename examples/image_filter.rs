@@ -0,0 +1,80 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compute filters on an image: `cargo run --example image_filter <image>`.
+
+use druid::widget::prelude::*;
+use druid::widget::{Button, Flex, Label, RadioGroup, Slider};
+use druid::{AppLauncher, ImageBuf, WidgetExt, WindowDesc};
+
+use druid_wgpu::filter::{FilterSettings, ImageFilter, Kernel, EXPORT_IMAGE};
+
+fn slider(label: &'static str, min: f64, max: f64) -> impl Widget<f64> {
+    Flex::column()
+        .with_child(Label::dynamic(move |value: &f64, _| {
+            format!("{}: {:.2}", label, value)
+        }))
+        .with_child(Slider::new().with_range(min, max).expand_width())
+}
+
+fn controls() -> impl Widget<FilterSettings> {
+    Flex::column()
+        .with_child(
+            RadioGroup::column(vec![
+                ("Blur", Kernel::Blur),
+                ("Sharpen", Kernel::Sharpen),
+                ("Sobel", Kernel::Sobel),
+                ("Levels", Kernel::Levels),
+            ])
+            .lens(FilterSettings::kernel),
+        )
+        .with_spacer(8.0)
+        .with_child(slider("Amount", 0.0, 16.0).lens(FilterSettings::amount))
+        .with_child(slider("Black", 0.0, 1.0).lens(FilterSettings::black))
+        .with_child(slider("White", 0.0, 1.0).lens(FilterSettings::white))
+        .with_child(slider("Gamma", 0.1, 4.0).lens(FilterSettings::gamma))
+        .with_spacer(8.0)
+        .with_child(Button::new("Export").on_click(|ctx, _data, _env| {
+            ctx.submit_command(EXPORT_IMAGE.with("filtered.png".to_string()));
+        }))
+        .padding(8.0)
+        .fix_width(240.0)
+}
+
+pub fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: image_filter <image>");
+    let source = image::open(&path).expect("failed to open image").to_rgba8();
+    let (width, height) = source.dimensions();
+    let source = ImageBuf::from_raw(
+        source.into_raw(),
+        druid::piet::ImageFormat::RgbaSeparate,
+        width as usize,
+        height as usize,
+    );
+
+    let filter = pollster::block_on(ImageFilter::new(&source));
+    let window = WindowDesc::new(
+        Flex::row()
+            .with_flex_child(filter.center(), 1.0)
+            .with_child(controls()),
+    )
+    .title("Image filter");
+
+    AppLauncher::with_window(window)
+        .log_to_console()
+        .launch(FilterSettings::new(Kernel::Blur))
+        .expect("launch failed");
+}
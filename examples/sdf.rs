@@ -0,0 +1,130 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A raymarched CSG scene: drag to orbit, scroll to move closer.
+
+use druid::widget::prelude::*;
+use druid::widget::Controller;
+use druid::{AppLauncher, Point, WidgetExt, WindowDesc};
+
+use druid_wgpu::effects;
+use druid_wgpu::{ParamValue, Params, Playback, ViewportState, WgpuWidget};
+
+/// A rounded box with a sphere cut out of it, over a floor.
+const SCENE: &str = "
+fn map(p: vec3<f32>) -> f32 {
+    let spin = rotate_y(p, globals.time * 0.5);
+    let shape = op_smooth_subtract(
+        sd_round_box(spin, vec3<f32>(0.8), 0.1),
+        sd_sphere(spin, 1.0),
+        0.05
+    );
+    let ring = sd_torus(rotate_x(spin, 1.5708), 1.2, 0.08);
+    return op_union(op_smooth_union(shape, ring, 0.2), sd_plane(p + vec3<f32>(0.0, 1.2, 0.0), vec3<f32>(0.0, 1.0, 0.0)));
+}
+";
+
+/// Orbits the camera by editing `sdf.camera`.
+#[derive(Default)]
+struct Orbit {
+    drag_start: Option<Point>,
+}
+
+impl Orbit {
+    fn camera(params: &Params) -> (f64, f64, f64) {
+        match params.get("sdf.camera") {
+            Some(ParamValue::Vec4(yaw, pitch, distance, _)) => (*yaw, *pitch, *distance),
+            _ => (0.0, 0.3, 5.0),
+        }
+    }
+
+    fn set_camera(params: &mut Params, yaw: f64, pitch: f64, distance: f64) {
+        let pitch = pitch.clamp(-1.5, 1.5);
+        let distance = distance.clamp(1.5, 50.0);
+        params.set("sdf.camera", [yaw, pitch, distance, 0.0]);
+    }
+}
+
+impl<W: Widget<ViewportState>> Controller<ViewportState, W> for Orbit {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut ViewportState,
+        env: &Env,
+    ) {
+        let (yaw, pitch, distance) = Self::camera(&data.params);
+
+        match event {
+            Event::MouseDown(mouse) => {
+                self.drag_start = Some(mouse.pos);
+                ctx.set_active(true);
+            }
+            Event::MouseMove(mouse) => {
+                if let Some(start) = self.drag_start {
+                    let delta = mouse.pos - start;
+                    Self::set_camera(
+                        &mut data.params,
+                        yaw - delta.x * 0.01,
+                        pitch + delta.y * 0.01,
+                        distance,
+                    );
+                    self.drag_start = Some(mouse.pos);
+                }
+            }
+            Event::MouseUp(_) => {
+                self.drag_start = None;
+                ctx.set_active(false);
+            }
+            Event::Wheel(mouse) => {
+                let factor = 1.1f64.powf(mouse.wheel_delta.y / 100.0);
+                Self::set_camera(&mut data.params, yaw, pitch, distance * factor);
+                ctx.set_handled();
+            }
+            _ => (),
+        }
+
+        child.event(ctx, event, data, env);
+    }
+}
+
+pub fn main() {
+    let viewport = pollster::block_on(WgpuWidget::new())
+        .with_post_effect(effects::raymarch("sdf", SCENE))
+        .controller(Orbit::default());
+
+    let window = WindowDesc::new(viewport).title("SDF");
+
+    let mut params = Params::new()
+        .with("sdf.enabled", true)
+        .with("sdf.steps", 128i64)
+        .with(
+            "sdf.fog",
+            effects::Fog::Height {
+                density: 0.05,
+                falloff: 0.5,
+            },
+        )
+        .with("sdf.fog_color", [0.6, 0.65, 0.7, 0.0]);
+    Orbit::set_camera(&mut params, 0.6, 0.3, 5.0);
+
+    let mut playback = Playback::new(60.0);
+    playback.play();
+
+    AppLauncher::with_window(window)
+        .log_to_console()
+        .launch(ViewportState::new(playback, params))
+        .expect("launch failed");
+}
@@ -0,0 +1,46 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The smallest custom scene: a fragment shader over the whole viewport,
+//! with a druid label on top.
+
+use druid::widget::{Align, Label};
+use druid::{AppLauncher, UnitPoint, WidgetExt, WindowDesc};
+
+use druid_wgpu::{Params, Playback, ViewportState, WgpuBackdrop, WgpuWidget};
+
+const PLASMA: &str = "
+@fragment
+fn fs_main(in: SceneVertex) -> @location(0) vec4<f32> {
+    let t = globals.time;
+    let p = in.uv * 6.0;
+    let v = sin(p.x + t) + sin(p.y * 1.3 - t) + sin(length(p - 3.0) * 2.0 + t);
+    let color = 0.5 + 0.5 * cos(v + vec3<f32>(0.0, 2.1, 4.2));
+    return vec4<f32>(color, 1.0);
+}
+";
+
+pub fn main() {
+    let viewport = pollster::block_on(WgpuWidget::shader(PLASMA));
+    let time = Label::dynamic(|data: &ViewportState, _| format!("{:.1}s", data.playback.time));
+    let hud = Align::new(UnitPoint::TOP_LEFT, time.padding(8.0));
+
+    let mut playback = Playback::new(60.0);
+    playback.play();
+
+    AppLauncher::with_window(WindowDesc::new(WgpuBackdrop::new(viewport, hud)).title("Plasma"))
+        .log_to_console()
+        .launch(ViewportState::new(playback, Params::new()))
+        .expect("launch failed");
+}
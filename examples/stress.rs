@@ -0,0 +1,123 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Draws thousands of instanced triangles and prints frame rates, to see
+//! how far the readback approach scales on a given machine.
+//!
+//! Pass the starting instance count as the first argument, and change it
+//! with the slider.
+
+use std::time::{Duration, Instant};
+
+use druid::widget::prelude::*;
+use druid::widget::{Controller, Flex, Label, Slider};
+use druid::{AppLauncher, WidgetExt, WindowDesc};
+
+use druid_wgpu::{Params, Playback, ViewportState, WgpuWidget, FRAME_AVAILABLE};
+
+const MAX_INSTANCES: f64 = 100_000.0;
+
+/// How often frame stats are printed.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Counts the frames the viewport shows and prints the rate.
+struct FrameStats {
+    frames: u32,
+    since: Instant,
+}
+
+impl<W: Widget<ViewportState>> Controller<ViewportState, W> for FrameStats {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut ViewportState,
+        env: &Env,
+    ) {
+        if let Event::Notification(notification) = event {
+            if notification.is(FRAME_AVAILABLE) {
+                self.frames += 1;
+                let elapsed = self.since.elapsed();
+                if elapsed >= REPORT_INTERVAL {
+                    let seconds = elapsed.as_secs_f64();
+                    println!(
+                        "{:.0} instances: {:.1} fps, {:.2} ms per frame",
+                        data.params.float("instances").unwrap_or(1.0),
+                        self.frames as f64 / seconds,
+                        seconds * 1000.0 / self.frames as f64
+                    );
+                    self.frames = 0;
+                    self.since = Instant::now();
+                }
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+fn controls() -> impl Widget<ViewportState> {
+    Flex::column()
+        .with_child(Label::dynamic(|data: &Params, _| {
+            format!(
+                "Instances: {:.0}",
+                data.float("instances").unwrap_or_default()
+            )
+        }))
+        .with_spacer(8.0)
+        .with_child(
+            Slider::new()
+                .with_range(1.0, MAX_INSTANCES)
+                .lens(Params::float_lens("instances"))
+                .expand_width(),
+        )
+        .padding(8.0)
+        .fix_width(200.0)
+        .lens(ViewportState::params)
+}
+
+pub fn main() {
+    let instances = std::env::args()
+        .nth(1)
+        .and_then(|count| count.parse::<f64>().ok())
+        .unwrap_or(1000.0)
+        .clamp(1.0, MAX_INSTANCES);
+
+    let viewport = pollster::block_on(WgpuWidget::new())
+        .with_param_layout(["speed", "scale"])
+        .with_instance_param("instances");
+
+    let window = WindowDesc::new(
+        Flex::row()
+            .with_flex_child(viewport, 1.0)
+            .with_child(controls())
+            .controller(FrameStats {
+                frames: 0,
+                since: Instant::now(),
+            }),
+    )
+    .title("Stress test");
+
+    let params = Params::new()
+        .with("speed", 1.0)
+        .with("scale", 1.0)
+        .with("instances", instances);
+    let mut playback = Playback::new(10.0);
+    playback.play();
+
+    AppLauncher::with_window(window)
+        .log_to_console()
+        .launch(ViewportState::new(playback, params))
+        .expect("launch failed");
+}
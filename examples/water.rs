@@ -0,0 +1,132 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An ocean under a daylight sky, with sliders for the waves and the time
+//! of day.
+
+use druid::widget::{Flex, Label, Slider};
+use druid::{AppLauncher, Lens, Widget, WidgetExt, WindowDesc};
+
+use druid_wgpu::effects;
+use druid_wgpu::{ParamValue, Params, Playback, ViewportState, WgpuWidget};
+
+/// One component of the `water.waves` vector.
+struct WaveLens(usize);
+
+impl Lens<Params, f64> for WaveLens {
+    fn with<V, F: FnOnce(&f64) -> V>(&self, data: &Params, f: F) -> V {
+        f(&Self::waves(data)[self.0])
+    }
+
+    fn with_mut<V, F: FnOnce(&mut f64) -> V>(&self, data: &mut Params, f: F) -> V {
+        let mut waves = Self::waves(data);
+        let result = f(&mut waves[self.0]);
+        data.set("water.waves", waves);
+        result
+    }
+}
+
+impl WaveLens {
+    fn waves(params: &Params) -> [f64; 4] {
+        match params.get("water.waves") {
+            Some(ParamValue::Vec4(a, b, c, d)) => [*a, *b, *c, *d],
+            _ => [0.3, 8.0, 1.0, 0.5],
+        }
+    }
+}
+
+/// The time of day, which moves the sun.
+struct HourLens;
+
+impl HourLens {
+    fn set(params: &mut Params, hour: f64) {
+        params.set("hour", hour);
+        params.set("sky.sun", effects::sun_position(hour, 3.0));
+    }
+}
+
+impl Lens<Params, f64> for HourLens {
+    fn with<V, F: FnOnce(&f64) -> V>(&self, data: &Params, f: F) -> V {
+        f(&data.float("hour").unwrap_or(12.0))
+    }
+
+    fn with_mut<V, F: FnOnce(&mut f64) -> V>(&self, data: &mut Params, f: F) -> V {
+        let mut hour = data.float("hour").unwrap_or(12.0);
+        let result = f(&mut hour);
+        Self::set(data, hour);
+        result
+    }
+}
+
+fn wave_slider(name: &'static str, index: usize, max: f64) -> impl Widget<Params> {
+    Flex::column().with_child(Label::new(name)).with_child(
+        Slider::new()
+            .with_range(0.0, max)
+            .lens(WaveLens(index))
+            .expand_width(),
+    )
+}
+
+fn controls() -> impl Widget<ViewportState> {
+    Flex::column()
+        .with_child(Label::new("Time of day"))
+        .with_child(
+            Slider::new()
+                .with_range(5.0, 19.0)
+                .lens(HourLens)
+                .expand_width(),
+        )
+        .with_spacer(16.0)
+        .with_child(wave_slider("Amplitude", 0, 1.0))
+        .with_spacer(8.0)
+        .with_child(wave_slider("Wavelength", 1, 20.0))
+        .with_spacer(8.0)
+        .with_child(wave_slider("Speed", 2, 3.0))
+        .with_spacer(8.0)
+        .with_child(wave_slider("Steepness", 3, 1.0))
+        .padding(8.0)
+        .fix_width(200.0)
+        .lens(ViewportState::params)
+}
+
+pub fn main() {
+    let viewport = pollster::block_on(WgpuWidget::new())
+        .with_post_effect(effects::sky())
+        .with_post_effect(effects::water());
+
+    let window = WindowDesc::new(
+        Flex::row()
+            .with_flex_child(viewport, 1.0)
+            .with_child(controls()),
+    )
+    .title("Water");
+
+    // The sky looks the way the water's camera does, pitched up instead
+    // of down.
+    let mut params = Params::new()
+        .with("sky.enabled", true)
+        .with("sky.camera", [0.0, -0.15, 0.0, 0.0])
+        .with("water.enabled", true)
+        .with("water.camera", [0.0, 0.15, 2.0, 0.0])
+        .with("water.waves", [0.3, 8.0, 1.0, 0.5])
+        .with("water.color", [0.02, 0.08, 0.12, 0.0]);
+    HourLens::set(&mut params, 9.0);
+    let mut playback = Playback::new(60.0);
+    playback.play();
+
+    AppLauncher::with_window(window)
+        .log_to_console()
+        .launch(ViewportState::new(playback, params))
+        .expect("launch failed");
+}
@@ -0,0 +1,211 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Progressive rendering by averaging frames.
+//!
+//! Content that varies per sample, noise seeded with `globals.sample` for
+//! instance, converges to its average over repaints. Anything that changes
+//! what's on screen resets the average.
+
+use wgpu::util::DeviceExt;
+
+use crate::compat;
+use crate::gpu::{Gpu, SCENE_FORMAT};
+use crate::history::History;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct AccumulateUniforms {
+    weight: f32,
+    _padding: [f32; 3],
+}
+
+pub(crate) struct Accumulator {
+    history: History,
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    buffer: wgpu::Buffer,
+    /// Samples in the average so far.
+    samples: u32,
+    max_samples: u32,
+}
+
+impl Accumulator {
+    pub(crate) fn new(gpu: &Gpu, max_samples: u32) -> Self {
+        let device = &gpu.device;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Accumulate Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("accumulate.wgsl").into()),
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Accumulate Bind Group Layout"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Accumulate Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Accumulate Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: SCENE_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Accumulate Buffer"),
+            contents: bytemuck::bytes_of(&AccumulateUniforms {
+                weight: 1.0,
+                _padding: [0.0; 3],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        Self {
+            history: History::new("Accumulation", SCENE_FORMAT),
+            pipeline,
+            layout,
+            buffer,
+            samples: 0,
+            max_samples: max_samples.max(1),
+        }
+    }
+
+    /// The index of the sample the next frame adds, for seeding per-sample
+    /// variation.
+    pub(crate) fn sample(&self) -> u32 {
+        self.samples
+    }
+
+    pub(crate) fn max_samples(&self) -> u32 {
+        self.max_samples
+    }
+
+    /// Whether more repaints would still change the average.
+    pub(crate) fn converging(&self) -> bool {
+        self.samples < self.max_samples
+    }
+
+    /// Start over from the next frame.
+    pub(crate) fn reset(&mut self) {
+        self.samples = 0;
+    }
+
+    /// Fold `frame` into the average, returning the texture holding it.
+    pub(crate) fn encode(
+        &mut self,
+        gpu: &Gpu,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &wgpu::Texture,
+        size: wgpu::Extent3d,
+    ) -> &wgpu::Texture {
+        if !self.history.begin_frame(&gpu.device, size) {
+            self.samples = 0;
+        }
+
+        let weight = if self.converging() {
+            self.samples += 1;
+            1.0 / self.samples as f32
+        } else {
+            0.0
+        };
+        let uniforms = AccumulateUniforms {
+            weight,
+            _padding: [0.0; 3],
+        };
+        gpu.queue
+            .write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniforms));
+
+        let frame = frame.create_view(&Default::default());
+        let previous = self.history.previous().create_view(&Default::default());
+        let output = self.history.current().create_view(&Default::default());
+        let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Accumulate Bind Group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&frame),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&previous),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Accumulate Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &output,
+                    resolve_target: None,
+                    ops: compat::clear(wgpu::Color::TRANSPARENT),
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        self.history.current()
+    }
+}
@@ -0,0 +1,28 @@
+// Running average of frames for progressive rendering.
+
+struct Accumulate {
+    // 1 / n for the nth sample, or 0 once enough have been taken.
+    weight: f32,
+};
+
+@group(0) @binding(0)
+var current: texture_2d<f32>;
+
+@group(0) @binding(1)
+var average: texture_2d<f32>;
+
+@group(0) @binding(2)
+var<uniform> accumulate: Accumulate;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // A single triangle covering the whole target.
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(position.xy);
+    return mix(textureLoad(average, texel, 0), textureLoad(current, texel, 0), accumulate.weight);
+}
@@ -0,0 +1,235 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Spectrum and waveform data for audio-reactive shaders.
+//!
+//! [`AudioFrame::analyze`] works on any mono sample window. With the `audio`
+//! feature, [`AudioInput`] captures the default input device, and a widget
+//! given one uploads a fresh frame every paint.
+
+use std::f32::consts::PI;
+
+/// Number of spectrum bands and waveform samples handed to the shader.
+pub const AUDIO_BANDS: usize = 32;
+
+/// Samples analyzed per frame.
+pub const FFT_SIZE: usize = 1024;
+
+/// Quietest level that still shows up in the spectrum, in decibels.
+const MIN_DB: f32 = -60.0;
+
+/// One frame of audio analysis, see `Audio` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AudioFrame {
+    /// Log-spaced band levels in `[0, 1]`, lowest frequency first.
+    pub spectrum: [f32; AUDIO_BANDS],
+    /// The sample window decimated to `AUDIO_BANDS` points in `[-1, 1]`.
+    pub waveform: [f32; AUDIO_BANDS],
+}
+
+impl Default for AudioFrame {
+    fn default() -> Self {
+        Self {
+            spectrum: [0.0; AUDIO_BANDS],
+            waveform: [0.0; AUDIO_BANDS],
+        }
+    }
+}
+
+impl AudioFrame {
+    /// Analyze the most recent `FFT_SIZE` mono samples; shorter windows are
+    /// zero padded.
+    pub fn analyze(samples: &[f32]) -> Self {
+        let samples = &samples[samples.len().saturating_sub(FFT_SIZE)..];
+
+        let mut re = [0.0f32; FFT_SIZE];
+        let mut im = [0.0f32; FFT_SIZE];
+        for (i, sample) in samples.iter().enumerate() {
+            // Hann window to keep leakage between bands down.
+            let window = 0.5 - 0.5 * (2.0 * PI * i as f32 / (FFT_SIZE - 1) as f32).cos();
+            re[i] = sample * window;
+        }
+        fft(&mut re, &mut im);
+
+        let mut frame = Self::default();
+
+        // Band edges are spaced logarithmically over the bins below Nyquist,
+        // skipping the DC bin.
+        let bins = FFT_SIZE / 2;
+        let mut start = 1;
+        for (band, level) in frame.spectrum.iter_mut().enumerate() {
+            let t = (band + 1) as f32 / AUDIO_BANDS as f32;
+            let end = ((bins as f32).powf(t) as usize).clamp(start + 1, bins);
+
+            let power = (start..end)
+                .map(|bin| re[bin] * re[bin] + im[bin] * im[bin])
+                .sum::<f32>()
+                / (end - start) as f32;
+            let magnitude = power.sqrt() / (FFT_SIZE as f32 / 4.0);
+            let db = 20.0 * magnitude.max(1e-6).log10();
+            *level = ((db - MIN_DB) / -MIN_DB).clamp(0.0, 1.0);
+
+            start = end.min(bins - 1);
+        }
+
+        if !samples.is_empty() {
+            for (i, point) in frame.waveform.iter_mut().enumerate() {
+                *point = samples[i * samples.len() / AUDIO_BANDS].clamp(-1.0, 1.0);
+            }
+        }
+
+        frame
+    }
+}
+
+/// In-place iterative radix-2 FFT; the length must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_im, w_re) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+#[cfg(feature = "audio")]
+pub use capture::AudioInput;
+
+#[cfg(feature = "audio")]
+mod capture {
+    use std::collections::VecDeque;
+    use std::error::Error;
+    use std::sync::{Arc, Mutex};
+
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    use super::{AudioFrame, FFT_SIZE};
+
+    type SampleWindow = Arc<Mutex<VecDeque<f32>>>;
+
+    /// Captures the default audio input device.
+    ///
+    /// The stream stops when this is dropped.
+    pub struct AudioInput {
+        _stream: cpal::Stream,
+        samples: SampleWindow,
+    }
+
+    impl AudioInput {
+        pub fn default_input() -> Result<Self, Box<dyn Error>> {
+            let device = cpal::default_host()
+                .default_input_device()
+                .ok_or("no audio input device")?;
+            let config = device.default_input_config()?;
+            let stream_config = config.config();
+            let channels = stream_config.channels as usize;
+
+            let samples: SampleWindow = Arc::new(Mutex::new(VecDeque::with_capacity(FFT_SIZE)));
+            let err_fn = |err| eprintln!("Audio input error: {}", err);
+
+            let stream = match config.sample_format() {
+                cpal::SampleFormat::F32 => {
+                    let samples = samples.clone();
+                    device.build_input_stream(
+                        &stream_config,
+                        move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                            push_samples(&samples, data, channels, |s| s)
+                        },
+                        err_fn,
+                    )?
+                }
+                cpal::SampleFormat::I16 => {
+                    let samples = samples.clone();
+                    device.build_input_stream(
+                        &stream_config,
+                        move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                            push_samples(&samples, data, channels, |s| s as f32 / i16::MAX as f32)
+                        },
+                        err_fn,
+                    )?
+                }
+                cpal::SampleFormat::U16 => {
+                    let samples = samples.clone();
+                    device.build_input_stream(
+                        &stream_config,
+                        move |data: &[u16], _: &cpal::InputCallbackInfo| {
+                            push_samples(&samples, data, channels, |s| {
+                                s as f32 / u16::MAX as f32 * 2.0 - 1.0
+                            })
+                        },
+                        err_fn,
+                    )?
+                }
+            };
+            stream.play()?;
+
+            Ok(Self {
+                _stream: stream,
+                samples,
+            })
+        }
+
+        /// Analyze the latest captured samples.
+        pub fn frame(&self) -> AudioFrame {
+            let mut samples = self.samples.lock().unwrap();
+            AudioFrame::analyze(samples.make_contiguous())
+        }
+    }
+
+    /// Downmix interleaved `data` to mono and append it to the window.
+    fn push_samples<S: Copy>(
+        samples: &SampleWindow,
+        data: &[S],
+        channels: usize,
+        to_f32: impl Fn(S) -> f32,
+    ) {
+        let mut samples = samples.lock().unwrap();
+        for frame in data.chunks(channels) {
+            let mono = frame.iter().map(|&s| to_f32(s)).sum::<f32>() / channels as f32;
+            if samples.len() == FFT_SIZE {
+                samples.pop_front();
+            }
+            samples.push_back(mono);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GPU rendering behind ordinary druid widgets.
+//!
+//! [`WgpuBackdrop`] lays a child widget over a viewport, both filling the
+//! same box, for HUD-style UIs: labels, buttons and sliders drawn by druid
+//! on top of the wgpu content, in the same part of the widget tree. Events
+//! go to the child first, and only reach the viewport when the child
+//! leaves them unhandled, so clicks on a button don't also orbit a camera.
+
+use druid::widget::prelude::*;
+use druid::{Point, WidgetPod};
+
+use crate::{ViewportState, WgpuWidget};
+
+pub struct WgpuBackdrop<W> {
+    viewport: WidgetPod<ViewportState, WgpuWidget>,
+    child: WidgetPod<ViewportState, W>,
+}
+
+impl<W: Widget<ViewportState>> WgpuBackdrop<W> {
+    /// Draw `child` over `viewport`.
+    pub fn new(viewport: WgpuWidget, child: W) -> Self {
+        Self {
+            viewport: WidgetPod::new(viewport),
+            child: WidgetPod::new(child),
+        }
+    }
+}
+
+impl<W: Widget<ViewportState>> Widget<ViewportState> for WgpuBackdrop<W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut ViewportState, env: &Env) {
+        self.child.event(ctx, event, data, env);
+        if !ctx.is_handled() {
+            self.viewport.event(ctx, event, data, env);
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &ViewportState,
+        env: &Env,
+    ) {
+        self.viewport.lifecycle(ctx, event, data, env);
+        self.child.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, _: &ViewportState, data: &ViewportState, env: &Env) {
+        self.viewport.update(ctx, data, env);
+        self.child.update(ctx, data, env);
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &ViewportState,
+        env: &Env,
+    ) -> Size {
+        let size = self.viewport.layout(ctx, bc, data, env);
+        self.viewport.set_origin(ctx, data, env, Point::ORIGIN);
+
+        // Loose, so a child wrapped in `Align` can sit anywhere over the
+        // viewport.
+        self.child
+            .layout(ctx, &BoxConstraints::tight(size).loosen(), data, env);
+        self.child.set_origin(ctx, data, env, Point::ORIGIN);
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &ViewportState, env: &Env) {
+        self.viewport.paint(ctx, data, env);
+        self.child.paint(ctx, data, env);
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Entry points for the benchmarks in `benches/`, which can't reach the
+//! widget's GPU state otherwise. Not part of the public API.
+
+use std::time::Duration;
+
+use crate::compat;
+use crate::gpu::{self, Gpu};
+use crate::targets::RenderTargets;
+use crate::{GpuError, GpuOptions};
+
+/// The widget's readback path for one frame size: the same targets, the
+/// same present pass into the same output buffer, mapped the same way.
+pub struct FrameReadback {
+    gpu: Gpu,
+    targets: RenderTargets,
+    width: u32,
+    height: u32,
+    padded_width: u32,
+    padded_height: u32,
+    timeout: Duration,
+}
+
+impl FrameReadback {
+    pub fn new(width: u32, height: u32) -> Result<Self, GpuError> {
+        let options = GpuOptions::default();
+        let mut gpu = pollster::block_on(Gpu::try_new(&options))?;
+        let (padded_width, padded_height) = gpu::padded_output_size(width, height);
+        gpu.resize_output_buffer(padded_width, padded_height);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let targets = RenderTargets::new(&gpu.device, "Bench", size, false);
+
+        Ok(Self {
+            gpu,
+            targets,
+            width,
+            height,
+            padded_width,
+            padded_height,
+            timeout: options.frame_timeout,
+        })
+    }
+
+    /// Clear the scene, present it and copy the output to the buffer, as
+    /// paint does after the scene is drawn.
+    pub fn encode(&self) -> wgpu::CommandBuffer {
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Bench Encoder"),
+            });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Bench Scene Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.targets.scene_view,
+                resolve_target: None,
+                ops: compat::clear(wgpu::Color::BLUE),
+            })],
+            depth_stencil_attachment: None,
+        });
+        self.gpu.encode_present(
+            &mut encoder,
+            &self.targets.scene_view,
+            &self.targets.output_view,
+        );
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.targets.output,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.gpu.output_buffer,
+                layout: compat::image_layout(4 * self.padded_width, self.padded_height),
+            },
+            self.targets.size,
+        );
+        encoder.finish()
+    }
+
+    /// Submit `commands` and map the output buffer with `gpu::map_read`.
+    /// Panics if the readback fails.
+    pub fn submit_and_map(&self, commands: wgpu::CommandBuffer) {
+        self.gpu.queue.submit(Some(commands));
+        if let Err(err) = gpu::map_read(&self.gpu.device, &self.gpu.output_buffer, self.timeout) {
+            panic!("readback failed: {:?}", err);
+        }
+    }
+
+    pub fn unmap(&self) {
+        self.gpu.output_buffer.unmap();
+    }
+
+    /// The mapped frame without row padding, as paint copies it.
+    pub fn copy_rows(&self) -> Vec<u8> {
+        let data = self.gpu.output_buffer.slice(..).get_mapped_range();
+        let row_size = (4 * self.width) as usize;
+        let padded_row_size = (4 * self.padded_width) as usize;
+        let mut pixels = Vec::with_capacity(row_size * self.height as usize);
+        for row in gpu::unpadded_rows(&data, row_size, padded_row_size, self.height) {
+            pixels.extend_from_slice(row);
+        }
+        pixels
+    }
+
+    /// Render and read back one frame.
+    pub fn pixels(&self) -> Vec<u8> {
+        self.submit_and_map(self.encode());
+        let pixels = self.copy_rows();
+        self.unmap();
+        pixels
+    }
+}
@@ -0,0 +1,36 @@
+// Separable Gaussian blur with linear-sampling taps.
+//
+// Each tap lands between two texels and lets bilinear filtering weigh them,
+// so a blur of radius r takes about r + 1 samples per direction instead of
+// 2r + 1. The sampler has to filter linearly.
+
+fn gaussian(x: f32, sigma: f32) -> f32 {
+    return exp(-(x * x) / (2.0 * sigma * sigma));
+}
+
+// Blur `tex` at `uv` over `radius` texels along `texel_step`, one texel in
+// the blur direction in uv units. Run once in each direction.
+fn blur_linear(
+    tex: texture_2d<f32>,
+    tex_sampler: sampler,
+    uv: vec2<f32>,
+    texel_step: vec2<f32>,
+    radius: f32
+) -> vec4<f32> {
+    let sigma = max(radius, 1.0) / 2.0;
+    let taps = i32(ceil(clamp(radius, 0.0, 64.0)));
+
+    var sum = textureSampleLevel(tex, tex_sampler, uv, 0.0);
+    var total = 1.0;
+    for (var i = 1; i <= taps; i = i + 2) {
+        let near = gaussian(f32(i), sigma);
+        let far = gaussian(f32(i + 1), sigma);
+        let weight = near + far;
+        let offset = texel_step * (f32(i) * near + f32(i + 1) * far) / weight;
+
+        sum = sum + textureSampleLevel(tex, tex_sampler, uv + offset, 0.0) * weight;
+        sum = sum + textureSampleLevel(tex, tex_sampler, uv - offset, 0.0) * weight;
+        total = total + 2.0 * weight;
+    }
+    return sum / total;
+}
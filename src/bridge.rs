@@ -0,0 +1,655 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bridges from external controllers to viewport parameters.
+//!
+//! Incoming MIDI control changes (`midi` feature), OSC messages (`osc`
+//! feature) and HTTP requests (`http` feature) are turned into
+//! [`SET_PARAMETER`] commands submitted through an [`ExtEventSink`], so they
+//! reach the widget on the UI thread like any other command.
+
+use druid::Selector;
+#[cfg(any(feature = "midi", feature = "osc", feature = "http"))]
+use druid::{ExtEventSink, Target};
+
+use crate::ParamValue;
+
+/// Set a named parameter to a new value, converted to the type the
+/// parameter already has, see `Params::set_coerced`. The reserved name
+/// [`PLAYBACK_TIME`] seeks playback instead.
+pub const SET_PARAMETER: Selector<ParameterChange> = Selector::new("druid-wgpu.set-parameter");
+
+/// The parameter name that sets `Playback::time` rather than a parameter,
+/// so controllers can scrub the timeline.
+pub const PLAYBACK_TIME: &str = "playback.time";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterChange {
+    pub name: String,
+    pub value: ParamValue,
+}
+
+#[cfg(any(feature = "midi", feature = "osc", feature = "http"))]
+fn submit(sink: &ExtEventSink, name: String, value: ParamValue) {
+    let change = ParameterChange { name, value };
+    if sink
+        .submit_command(SET_PARAMETER, change, Target::Auto)
+        .is_err()
+    {
+        eprintln!("Dropped parameter change, the app has exited");
+    }
+}
+
+#[cfg(feature = "midi")]
+pub use midi::{MidiBridge, MidiMapping};
+
+#[cfg(feature = "midi")]
+mod midi {
+    use std::error::Error;
+
+    use druid::ExtEventSink;
+    use midir::{MidiInput, MidiInputConnection};
+
+    /// Maps one MIDI control change to a parameter.
+    #[derive(Clone, Debug)]
+    pub struct MidiMapping {
+        /// MIDI channel, 0-15.
+        pub channel: u8,
+        pub controller: u8,
+        pub name: String,
+        /// The parameter values a controller value of 0 and 127 map to.
+        pub range: (f64, f64),
+    }
+
+    /// Listens for control changes on a MIDI input port.
+    ///
+    /// The port is closed when this is dropped.
+    pub struct MidiBridge {
+        _connection: MidiInputConnection<()>,
+    }
+
+    impl MidiBridge {
+        /// Connect to the first input port whose name contains `port_name`,
+        /// or the first port at all when it's empty.
+        pub fn connect(
+            port_name: &str,
+            mappings: Vec<MidiMapping>,
+            sink: ExtEventSink,
+        ) -> Result<Self, Box<dyn Error>> {
+            let input = MidiInput::new("druid-wgpu")?;
+            let port = input
+                .ports()
+                .into_iter()
+                .find(|port| {
+                    input
+                        .port_name(port)
+                        .map(|name| name.contains(port_name))
+                        .unwrap_or(false)
+                })
+                .ok_or("no matching MIDI input port")?;
+
+            let connection = input
+                .connect(
+                    &port,
+                    "druid-wgpu-input",
+                    move |_timestamp, message, _| {
+                        // Control change: status 0xBn, controller, value.
+                        if let &[status, controller, value] = message {
+                            if status & 0xF0 != 0xB0 {
+                                return;
+                            }
+                            let channel = status & 0x0F;
+                            for mapping in &mappings {
+                                if mapping.channel == channel && mapping.controller == controller {
+                                    let (min, max) = mapping.range;
+                                    let t = value as f64 / 127.0;
+                                    super::submit(
+                                        &sink,
+                                        mapping.name.clone(),
+                                        (min + (max - min) * t).into(),
+                                    );
+                                }
+                            }
+                        }
+                    },
+                    (),
+                )
+                .map_err(|err| err.to_string())?;
+
+            Ok(Self {
+                _connection: connection,
+            })
+        }
+    }
+}
+
+#[cfg(feature = "osc")]
+pub use osc::OscBridge;
+
+#[cfg(feature = "osc")]
+mod osc {
+    use std::io;
+    use std::net::{ToSocketAddrs, UdpSocket};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    use druid::ExtEventSink;
+
+    use crate::ParamValue;
+
+    /// How often the listener thread checks whether it should stop.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// Listens for OSC messages over UDP.
+    ///
+    /// A message to `/name` sets parameter `name` to its argument: a
+    /// single `f`, `d` or `i` number, `T` or `F` for a boolean, or `ffff`
+    /// for a vector. Bundles are unpacked. The listener stops when this is dropped.
+    pub struct OscBridge {
+        running: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl OscBridge {
+        pub fn listen(addr: impl ToSocketAddrs, sink: ExtEventSink) -> io::Result<Self> {
+            let socket = UdpSocket::bind(addr)?;
+            socket.set_read_timeout(Some(POLL_INTERVAL))?;
+
+            let running = Arc::new(AtomicBool::new(true));
+            let thread = {
+                let running = running.clone();
+                std::thread::spawn(move || {
+                    let mut packet = [0u8; 1536];
+                    while running.load(Ordering::Relaxed) {
+                        match socket.recv(&mut packet) {
+                            Ok(len) => handle_packet(&packet[..len], &sink),
+                            Err(err)
+                                if err.kind() == io::ErrorKind::WouldBlock
+                                    || err.kind() == io::ErrorKind::TimedOut => {}
+                            Err(err) => {
+                                eprintln!("OSC listener stopped: {}", err);
+                                break;
+                            }
+                        }
+                    }
+                })
+            };
+
+            Ok(Self {
+                running,
+                thread: Some(thread),
+            })
+        }
+    }
+
+    impl Drop for OscBridge {
+        fn drop(&mut self) {
+            self.running.store(false, Ordering::Relaxed);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    fn handle_packet(packet: &[u8], sink: &ExtEventSink) {
+        if let Some(elements) = packet.strip_prefix(b"#bundle\0") {
+            // Skip the time tag, then each element is a size-prefixed packet.
+            let mut rest = elements.get(8..).unwrap_or_default();
+            while let Some((size, tail)) = read_i32(rest) {
+                let size = size as usize;
+                if size > tail.len() {
+                    return;
+                }
+                handle_packet(&tail[..size], sink);
+                rest = &tail[size..];
+            }
+        } else if let Some((name, value)) = parse_message(packet) {
+            super::submit(sink, name, value);
+        }
+    }
+
+    /// Parse a message with the arguments `OscBridge` accepts.
+    fn parse_message(packet: &[u8]) -> Option<(String, ParamValue)> {
+        let (address, rest) = read_string(packet)?;
+        let (tags, args) = read_string(rest)?;
+        let name = address.strip_prefix('/')?.to_string();
+
+        let value = match tags {
+            ",f" => ParamValue::Float(read_f32(args)?.0 as f64),
+            ",d" => ParamValue::Float(f64::from_be_bytes(args.get(..8)?.try_into().ok()?)),
+            ",i" => ParamValue::Int(read_i32(args)?.0 as i64),
+            ",T" => ParamValue::Bool(true),
+            ",F" => ParamValue::Bool(false),
+            ",ffff" => {
+                let (x, args) = read_f32(args)?;
+                let (y, args) = read_f32(args)?;
+                let (z, args) = read_f32(args)?;
+                let (w, _) = read_f32(args)?;
+                ParamValue::Vec4(x as f64, y as f64, z as f64, w as f64)
+            }
+            _ => return None,
+        };
+        Some((name, value))
+    }
+
+    fn read_f32(data: &[u8]) -> Option<(f32, &[u8])> {
+        let bytes = data.get(..4)?.try_into().ok()?;
+        Some((f32::from_be_bytes(bytes), &data[4..]))
+    }
+
+    /// Read a null-terminated string padded to four bytes.
+    fn read_string(data: &[u8]) -> Option<(&str, &[u8])> {
+        let end = data.iter().position(|&b| b == 0)?;
+        let string = std::str::from_utf8(&data[..end]).ok()?;
+        let padded = (end + 4) & !3;
+        Some((string, data.get(padded..)?))
+    }
+
+    fn read_i32(data: &[u8]) -> Option<(i32, &[u8])> {
+        let bytes = data.get(..4)?.try_into().ok()?;
+        Some((i32::from_be_bytes(bytes), &data[4..]))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// An OSC message to `address` with `tags` and the encoded `args`.
+        fn message(address: &str, tags: &str, args: &[u8]) -> Vec<u8> {
+            let mut packet = Vec::new();
+            for string in [address, tags] {
+                packet.extend_from_slice(string.as_bytes());
+                packet.resize((packet.len() + 4) & !3, 0);
+            }
+            packet.extend_from_slice(args);
+            packet
+        }
+
+        #[test]
+        fn parses_each_argument_type() {
+            let parse = |tags, args: &[u8]| parse_message(&message("/speed", tags, args));
+            let speed = |value| Some(("speed".to_string(), value));
+
+            assert_eq!(
+                parse(",f", &1.5_f32.to_be_bytes()),
+                speed(ParamValue::Float(1.5))
+            );
+            assert_eq!(
+                parse(",d", &0.25_f64.to_be_bytes()),
+                speed(ParamValue::Float(0.25))
+            );
+            assert_eq!(
+                parse(",i", &(-3_i32).to_be_bytes()),
+                speed(ParamValue::Int(-3))
+            );
+            assert_eq!(parse(",T", &[]), speed(ParamValue::Bool(true)));
+            assert_eq!(parse(",F", &[]), speed(ParamValue::Bool(false)));
+
+            let args: Vec<u8> = [1.0_f32, 0.5, 0.25, 0.0]
+                .iter()
+                .flat_map(|value| value.to_be_bytes())
+                .collect();
+            assert_eq!(
+                parse(",ffff", &args),
+                speed(ParamValue::Vec4(1.0, 0.5, 0.25, 0.0))
+            );
+        }
+
+        #[test]
+        fn rejects_malformed_messages() {
+            // Unsupported tags, missing arguments and addresses without a slash.
+            assert_eq!(
+                parse_message(&message("/speed", ",s", b"fast\0\0\0\0")),
+                None
+            );
+            assert_eq!(parse_message(&message("/speed", ",f", &[0, 0])), None);
+            assert_eq!(parse_message(&message("/tint", ",ffff", &[0; 12])), None);
+            assert_eq!(parse_message(&message("speed", ",T", &[])), None);
+            assert_eq!(parse_message(b"/speed"), None);
+        }
+
+        #[test]
+        fn reads_padded_strings() {
+            let (string, rest) = read_string(b"/abc\0\0\0\0,f\0\0").unwrap();
+            assert_eq!(string, "/abc");
+            assert_eq!(rest, b",f\0\0");
+            // Four characters still need a terminator and its padding.
+            assert_eq!(read_string(b"/abc"), None);
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+pub use http::HttpBridge;
+
+#[cfg(feature = "http")]
+mod http {
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    use druid::ExtEventSink;
+
+    use crate::{ParamValue, Params};
+
+    /// How often the listener thread checks whether it should stop.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// Largest request body accepted.
+    const MAX_BODY: usize = 1024;
+
+    /// Serves the parameter registry over HTTP, for control surfaces and
+    /// scripted tests.
+    ///
+    /// - `GET /params` answers with every parameter, as a RON preset.
+    /// - `GET /params/name` answers with one parameter's value, written as
+    ///   `ParamValue::parse` reads it.
+    /// - `PUT /params/name` or `POST /params/name`, with a value as the
+    ///   body, sets it: `true` or `false`, a number, or four numbers for a
+    ///   vector.
+    ///
+    /// Names are percent-decoded. [`PLAYBACK_TIME`] reads and seeks the
+    /// playback time, which isn't part of `GET /params`. Each connection is
+    /// served on its own thread.
+    ///
+    /// [`PLAYBACK_TIME`]: super::PLAYBACK_TIME
+    /// Give it to `WgpuWidget::with_http_bridge`, which hands it the app's
+    /// event sink and keeps what requests read up to date. Until then,
+    /// reads see no parameters and sets fail. The listener stops when this
+    /// is dropped.
+    pub struct HttpBridge {
+        running: Arc<AtomicBool>,
+        shared: Arc<Mutex<Shared>>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    #[derive(Default)]
+    struct Shared {
+        params: Params,
+        /// `Playback::time`, for reads of `PLAYBACK_TIME`.
+        time: f64,
+        sink: Option<ExtEventSink>,
+    }
+
+    impl HttpBridge {
+        pub fn listen(addr: impl ToSocketAddrs) -> io::Result<Self> {
+            let listener = TcpListener::bind(addr)?;
+            listener.set_nonblocking(true)?;
+
+            let running = Arc::new(AtomicBool::new(true));
+            let shared = Arc::new(Mutex::new(Shared::default()));
+            let thread = {
+                let running = running.clone();
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    while running.load(Ordering::Relaxed) {
+                        match listener.accept() {
+                            Ok((stream, _)) => {
+                                // A client that stalls only holds up itself.
+                                let shared = shared.clone();
+                                std::thread::spawn(move || {
+                                    if let Err(err) = handle_connection(stream, &shared) {
+                                        eprintln!("HTTP request failed: {}", err);
+                                    }
+                                });
+                            }
+                            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                                std::thread::sleep(POLL_INTERVAL);
+                            }
+                            Err(err) => {
+                                eprintln!("HTTP listener stopped: {}", err);
+                                break;
+                            }
+                        }
+                    }
+                })
+            };
+
+            Ok(Self {
+                running,
+                shared,
+                thread: Some(thread),
+            })
+        }
+
+        /// Send parameter changes through `sink`.
+        pub(crate) fn attach(&self, sink: ExtEventSink) {
+            self.shared.lock().unwrap().sink = Some(sink);
+        }
+
+        /// Make `params` what requests read.
+        pub(crate) fn publish(&self, params: &Params) {
+            self.shared.lock().unwrap().params = params.clone();
+        }
+
+        /// Make `time` what reads of `PLAYBACK_TIME` see.
+        pub(crate) fn publish_time(&self, time: f64) {
+            self.shared.lock().unwrap().time = time;
+        }
+    }
+
+    impl Drop for HttpBridge {
+        fn drop(&mut self) {
+            self.running.store(false, Ordering::Relaxed);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    fn handle_connection(stream: TcpStream, shared: &Mutex<Shared>) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        let mut body = vec![0; content_length.min(MAX_BODY)];
+        reader.read_exact(&mut body)?;
+
+        let mut words = request_line.split_whitespace();
+        let method = words.next().unwrap_or_default();
+        let path = words.next().unwrap_or_default();
+        let (status, response) = respond(method, path, &body, &shared.lock().unwrap());
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.0 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            response.len(),
+            response
+        )?;
+        stream.flush()
+    }
+
+    fn respond(method: &str, path: &str, body: &[u8], shared: &Shared) -> (&'static str, String) {
+        let name = match path.strip_prefix("/params") {
+            Some("") | Some("/") => None,
+            Some(rest) => match rest.strip_prefix('/') {
+                Some(name) => match percent_decode(name) {
+                    Some(name) => Some(name),
+                    None => return ("400 Bad Request", "malformed name\n".into()),
+                },
+                None => return ("404 Not Found", "not found\n".into()),
+            },
+            None => return ("404 Not Found", "not found\n".into()),
+        };
+
+        match (method, name.as_deref()) {
+            ("GET", None) => match shared.params.to_ron() {
+                Ok(ron) => ("200 OK", ron),
+                Err(err) => ("500 Internal Server Error", format!("{}\n", err)),
+            },
+            ("GET", Some(super::PLAYBACK_TIME)) => {
+                ("200 OK", format!("{}\n", ParamValue::Float(shared.time)))
+            }
+            ("GET", Some(name)) => match shared.params.get(name) {
+                Some(value) => ("200 OK", format!("{}\n", value)),
+                None => ("404 Not Found", format!("no parameter {}\n", name)),
+            },
+            ("PUT", Some(name)) | ("POST", Some(name)) => {
+                let value = std::str::from_utf8(body).ok().and_then(ParamValue::parse);
+                match (value, &shared.sink) {
+                    (Some(value), Some(sink)) => {
+                        let response = format!("{}\n", value);
+                        super::submit(sink, name.to_string(), value);
+                        ("200 OK", response)
+                    }
+                    (Some(_), None) => (
+                        "503 Service Unavailable",
+                        "not attached to a widget\n".into(),
+                    ),
+                    (None, _) => ("400 Bad Request", "expected a value\n".into()),
+                }
+            }
+            _ => ("405 Method Not Allowed", "method not allowed\n".into()),
+        }
+    }
+
+    /// Undo the percent-encoding of a path segment, or `None` when an escape
+    /// is cut short or the result isn't UTF-8.
+    fn percent_decode(segment: &str) -> Option<String> {
+        let mut bytes = Vec::with_capacity(segment.len());
+        let mut rest = segment.as_bytes();
+        while let Some((&byte, tail)) = rest.split_first() {
+            if byte == b'%' {
+                let hex = tail
+                    .get(..2)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+                bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                rest = &tail[2..];
+            } else {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+        String::from_utf8(bytes).ok()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn shared() -> Shared {
+            Shared {
+                params: Params::new().with("speed", 1.5).with("grain.amount", 0.05),
+                time: 2.5,
+                sink: None,
+            }
+        }
+
+        #[test]
+        fn gets_every_parameter() {
+            let shared = shared();
+            assert_eq!(
+                respond("GET", "/params", b"", &shared),
+                ("200 OK", shared.params.to_ron().unwrap())
+            );
+            assert_eq!(respond("GET", "/params/", b"", &shared).0, "200 OK");
+        }
+
+        #[test]
+        fn gets_one_parameter() {
+            let shared = shared();
+            assert_eq!(
+                respond("GET", "/params/speed", b"", &shared),
+                ("200 OK", "1.5\n".into())
+            );
+            assert_eq!(
+                respond("GET", "/params/grain%2Eamount", b"", &shared),
+                ("200 OK", "0.05\n".into())
+            );
+            assert_eq!(
+                respond("GET", "/params/scale", b"", &shared).0,
+                "404 Not Found"
+            );
+            assert_eq!(respond("GET", "/other", b"", &shared).0, "404 Not Found");
+        }
+
+        #[test]
+        fn gets_the_playback_time() {
+            assert_eq!(
+                respond("GET", "/params/playback.time", b"", &shared()),
+                ("200 OK", "2.5\n".into())
+            );
+        }
+
+        #[test]
+        fn rejects_bad_values_and_names() {
+            let shared = shared();
+            assert_eq!(
+                respond("PUT", "/params/speed", b"fast", &shared).0,
+                "400 Bad Request"
+            );
+            assert_eq!(
+                respond("PUT", "/params/speed", &[0xff], &shared).0,
+                "400 Bad Request"
+            );
+            assert_eq!(
+                respond("GET", "/params/speed%2", b"", &shared).0,
+                "400 Bad Request"
+            );
+        }
+
+        #[test]
+        fn rejects_other_methods() {
+            let shared = shared();
+            assert_eq!(
+                respond("DELETE", "/params/speed", b"", &shared).0,
+                "405 Method Not Allowed"
+            );
+            assert_eq!(
+                respond("PUT", "/params", b"1.0", &shared).0,
+                "405 Method Not Allowed"
+            );
+        }
+
+        #[test]
+        fn sets_fail_until_attached() {
+            assert_eq!(
+                respond("PUT", "/params/speed", b"2.0", &shared()).0,
+                "503 Service Unavailable"
+            );
+        }
+
+        #[test]
+        fn decodes_percent_escapes() {
+            assert_eq!(percent_decode("a%20b%2fc").as_deref(), Some("a b/c"));
+            assert_eq!(percent_decode("plain").as_deref(), Some("plain"));
+            assert_eq!(percent_decode("%"), None);
+            assert_eq!(percent_decode("%+1"), None);
+            assert_eq!(percent_decode("%ff"), None);
+        }
+    }
+}
@@ -0,0 +1,512 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bounding volume hierarchies over boxes.
+//!
+//! [`Bvh::build`] splits primitives with the surface area heuristic,
+//! evaluated over a fixed number of bins per axis. The result is a flat
+//! array of [`BvhNode`]s that's uploaded to a storage buffer as is, and
+//! traversed in shaders with the helpers in [`WGSL`] or on the CPU with
+//! [`Bvh::raycast`] for picking.
+//!
+//! Nothing in the crate builds one yet; it's here for `WgpuScene`
+//! implementations with enough geometry to need it. `cargo bench --bench
+//! bvh` times building and raycasting.
+
+/// The WGSL declaration of `BvhNode` and `bvh_ray_box`, for traversal in
+/// shaders.
+pub const WGSL: &str = include_str!("bvh.wgsl");
+
+/// Candidate split planes per axis.
+const BINS: usize = 12;
+
+/// Nodes with this many primitives or fewer are never split.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Cost of visiting a node, relative to intersecting one primitive.
+const TRAVERSAL_COST: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    /// Contains nothing, and so is the identity for [`union`](Self::union).
+    pub const EMPTY: Aabb = Aabb {
+        min: [f32::INFINITY; 3],
+        max: [f32::NEG_INFINITY; 3],
+    };
+
+    pub fn new(min: [f32; 3], max: [f32; 3]) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box containing `points`.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Self {
+        points.into_iter().fold(Self::EMPTY, Self::grow)
+    }
+
+    pub fn grow(self, point: [f32; 3]) -> Self {
+        self.union(&Self::new(point, point))
+    }
+
+    pub fn union(self, other: &Aabb) -> Self {
+        Self {
+            min: [0, 1, 2].map(|axis| self.min[axis].min(other.min[axis])),
+            max: [0, 1, 2].map(|axis| self.max[axis].max(other.max[axis])),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|axis| self.min[axis] > self.max[axis])
+    }
+
+    pub fn centroid(&self) -> [f32; 3] {
+        [0, 1, 2].map(|axis| 0.5 * (self.min[axis] + self.max[axis]))
+    }
+
+    pub fn extent(&self) -> [f32; 3] {
+        [0, 1, 2].map(|axis| (self.max[axis] - self.min[axis]).max(0.0))
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let [x, y, z] = self.extent();
+        2.0 * (x * y + y * z + z * x)
+    }
+
+    /// Distance along `ray` to where it enters the box, if it does so
+    /// before `t_max`.
+    pub fn intersect(&self, ray: &Ray, t_max: f32) -> Option<f32> {
+        let mut near = 0.0_f32;
+        let mut far = t_max;
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction[axis];
+            let t0 = (self.min[axis] - ray.origin[axis]) * inverse;
+            let t1 = (self.max[axis] - ray.origin[axis]) * inverse;
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+        (near <= far).then_some(near)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+impl Ray {
+    pub fn new(origin: [f32; 3], direction: [f32; 3]) -> Self {
+        Self { origin, direction }
+    }
+
+    pub fn at(&self, t: f32) -> [f32; 3] {
+        [0, 1, 2].map(|axis| self.origin[axis] + t * self.direction[axis])
+    }
+}
+
+/// One node of a flattened hierarchy, laid out like `BvhNode` in
+/// `bvh.wgsl`.
+///
+/// Interior nodes have a `count` of zero and their children at
+/// `left_or_first` and the index after it. Leaves cover `count` entries of
+/// [`Bvh::indices`] from `left_or_first`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BvhNode {
+    pub min: [f32; 3],
+    pub left_or_first: u32,
+    pub max: [f32; 3],
+    pub count: u32,
+}
+
+impl BvhNode {
+    fn leaf(first: usize, count: usize) -> Self {
+        Self {
+            min: Aabb::EMPTY.min,
+            left_or_first: first as u32,
+            max: Aabb::EMPTY.max,
+            count: count as u32,
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(self.min, self.max)
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// A split plane between two bins along one axis.
+struct Split {
+    axis: usize,
+    bin: usize,
+}
+
+/// Maps centroids on one axis of a node to bins.
+#[derive(Clone, Copy)]
+struct Binning {
+    min: f32,
+    scale: f32,
+}
+
+impl Binning {
+    fn bin(&self, value: f32) -> usize {
+        (((value - self.min) * self.scale) as usize).min(BINS - 1)
+    }
+}
+
+/// A hierarchy over primitives given as bounding boxes.
+#[derive(Clone, Debug, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<u32>,
+}
+
+impl Bvh {
+    /// Build a hierarchy over `bounds`, one box per primitive.
+    pub fn build(bounds: &[Aabb]) -> Self {
+        if bounds.is_empty() {
+            return Self::default();
+        }
+
+        let centroids: Vec<[f32; 3]> = bounds.iter().map(Aabb::centroid).collect();
+        let mut indices: Vec<u32> = (0..bounds.len() as u32).collect();
+        let mut nodes = Vec::with_capacity(2 * bounds.len());
+        nodes.push(BvhNode::leaf(0, bounds.len()));
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let first = nodes[index].left_or_first as usize;
+            let count = nodes[index].count as usize;
+            let primitives = &mut indices[first..first + count];
+
+            let node_bounds = primitives
+                .iter()
+                .fold(Aabb::EMPTY, |acc, &i| acc.union(&bounds[i as usize]));
+            nodes[index].min = node_bounds.min;
+            nodes[index].max = node_bounds.max;
+
+            if count <= MAX_LEAF_SIZE {
+                continue;
+            }
+
+            let centroid_bounds =
+                Aabb::from_points(primitives.iter().map(|&i| centroids[i as usize]));
+            let (split, binning) = match best_split(
+                primitives,
+                bounds,
+                &centroids,
+                &node_bounds,
+                &centroid_bounds,
+            ) {
+                Some(split) => split,
+                None => continue,
+            };
+
+            let left_count = partition(primitives, |i| {
+                binning.bin(centroids[i as usize][split.axis]) < split.bin
+            });
+            if left_count == 0 || left_count == count {
+                continue;
+            }
+
+            let left = nodes.len();
+            nodes.push(BvhNode::leaf(first, left_count));
+            nodes.push(BvhNode::leaf(first + left_count, count - left_count));
+            nodes[index].left_or_first = left as u32;
+            nodes[index].count = 0;
+            stack.push(left + 1);
+            stack.push(left);
+        }
+
+        Self { nodes, indices }
+    }
+
+    /// The flattened nodes, with the root first.
+    pub fn nodes(&self) -> &[BvhNode] {
+        &self.nodes
+    }
+
+    /// Primitive indices, in the order the leaves refer to them.
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// The closest primitive along `ray` and its distance, where `hit`
+    /// intersects the ray with one primitive by index.
+    pub fn raycast(
+        &self,
+        ray: &Ray,
+        mut hit: impl FnMut(u32) -> Option<f32>,
+    ) -> Option<(u32, f32)> {
+        let mut closest = None;
+        let mut t_max = f32::INFINITY;
+
+        let mut stack = Vec::with_capacity(64);
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.bounds().intersect(ray, t_max).is_none() {
+                continue;
+            }
+
+            let first = node.left_or_first as usize;
+            if node.is_leaf() {
+                for &primitive in &self.indices[first..first + node.count as usize] {
+                    match hit(primitive) {
+                        Some(t) if t >= 0.0 && t < t_max => {
+                            t_max = t;
+                            closest = Some((primitive, t));
+                        }
+                        _ => (),
+                    }
+                }
+                continue;
+            }
+
+            // Visit the nearer child first, so hits there cull the other.
+            let near = |child: usize| {
+                self.nodes[child]
+                    .bounds()
+                    .intersect(ray, t_max)
+                    .unwrap_or(f32::INFINITY)
+            };
+            if near(first) < near(first + 1) {
+                stack.push(first + 1);
+                stack.push(first);
+            } else {
+                stack.push(first);
+                stack.push(first + 1);
+            }
+        }
+
+        closest
+    }
+}
+
+/// The cheapest split of `primitives` by the surface area heuristic, if
+/// it's cheaper than leaving them in one leaf.
+fn best_split(
+    primitives: &[u32],
+    bounds: &[Aabb],
+    centroids: &[[f32; 3]],
+    node_bounds: &Aabb,
+    centroid_bounds: &Aabb,
+) -> Option<(Split, Binning)> {
+    let mut best: Option<(f32, Split, Binning)> = None;
+
+    for axis in 0..3 {
+        let extent = centroid_bounds.extent()[axis];
+        if extent <= 0.0 {
+            continue;
+        }
+        let binning = Binning {
+            min: centroid_bounds.min[axis],
+            scale: BINS as f32 / extent,
+        };
+
+        let mut bin_bounds = [Aabb::EMPTY; BINS];
+        let mut bin_counts = [0_usize; BINS];
+        for &i in primitives {
+            let bin = binning.bin(centroids[i as usize][axis]);
+            bin_bounds[bin] = bin_bounds[bin].union(&bounds[i as usize]);
+            bin_counts[bin] += 1;
+        }
+
+        // Sweep from the right to get the cost of everything past each plane.
+        let mut right_costs = [0.0; BINS];
+        let mut right_bounds = Aabb::EMPTY;
+        let mut right_count = 0;
+        for bin in (1..BINS).rev() {
+            right_bounds = right_bounds.union(&bin_bounds[bin]);
+            right_count += bin_counts[bin];
+            right_costs[bin] = right_count as f32 * right_bounds.surface_area();
+        }
+
+        let mut left_bounds = Aabb::EMPTY;
+        let mut left_count = 0;
+        for bin in 1..BINS {
+            left_bounds = left_bounds.union(&bin_bounds[bin - 1]);
+            left_count += bin_counts[bin - 1];
+            if left_count == 0 || left_count == primitives.len() {
+                continue;
+            }
+
+            let cost = left_count as f32 * left_bounds.surface_area() + right_costs[bin];
+            if best
+                .as_ref()
+                .map_or(true, |(best_cost, ..)| cost < *best_cost)
+            {
+                best = Some((cost, Split { axis, bin }, binning));
+            }
+        }
+    }
+
+    let (cost, split, binning) = best?;
+    let area = node_bounds.surface_area().max(f32::MIN_POSITIVE);
+    let split_cost = TRAVERSAL_COST + cost / area;
+    (split_cost < primitives.len() as f32).then_some((split, binning))
+}
+
+/// Reorder `items` so those matching `left` come first, returning how many
+/// there are.
+fn partition(items: &mut [u32], left: impl Fn(u32) -> bool) -> usize {
+    let mut count = 0;
+    for i in 0..items.len() {
+        if left(items[i]) {
+            items.swap(i, count);
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` unit boxes in a row along x, a unit apart.
+    fn row(count: usize) -> Vec<Aabb> {
+        (0..count)
+            .map(|i| {
+                let x = 2.0 * i as f32;
+                Aabb::new([x, 0.0, 0.0], [x + 1.0, 1.0, 1.0])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn partition_puts_matches_first() {
+        let mut items: Vec<u32> = (0..9).collect();
+        let count = partition(&mut items, |i| i % 3 == 0);
+        assert_eq!(count, 3);
+        assert!(items[..count].iter().all(|i| i % 3 == 0));
+        assert!(items[count..].iter().all(|i| i % 3 != 0));
+
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..9).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn partition_without_matches() {
+        let mut items = vec![1, 2, 3];
+        assert_eq!(partition(&mut items, |_| false), 0);
+        assert_eq!(items, [1, 2, 3]);
+    }
+
+    #[test]
+    fn best_split_separates_clusters() {
+        let mut bounds = row(4);
+        bounds.extend(row(4).iter().map(|b| {
+            Aabb::new(
+                [b.min[0] + 100.0, b.min[1], b.min[2]],
+                [b.max[0] + 100.0, b.max[1], b.max[2]],
+            )
+        }));
+        let centroids: Vec<_> = bounds.iter().map(Aabb::centroid).collect();
+        let primitives: Vec<u32> = (0..bounds.len() as u32).collect();
+        let node_bounds = bounds.iter().fold(Aabb::EMPTY, |acc, b| acc.union(b));
+        let centroid_bounds = Aabb::from_points(centroids.iter().copied());
+
+        let (split, binning) = best_split(
+            &primitives,
+            &bounds,
+            &centroids,
+            &node_bounds,
+            &centroid_bounds,
+        )
+        .expect("a split between the clusters");
+        assert_eq!(split.axis, 0);
+        for (i, centroid) in centroids.iter().enumerate() {
+            let left = binning.bin(centroid[0]) < split.bin;
+            assert_eq!(left, i < 4, "primitive {}", i);
+        }
+    }
+
+    #[test]
+    fn best_split_keeps_coincident_primitives_together() {
+        let bounds = vec![Aabb::new([0.0; 3], [1.0; 3]); 8];
+        let centroids: Vec<_> = bounds.iter().map(Aabb::centroid).collect();
+        let primitives: Vec<u32> = (0..8).collect();
+        let centroid_bounds = Aabb::from_points(centroids.iter().copied());
+        assert!(best_split(
+            &primitives,
+            &bounds,
+            &centroids,
+            &bounds[0],
+            &centroid_bounds
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn build_covers_every_primitive_once() {
+        let bvh = Bvh::build(&row(100));
+        let mut indices = bvh.indices().to_vec();
+        indices.sort_unstable();
+        assert_eq!(indices, (0..100).collect::<Vec<_>>());
+
+        let leaves: u32 = bvh
+            .nodes()
+            .iter()
+            .filter(|n| n.is_leaf())
+            .map(|n| n.count)
+            .sum();
+        assert_eq!(leaves, 100);
+        assert!(bvh.nodes().len() > 1);
+    }
+
+    #[test]
+    fn raycast_finds_the_nearest_hit_first() {
+        let bounds = row(64);
+        let bvh = Bvh::build(&bounds);
+
+        for (origin, direction, nearest) in [
+            ([-1.0, 0.5, 0.5], [1.0, 0.0, 0.0], 0),
+            ([128.0, 0.5, 0.5], [-1.0, 0.0, 0.0], 63),
+        ] {
+            let ray = Ray::new(origin, direction);
+            let mut tested = Vec::new();
+            let hit = bvh.raycast(&ray, |i| {
+                tested.push(i);
+                bounds[i as usize].intersect(&ray, f32::INFINITY)
+            });
+            assert_eq!(hit, Some((nearest, 1.0)));
+            // The nearest leaf is visited first, and its hit culls the rest.
+            assert!(tested.contains(&nearest));
+            assert!(tested.len() <= MAX_LEAF_SIZE, "tested {:?}", tested);
+        }
+    }
+
+    #[test]
+    fn raycast_misses() {
+        let bounds = row(16);
+        let bvh = Bvh::build(&bounds);
+        let ray = Ray::new([-1.0, 5.0, 0.5], [1.0, 0.0, 0.0]);
+        assert_eq!(
+            bvh.raycast(&ray, |i| bounds[i as usize].intersect(&ray, f32::INFINITY)),
+            None
+        );
+        assert_eq!(Bvh::default().raycast(&ray, |_| Some(0.0)), None);
+    }
+}
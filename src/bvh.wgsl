@@ -0,0 +1,22 @@
+// Flattened bounding volume hierarchies, matching `druid_wgpu::bvh`.
+
+// Interior nodes have a `count` of zero and their children at
+// `left_or_first` and the index after it. Leaves cover `count` primitive
+// indices from `left_or_first`.
+struct BvhNode {
+    min: vec3<f32>,
+    left_or_first: u32,
+    max: vec3<f32>,
+    count: u32,
+};
+
+// Distance to where a ray enters a node's box, or a negative value if it
+// misses or enters after `t_max`. Takes `1.0 / direction`, which is shared
+// by every node visited.
+fn bvh_ray_box(node: BvhNode, origin: vec3<f32>, inverse_direction: vec3<f32>, t_max: f32) -> f32 {
+    let t0 = (node.min - origin) * inverse_direction;
+    let t1 = (node.max - origin) * inverse_direction;
+    let near = max(max(min(t0.x, t1.x), min(t0.y, t1.y)), max(min(t0.z, t1.z), 0.0));
+    let far = min(min(max(t0.x, t1.x), max(t0.y, t1.y)), min(max(t0.z, t1.z), t_max));
+    return select(-1.0, near, near <= far);
+}
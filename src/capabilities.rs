@@ -0,0 +1,81 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! What the widget's GPU can do, as app data.
+
+use std::fmt;
+
+use druid::{Data, Lens};
+
+use crate::gpu::SCENE_FORMAT;
+
+/// The adapter and device the widget renders with, for an "About GPU" panel
+/// or for hiding options the device can't do.
+///
+/// The widget fills in `ViewportState::gpu` once it's added.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct GpuCapabilities {
+    pub name: String,
+    #[data(same_fn = "PartialEq::eq")]
+    pub backend: wgpu::Backend,
+    #[data(same_fn = "PartialEq::eq")]
+    pub device_type: wgpu::DeviceType,
+    /// Whether the adapter rasterizes on the CPU, see
+    /// `GpuOptions::reduce_quality_on_software`.
+    pub software: bool,
+    /// Everything the adapter supports.
+    #[data(same_fn = "PartialEq::eq")]
+    pub adapter_features: wgpu::Features,
+    /// What the device was created with, which is only what the widget uses.
+    #[data(same_fn = "PartialEq::eq")]
+    pub features: wgpu::Features,
+    #[data(same_fn = "PartialEq::eq")]
+    pub limits: wgpu::Limits,
+    /// Whether the scene format can be rendered with more than one sample.
+    pub multisample: bool,
+}
+
+impl GpuCapabilities {
+    pub(crate) fn new(adapter: &wgpu::Adapter, device: &wgpu::Device) -> Self {
+        let info = adapter.get_info();
+        let scene_format = adapter.get_texture_format_features(SCENE_FORMAT);
+        Self {
+            software: crate::gpu::is_software(&info),
+            name: info.name,
+            backend: info.backend,
+            device_type: info.device_type,
+            adapter_features: adapter.features(),
+            features: device.features(),
+            limits: device.limits(),
+            multisample: scene_format
+                .flags
+                .contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE),
+        }
+    }
+
+    /// Whether the adapter supports all of `features`.
+    pub fn supports(&self, features: wgpu::Features) -> bool {
+        self.adapter_features.contains(features)
+    }
+}
+
+impl fmt::Display for GpuCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({:?}, {:?})",
+            self.name, self.backend, self.device_type
+        )
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Color profiles for the viewport's output.
+//!
+//! The scene renders into a linear, half-float texture with sRGB primaries.
+//! A final present pass converts it to the target profile's primaries and
+//! encodes it for readback. Both supported profiles share the sRGB transfer
+//! curve, so only the primaries differ.
+
+/// The color space frames are converted to before they're handed to druid.
+///
+/// Pick the profile of the display the window is on. On macOS druid draws
+/// images in the display's color space, so `DisplayP3` shows the full gamut
+/// on P3 panels instead of clamping every color to sRGB.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorProfile {
+    #[default]
+    Srgb,
+    DisplayP3,
+}
+
+impl ColorProfile {
+    /// Rows of the matrix taking linear sRGB to this profile's linear RGB,
+    /// padded for the shader, see `Present` in `present.wgsl`.
+    pub(crate) fn srgb_matrix(self) -> [[f32; 4]; 3] {
+        match self {
+            ColorProfile::Srgb => [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+            ],
+            ColorProfile::DisplayP3 => [
+                [0.822_462, 0.177_538, 0.0, 0.0],
+                [0.033_194, 0.966_806, 0.0, 0.0],
+                [0.017_083, 0.072_397, 0.910_520, 0.0],
+            ],
+        }
+    }
+}
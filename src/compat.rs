@@ -0,0 +1,52 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The few wgpu APIs that change shape between releases.
+//!
+//! Passes and copies go through these helpers rather than building the
+//! descriptors themselves, so moving to a newer wgpu means changing this
+//! file instead of every pipeline.
+//!
+//! The crate is on wgpu 0.14. Moving to a current release is its own
+//! change, not this one: `GpuOptions::backends` and the `WgpuScene` hooks
+//! hand out wgpu types directly, so the bump breaks custom scenes unless
+//! those move behind types of our own first.
+
+use std::num::NonZeroU32;
+
+/// Clear the attachment to `color` and keep what's drawn.
+pub(crate) fn clear(color: wgpu::Color) -> wgpu::Operations<wgpu::Color> {
+    wgpu::Operations {
+        load: wgpu::LoadOp::Clear(color),
+        store: true,
+    }
+}
+
+/// Clear the attachment to `value` and throw it away after the pass.
+pub(crate) fn clear_and_discard<V>(value: V) -> wgpu::Operations<V> {
+    wgpu::Operations {
+        load: wgpu::LoadOp::Clear(value),
+        store: false,
+    }
+}
+
+/// The layout of a texture copied to or from a buffer, rows
+/// `bytes_per_row` apart and images `rows_per_image` rows apart.
+pub(crate) fn image_layout(bytes_per_row: u32, rows_per_image: u32) -> wgpu::ImageDataLayout {
+    wgpu::ImageDataLayout {
+        offset: 0,
+        bytes_per_row: NonZeroU32::new(bytes_per_row),
+        rows_per_image: NonZeroU32::new(rows_per_image),
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A plain text report on the GPU, to attach to bug reports about frames
+//! that look different from one machine to the next.
+//!
+//! It lists every adapter wgpu can see, the device the widget would create
+//! with the given options, the formats it renders in and how long a test
+//! frame takes.
+
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::compat;
+use crate::gpu::{self, Globals, Gpu, ReadbackError, OUTPUT_FORMAT, SCENE_FORMAT};
+use crate::mask::STENCIL_FORMAT;
+use crate::{GpuError, GpuOptions};
+
+/// Width and height of the test frame. A multiple of 64, so its rows need
+/// no padding.
+const TEST_FRAME_SIZE: u32 = 512;
+
+/// A test frame read back from the device.
+struct TestFrame {
+    elapsed: Duration,
+    /// The pixel in the middle, which the demo scene covers.
+    center: [u8; 4],
+}
+
+/// The first test frame, and the second unless the first failed.
+type TestFrames = (
+    Result<TestFrame, ReadbackError>,
+    Option<Result<TestFrame, ReadbackError>>,
+);
+
+/// Build the report for the device `options` describe.
+///
+/// This never fails: a device that can't be created or a test frame that
+/// times out is part of the report.
+pub async fn report(options: &GpuOptions) -> String {
+    let instance = wgpu::Instance::new(options.backends);
+    let adapters: Vec<_> = instance.enumerate_adapters(options.backends).collect();
+
+    let start = Instant::now();
+    let mut gpu = Gpu::try_new(options).await;
+    let created = start.elapsed();
+
+    // The first frame also pays for any pipelines the driver compiles lazily.
+    // A failed one leaves the output buffer mapped or pending, so there's
+    // no second frame after it.
+    let frames = gpu
+        .as_mut()
+        .map(|gpu| {
+            let first = test_frame(gpu, options);
+            let second = first.is_ok().then(|| test_frame(gpu, options));
+            (first, second)
+        })
+        .ok();
+
+    let mut out = String::new();
+    write_report(&mut out, options, &adapters, &gpu, created, frames)
+        .expect("writing to a String can't fail");
+    out
+}
+
+fn write_report(
+    out: &mut String,
+    options: &GpuOptions,
+    adapters: &[wgpu::Adapter],
+    gpu: &Result<Gpu, GpuError>,
+    created: Duration,
+    frames: Option<TestFrames>,
+) -> fmt::Result {
+    writeln!(out, "druid-wgpu {} diagnostics", env!("CARGO_PKG_VERSION"))?;
+    writeln!(
+        out,
+        "OS: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )?;
+    writeln!(out)?;
+
+    writeln!(out, "== Options")?;
+    writeln!(out, "{:#?}", options)?;
+    writeln!(out)?;
+
+    writeln!(out, "== Adapters ({})", adapters.len())?;
+    for adapter in adapters {
+        let info = adapter.get_info();
+        writeln!(out, "{}", info.name)?;
+        writeln!(out, "  backend: {:?}", info.backend)?;
+        writeln!(out, "  type: {:?}", info.device_type)?;
+        writeln!(
+            out,
+            "  vendor: {:#06x}, device: {:#06x}",
+            info.vendor, info.device
+        )?;
+        writeln!(out, "  software: {}", gpu::is_software(&info))?;
+        writeln!(out, "  features: {:?}", adapter.features())?;
+        writeln!(
+            out,
+            "  downlevel: {:?}",
+            adapter.get_downlevel_capabilities().flags
+        )?;
+        for (name, format) in [
+            ("scene", SCENE_FORMAT),
+            ("output", OUTPUT_FORMAT),
+            ("stencil", STENCIL_FORMAT),
+        ] {
+            let features = adapter.get_texture_format_features(format);
+            writeln!(
+                out,
+                "  {} format {:?}: {:?}, {:?}",
+                name, format, features.allowed_usages, features.flags
+            )?;
+        }
+    }
+    writeln!(out)?;
+
+    writeln!(out, "== Device")?;
+    let gpu = match gpu {
+        Ok(gpu) => gpu,
+        Err(err) => return writeln!(out, "{}", err),
+    };
+    writeln!(
+        out,
+        "adapter: {} ({:?})",
+        gpu.adapter.name, gpu.adapter.backend
+    )?;
+    writeln!(
+        out,
+        "reduced quality: {}",
+        options.reduced_quality(&gpu.adapter)
+    )?;
+    writeln!(out, "created in: {:?}", created)?;
+    writeln!(out, "features: {:?}", gpu.device.features())?;
+    writeln!(out, "limits: {:#?}", gpu.device.limits())?;
+    writeln!(out)?;
+
+    writeln!(out, "== Formats")?;
+    writeln!(out, "scene: {:?}", SCENE_FORMAT)?;
+    writeln!(out, "output: {:?}", OUTPUT_FORMAT)?;
+    writeln!(out, "stencil: {:?}", STENCIL_FORMAT)?;
+    writeln!(out)?;
+
+    writeln!(out, "== Test frame ({0}x{0})", TEST_FRAME_SIZE)?;
+    let (first, second) = match frames {
+        Some(frames) => frames,
+        None => return Ok(()),
+    };
+    let second_skipped = second.is_none();
+    for (name, frame) in [("first", Some(first)), ("second", second)] {
+        let frame = match frame {
+            Some(frame) => frame,
+            None => continue,
+        };
+        match frame {
+            Ok(frame) => writeln!(
+                out,
+                "{}: {:?}, center pixel {:?}",
+                name, frame.elapsed, frame.center
+            )?,
+            Err(ReadbackError::Timeout) => writeln!(
+                out,
+                "{}: didn't finish within {:?}",
+                name, options.frame_timeout
+            )?,
+            Err(ReadbackError::MapFailed) => writeln!(out, "{}: couldn't be read back", name)?,
+        }
+    }
+    if second_skipped {
+        writeln!(out, "second: skipped after the first failed")?;
+    }
+    Ok(())
+}
+
+/// Render the scene once, without post effects, and read it back.
+fn test_frame(gpu: &mut Gpu, options: &GpuOptions) -> Result<TestFrame, ReadbackError> {
+    let start = Instant::now();
+    let size = wgpu::Extent3d {
+        width: TEST_FRAME_SIZE,
+        height: TEST_FRAME_SIZE,
+        depth_or_array_layers: 1,
+    };
+    let texture = |format, usage, label| {
+        gpu.device.create_texture(&wgpu::TextureDescriptor {
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            label: Some(label),
+        })
+    };
+    let scene = texture(
+        SCENE_FORMAT,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        "Diagnostics Scene Texture",
+    );
+    let output = texture(
+        OUTPUT_FORMAT,
+        wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        "Diagnostics Output Texture",
+    );
+    let scene_view = scene.create_view(&Default::default());
+    let output_view = output.create_view(&Default::default());
+
+    let globals = Globals {
+        instances: 1.0,
+        ..Globals::default()
+    };
+    gpu.queue
+        .write_buffer(&gpu.globals_buffer, 0, bytemuck::bytes_of(&globals));
+    gpu.resize_output_buffer(TEST_FRAME_SIZE, TEST_FRAME_SIZE);
+
+    let mut encoder = gpu
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Diagnostics Encoder"),
+        });
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Diagnostics Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &scene_view,
+                resolve_target: None,
+                ops: compat::clear(wgpu::Color::BLACK),
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&gpu.render_pipeline);
+        render_pass.set_bind_group(0, &gpu.globals_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, gpu.vertex_buffer.slice(..));
+        render_pass.draw(0..gpu.num_vertices, 0..1);
+    }
+    gpu.encode_present(&mut encoder, &scene_view, &output_view);
+
+    let u32_size = std::mem::size_of::<u32>() as u32;
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture: &output,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &gpu.output_buffer,
+            layout: compat::image_layout(u32_size * TEST_FRAME_SIZE, TEST_FRAME_SIZE),
+        },
+        size,
+    );
+    gpu.queue.submit(std::iter::once(encoder.finish()));
+    gpu::map_read(&gpu.device, &gpu.output_buffer, options.frame_timeout)?;
+    let elapsed = start.elapsed();
+
+    let center = {
+        let data = gpu.output_buffer.slice(..).get_mapped_range();
+        let middle = TEST_FRAME_SIZE / 2;
+        let offset = ((middle * TEST_FRAME_SIZE + middle) * u32_size) as usize;
+        let mut center = [0; 4];
+        center.copy_from_slice(&data[offset..offset + 4]);
+        center
+    };
+    gpu.output_buffer.unmap();
+
+    Ok(TestFrame { elapsed, center })
+}
@@ -0,0 +1,9 @@
+// One direction of a Gaussian blur, see `effects::blur`. Follows
+// `blur.wgsl` and a `blur_direction` function.
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    let radius = params.slots[0].x;
+    let texel = 1.0 / vec2<f32>(textureDimensions(frame));
+    return blur_linear(frame, frame_sampler, in.uv, blur_direction() * texel, radius);
+}
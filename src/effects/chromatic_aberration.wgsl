@@ -0,0 +1,14 @@
+// Chromatic aberration, see `effects::chromatic_aberration`.
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    // Red and blue are pulled apart towards the edges, by `amount` of the
+    // frame at the very edge.
+    let amount = params.slots[0].x;
+    let offset = (in.uv - 0.5) * amount;
+
+    let center = textureSample(frame, frame_sampler, in.uv);
+    let red = textureSample(frame, frame_sampler, in.uv + offset).r;
+    let blue = textureSample(frame, frame_sampler, in.uv - offset).b;
+    return vec4<f32>(red, center.g, blue, center.a);
+}
@@ -0,0 +1,37 @@
+// CRT emulation, see `effects::crt`.
+
+// Bend `uv` outwards from the center like a curved tube.
+fn curve(uv: vec2<f32>, curvature: f32) -> vec2<f32> {
+    let centered = uv * 2.0 - 1.0;
+    let bent = centered * (1.0 + curvature * dot(centered, centered) * vec2<f32>(0.25, 0.3));
+    return bent * 0.5 + 0.5;
+}
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    let curvature = params.slots[0].x;
+    let scanlines = params.slots[1].x;
+    let mask = params.slots[2].x;
+
+    let uv = curve(in.uv, curvature);
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    var color = textureSample(frame, frame_sampler, uv).rgb;
+
+    // One dark band between each pair of rows of the frame.
+    let size = vec2<f32>(textureDimensions(frame));
+    let band = 0.5 + 0.5 * cos(uv.y * size.y * 3.14159265 * 2.0);
+    color = color * (1.0 - scanlines * band);
+
+    // Aperture grille: each column of pixels favours one of red, green and
+    // blue in turn.
+    let column = u32(in.position.x) % 3u;
+    let tint = select(select(vec3<f32>(0.0, 0.0, 1.0), vec3<f32>(0.0, 1.0, 0.0), column == 1u), vec3<f32>(1.0, 0.0, 0.0), column == 0u);
+    color = color * mix(vec3<f32>(1.0), tint * 2.0 + 0.25, mask);
+
+    // Darken towards the rounded edges of the tube.
+    let edge = uv * (1.0 - uv);
+    let falloff = clamp(pow(edge.x * edge.y * 30.0, 0.3), 0.0, 1.0);
+    return vec4<f32>(color * falloff, 1.0);
+}
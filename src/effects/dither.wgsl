@@ -0,0 +1,42 @@
+// Ordered dithering to a palette, see `effects::dither`. The palette comes
+// as a LUT mapping every color to its nearest entry.
+
+@group(1) @binding(0)
+var lut: texture_3d<f32>;
+
+fn to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3<f32>(0.0031308));
+}
+
+fn to_linear(srgb: vec3<f32>) -> vec3<f32> {
+    let low = srgb / 12.92;
+    let high = pow((srgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, srgb <= vec3<f32>(0.04045));
+}
+
+// The 4x4 Bayer matrix, from -0.5 to 0.5, by bit-reversing the interleaved
+// bits of x ^ y and y.
+fn bayer(pixel: vec2<u32>) -> f32 {
+    let y = pixel.y & 3u;
+    let a = (pixel.x & 3u) ^ y;
+    let index = ((a & 1u) << 3u) | ((y & 1u) << 2u) | (a & 2u) | ((y & 2u) >> 1u);
+    return (f32(index) + 0.5) / 16.0 - 0.5;
+}
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    let color = textureSample(frame, frame_sampler, in.uv);
+    let amount = params.slots[0].x;
+
+    // Palettes are picked in display-encoded values, so dither in those too.
+    let encoded = to_srgb(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+    let offset = bayer(vec2<u32>(in.position.xy)) * amount;
+    let dithered = clamp(encoded + offset, vec3<f32>(0.0), vec3<f32>(1.0));
+
+    // Load rather than sample, so colors never blend between entries.
+    let size = f32(textureDimensions(lut).x);
+    let quantized = textureLoad(lut, vec3<i32>(round(dithered * (size - 1.0))), 0).rgb;
+    return vec4<f32>(to_linear(quantized), color.a);
+}
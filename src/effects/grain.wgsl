@@ -0,0 +1,13 @@
+// Film grain, see `effects::grain`.
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    let amount = params.slots[0].x;
+    let color = textureSample(frame, frame_sampler, in.uv);
+
+    // New grain for every frame of 24 fps film, rather than every repaint.
+    let seed = u32(globals.time * 24.0);
+    let pixel = vec2<u32>(in.position.xy);
+    let noise = to_unit(hash3(pixel.x, pixel.y, seed)) - 0.5;
+    return vec4<f32>(max(color.rgb + noise * amount, vec3<f32>(0.0)), color.a);
+}
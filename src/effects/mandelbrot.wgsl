@@ -0,0 +1,88 @@
+// Mandelbrot set, see `effects::mandelbrot`. Replaces the frame.
+//
+// The center is kept as double-floats, (hi, lo) pairs of f32, so zooms go
+// far past where f32 runs out. This relies on the shader compiler not
+// fusing or reordering the float operations below.
+
+fn two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    let v = s - a;
+    return vec2<f32>(s, (a - (s - v)) + (b - v));
+}
+
+fn quick_two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    return vec2<f32>(s, b - (s - a));
+}
+
+fn df_add(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let s = two_sum(a.x, b.x);
+    let t = two_sum(a.y, b.y);
+    let r = quick_two_sum(s.x, s.y + t.x);
+    return quick_two_sum(r.x, r.y + t.y);
+}
+
+// Dekker's split, so products of the halves are exact.
+fn split(a: f32) -> vec2<f32> {
+    let c = 4097.0 * a;
+    let hi = c - (c - a);
+    return vec2<f32>(hi, a - hi);
+}
+
+fn two_prod(a: f32, b: f32) -> vec2<f32> {
+    let p = a * b;
+    let sa = split(a);
+    let sb = split(b);
+    let e = ((sa.x * sb.x - p) + sa.x * sb.y + sa.y * sb.x) + sa.y * sb.y;
+    return vec2<f32>(p, e);
+}
+
+fn df_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let p = two_prod(a.x, b.x);
+    return quick_two_sum(p.x, p.y + a.x * b.y + a.y * b.x);
+}
+
+fn palette(t: f32) -> vec3<f32> {
+    return 0.5 + 0.5 * cos(6.28318 * (t + vec3<f32>(0.0, 0.1, 0.2)));
+}
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    // x hi, y hi, x lo, y lo.
+    let center = params.slots[0];
+    // Height of the view in set units.
+    let scale = params.slots[1].x;
+    let max_iterations = i32(params.slots[2].x);
+
+    let size = vec2<f32>(textureDimensions(frame));
+    let offset = (in.uv - 0.5) * vec2<f32>(size.x / size.y, -1.0) * scale;
+    let cx = df_add(center.xz, vec2<f32>(offset.x, 0.0));
+    let cy = df_add(center.yw, vec2<f32>(offset.y, 0.0));
+
+    var zx = vec2<f32>(0.0);
+    var zy = vec2<f32>(0.0);
+    var i = 0;
+    loop {
+        if (i >= max_iterations) {
+            break;
+        }
+        let xx = df_mul(zx, zx);
+        let yy = df_mul(zy, zy);
+        if (xx.x + yy.x > 256.0) {
+            break;
+        }
+        let xy = df_mul(zx, zy);
+        zx = df_add(df_add(xx, -yy), cx);
+        zy = df_add(df_add(xy, xy), cy);
+        i = i + 1;
+    }
+
+    if (i >= max_iterations) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    // Smooth the iteration count between bands.
+    let magnitude = zx.x * zx.x + zy.x * zy.x;
+    let smooth_i = f32(i) + 1.0 - log2(log2(magnitude) * 0.5);
+    return vec4<f32>(palette(smooth_i * 0.02), 1.0);
+}
@@ -0,0 +1,265 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A few stylistic post effects.
+//!
+//! Each effect reads its settings from the app's [`Params`], under names
+//! prefixed with the effect's name, and only runs while its `enabled`
+//! parameter is true. They're also meant as examples of writing a
+//! [`PostEffect`]: the shaders are short and live next to this file.
+//!
+//! [`Params`]: crate::Params
+
+use crate::params::ParamValue;
+use crate::post::{Lut, PostEffect};
+
+/// The WGSL source of `blur_linear`, a Gaussian blur in one direction.
+pub const BLUR_WGSL: &str = include_str!("../blur.wgsl");
+
+/// The WGSL source of signed distance primitives (`sd_sphere`, `sd_box`,
+/// `sd_torus`, ...), the operators combining them (`op_union`,
+/// `op_smooth_union`, `op_repeat`, ...) and `rotate_x/y/z`.
+pub const SDF_WGSL: &str = include_str!("../sdf.wgsl");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlurDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// One direction of a separable Gaussian blur. Add a horizontal and a
+/// vertical pass, in either order, for the full blur.
+///
+/// - `blur.enabled`
+/// - `blur.radius`: in pixels, up to 64.
+pub fn blur(direction: BlurDirection) -> PostEffect {
+    let (name, vector) = match direction {
+        BlurDirection::Horizontal => ("blur_horizontal", "1.0, 0.0"),
+        BlurDirection::Vertical => ("blur_vertical", "0.0, 1.0"),
+    };
+    let source = format!(
+        "{}\nfn blur_direction() -> vec2<f32> {{\n    return vec2<f32>({});\n}}\n\n{}",
+        BLUR_WGSL,
+        vector,
+        include_str!("blur.wgsl"),
+    );
+    PostEffect::new(name, source)
+        .with_toggle("blur.enabled")
+        .with_param_layout(["blur.radius"])
+}
+
+/// Draws the Mandelbrot set over the whole frame, with double-float
+/// precision for the center so deep zooms stay sharp.
+///
+/// - `mandelbrot.enabled`
+/// - `mandelbrot.center`: from [`split_center`].
+/// - `mandelbrot.scale`: height of the view in set units, 3 to see it all.
+/// - `mandelbrot.iterations`: the iteration limit, a few hundred to start.
+pub fn mandelbrot() -> PostEffect {
+    PostEffect::new("mandelbrot", include_str!("mandelbrot.wgsl"))
+        .with_toggle("mandelbrot.enabled")
+        .with_param_layout([
+            "mandelbrot.center",
+            "mandelbrot.scale",
+            "mandelbrot.iterations",
+        ])
+}
+
+/// Pack a point as double-floats for [`mandelbrot`], since parameters reach
+/// the shader as `f32`.
+pub fn split_center(x: f64, y: f64) -> ParamValue {
+    let x_hi = x as f32 as f64;
+    let y_hi = y as f32 as f64;
+    ParamValue::Vec4(x_hi, y_hi, x - x_hi, y - y_hi)
+}
+
+/// Raymarches the signed distance field `scene` over the frame, as a
+/// starting point for shader art and CSG previews.
+///
+/// `scene` is WGSL defining `fn map(p: vec3<f32>) -> f32`, with everything
+/// in [`SDF_WGSL`] and the post prelude, `globals.time` included, available
+/// to it. Surfaces get simple sun and sky lighting, and the frame shows
+/// through where there's nothing.
+///
+/// - `{name}.enabled`
+/// - `{name}.camera`: a `Vec4` of yaw and pitch in radians, then the
+///   distance of the camera from the origin, orbiting it.
+/// - `{name}.steps`: the march step limit, around 128.
+/// - `{name}.sun`: where the light comes from, from [`sun_position`], so it
+///   can follow a [`sky`]. Unset, it comes from a fixed point above.
+/// - `{name}.fog`: a [`Fog`], none when unset.
+/// - `{name}.fog_color`: RGB in a `Vec4`.
+pub fn raymarch(name: &str, scene: &str) -> PostEffect {
+    let source = format!("{}\n{}\n{}", SDF_WGSL, scene, include_str!("raymarch.wgsl"));
+    PostEffect::new(name, source)
+        .with_toggle(format!("{}.enabled", name))
+        .with_param_layout([
+            format!("{}.camera", name),
+            format!("{}.steps", name),
+            format!("{}.sun", name),
+            format!("{}.fog", name),
+            format!("{}.fog_color", name),
+        ])
+}
+
+/// Fog in front of [`raymarch`] surfaces, set as its `{name}.fog`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fog {
+    /// None before `start` and solid from `end`, in scene units.
+    Linear { start: f64, end: f64 },
+    /// Thickening with distance, `density` per scene unit.
+    Exponential { density: f64 },
+    /// Exponential fog that thins out going up, by a factor of e every
+    /// `1 / falloff` scene units above zero.
+    Height { density: f64, falloff: f64 },
+}
+
+impl From<Fog> for ParamValue {
+    fn from(fog: Fog) -> Self {
+        match fog {
+            Fog::Linear { start, end } => ParamValue::Vec4(1.0, start, end, 0.0),
+            Fog::Exponential { density } => ParamValue::Vec4(2.0, density, 0.0, 0.0),
+            Fog::Height { density, falloff } => ParamValue::Vec4(3.0, density, falloff, 0.0),
+        }
+    }
+}
+
+/// A daylight sky from Preetham's analytic model, replacing the frame. Add
+/// it first, with effects like [`water`] and [`raymarch`] after it: they
+/// show it wherever there's nothing else.
+///
+/// - `sky.enabled`
+/// - `sky.camera`: a `Vec4` of the view's yaw and pitch in radians.
+/// - `sky.sun`: from [`sun_position`].
+pub fn sky() -> PostEffect {
+    PostEffect::new("sky", include_str!("sky.wgsl"))
+        .with_toggle("sky.enabled")
+        .with_param_layout(["sky.camera", "sky.sun"])
+}
+
+/// The sun's direction at `hour`, from 0 to 24, for [`sky`] and
+/// [`raymarch`], as azimuth and elevation in radians. It rises in the east
+/// at 6, passes overhead at noon, as on the equator at an equinox, and sets
+/// at 18. `turbidity` is the haziness of the
+/// air, from 2 for a clear sky to 10 for a hazy one.
+pub fn sun_position(hour: f64, turbidity: f64) -> ParamValue {
+    use std::f64::consts::PI;
+
+    let day = (hour - 6.0) / 12.0 * PI;
+    let azimuth = day - PI / 2.0;
+    // Rising at a steady rate, so the angle is the arc walked so far.
+    let elevation = day.sin().asin();
+    ParamValue::Vec4(azimuth, elevation, turbidity, 0.0)
+}
+
+/// An animated ocean of Gerstner waves below the horizon. The frame stays
+/// above it, and the water reflects and refracts whatever the effects
+/// before this one drew there, a sky for instance.
+///
+/// - `water.enabled`
+/// - `water.camera`: a `Vec4` of yaw and pitch down towards the water in
+///   radians, then the camera's height above it, around 2.
+/// - `water.waves`: a `Vec4` of the largest wave's amplitude and
+///   wavelength, around 0.3 and 8, a speed factor, 1 for real ocean waves,
+///   and steepness from 0 to 1.
+/// - `water.color`: the color of deep water, as RGB in a `Vec4`.
+pub fn water() -> PostEffect {
+    PostEffect::new("water", include_str!("water.wgsl"))
+        .with_toggle("water.enabled")
+        .with_param_layout(["water.camera", "water.waves", "water.color"])
+}
+
+/// An old tube screen: curved glass, scanlines and an aperture grille.
+/// Toggle it at runtime with [`TOGGLE_EFFECT`] for `"crt"`.
+///
+/// - `crt.enabled`
+/// - `crt.curvature`: 0 for a flat screen, around 0.2 for a curved one.
+/// - `crt.scanlines`: how dark the gaps between rows get, 0 to 1.
+/// - `crt.mask`: strength of the aperture grille, 0 to 1, around 0.3.
+///
+/// [`TOGGLE_EFFECT`]: crate::post::TOGGLE_EFFECT
+pub fn crt() -> PostEffect {
+    PostEffect::new("crt", include_str!("crt.wgsl"))
+        .with_toggle("crt.enabled")
+        .with_param_layout(["crt.curvature", "crt.scanlines", "crt.mask"])
+}
+
+/// Reduces the frame to `palette`, display-encoded colors from 0 to 1,
+/// with 4×4 ordered dithering. Pairs well with
+/// [`WgpuWidget::with_resolution`] for retro looks.
+///
+/// The palette can be swapped while running by sending [`SET_LUT`] for
+/// `"dither"` with a [`Lut::from_palette`].
+///
+/// - `dither.enabled`
+/// - `dither.amount`: spread of the dither pattern, around the gap between
+///   neighboring palette colors, 0.1 to 0.3.
+///
+/// [`WgpuWidget::with_resolution`]: crate::WgpuWidget::with_resolution
+/// [`SET_LUT`]: crate::post::SET_LUT
+pub fn dither(palette: &[[f32; 3]]) -> PostEffect {
+    PostEffect::new("dither", include_str!("dither.wgsl"))
+        .with_toggle("dither.enabled")
+        .with_param_layout(["dither.amount"])
+        .with_lut(Lut::from_palette(palette, 32))
+}
+
+/// Splits red and blue towards the edges of the frame, like a cheap lens.
+///
+/// - `chromatic_aberration.enabled`
+/// - `chromatic_aberration.amount`: shift at the edges as a fraction of the
+///   frame, around 0.01.
+pub fn chromatic_aberration() -> PostEffect {
+    PostEffect::new(
+        "chromatic_aberration",
+        include_str!("chromatic_aberration.wgsl"),
+    )
+    .with_toggle("chromatic_aberration.enabled")
+    .with_param_layout(["chromatic_aberration.amount"])
+}
+
+/// Animated film grain.
+///
+/// - `grain.enabled`
+/// - `grain.amount`: strength of the noise, around 0.05.
+pub fn grain() -> PostEffect {
+    PostEffect::new("grain", include_str!("grain.wgsl"))
+        .with_toggle("grain.enabled")
+        .with_param_layout(["grain.amount"])
+}
+
+/// Leaves fading trails behind anything that moves, by feeding the
+/// previous output back in. A starting point for other feedback effects.
+///
+/// - `trails.enabled`
+/// - `trails.decay`: how much of the previous frame is kept, 0 to 1, around
+///   0.9.
+pub fn trails() -> PostEffect {
+    PostEffect::new("trails", include_str!("trails.wgsl"))
+        .with_toggle("trails.enabled")
+        .with_param_layout(["trails.decay"])
+        .with_history()
+}
+
+/// Darkens the corners of the frame.
+///
+/// - `vignette.enabled`
+/// - `vignette.strength`: how dark the corners get, 0 to 1.
+/// - `vignette.radius`: where darkening starts, 0 at the center to 1 in the
+///   corners.
+pub fn vignette() -> PostEffect {
+    PostEffect::new("vignette", include_str!("vignette.wgsl"))
+        .with_toggle("vignette.enabled")
+        .with_param_layout(["vignette.strength", "vignette.radius"])
+}
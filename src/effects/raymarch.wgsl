@@ -0,0 +1,108 @@
+// Sphere tracing of `map`, see `effects::raymarch`. The frame shows through
+// wherever the rays miss.
+
+fn map_normal(p: vec3<f32>) -> vec3<f32> {
+    // Tetrahedral differences, four evaluations of `map`.
+    let e = vec2<f32>(1.0, -1.0) * 0.0005;
+    return normalize(
+        e.xyy * map(p + e.xyy) + e.yyx * map(p + e.yyx) + e.yxy * map(p + e.yxy) + e.xxx * map(p + e.xxx)
+    );
+}
+
+// Soft shadow towards the light, 0 in full shadow.
+fn map_shadow(origin: vec3<f32>, direction: vec3<f32>) -> f32 {
+    var shadow = 1.0;
+    var t = 0.02;
+    var i = 0;
+    loop {
+        if (i >= 64 || t > 20.0) {
+            break;
+        }
+        let d = map(origin + direction * t);
+        if (d < 0.0001) {
+            return 0.0;
+        }
+        shadow = min(shadow, 8.0 * d / t);
+        t = t + clamp(d, 0.01, 0.5);
+        i = i + 1;
+    }
+    return clamp(shadow, 0.0, 1.0);
+}
+
+// How much fog is in front of a surface `t` along the ray. `fog` is the
+// mode, 0 for none, 1 linear, 2 exponential and 3 height, and its settings.
+fn map_fog(fog: vec4<f32>, eye: vec3<f32>, direction: vec3<f32>, t: f32) -> f32 {
+    let mode = i32(fog.x + 0.5);
+    if (mode == 1) {
+        return clamp((t - fog.y) / max(fog.z - fog.y, 0.0001), 0.0, 1.0);
+    }
+    if (mode == 2) {
+        return 1.0 - exp(-fog.y * t);
+    }
+    if (mode == 3) {
+        // Density falling off with height, integrated along the ray.
+        let falloff = max(fog.z, 0.0001);
+        let rise = falloff * direction.y * t;
+        var along = t;
+        if (abs(rise) > 0.0001) {
+            along = (1.0 - exp(-rise)) / (falloff * direction.y);
+        }
+        return 1.0 - exp(-fog.y * exp(-falloff * eye.y) * along);
+    }
+    return 0.0;
+}
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    // Yaw and pitch in radians, then distance from the origin.
+    let camera = params.slots[0];
+    let max_steps = i32(params.slots[1].x);
+    // Azimuth and elevation of the sun, or all zero for the default light.
+    let sun = params.slots[2];
+    let fog = params.slots[3];
+    let fog_color = params.slots[4].xyz;
+
+    let eye = camera.z * vec3<f32>(
+        cos(camera.y) * sin(camera.x),
+        sin(camera.y),
+        cos(camera.y) * cos(camera.x)
+    );
+    let forward = normalize(-eye);
+    let right = normalize(cross(forward, vec3<f32>(0.0, 1.0, 0.0)));
+    let up = cross(right, forward);
+
+    let size = vec2<f32>(textureDimensions(frame));
+    let screen = (in.uv - 0.5) * vec2<f32>(size.x / size.y, -1.0);
+    let direction = normalize(forward * 1.5 + right * screen.x + up * screen.y);
+
+    var t = 0.0;
+    var hit = false;
+    var i = 0;
+    loop {
+        if (i >= max_steps || t > 100.0) {
+            break;
+        }
+        let d = map(eye + direction * t);
+        if (d < 0.0005 * t) {
+            hit = true;
+            break;
+        }
+        t = t + d;
+        i = i + 1;
+    }
+
+    if (!hit) {
+        return textureSample(frame, frame_sampler, in.uv);
+    }
+
+    let p = eye + direction * t;
+    let normal = map_normal(p);
+    var light = normalize(vec3<f32>(0.6, 0.8, 0.4));
+    if (any(sun != vec4<f32>(0.0))) {
+        light = vec3<f32>(cos(sun.y) * sin(sun.x), sin(sun.y), cos(sun.y) * cos(sun.x));
+    }
+    let diffuse = max(dot(normal, light), 0.0) * map_shadow(p + normal * 0.001, light);
+    let sky = 0.5 + 0.5 * normal.y;
+    let color = vec3<f32>(0.8, 0.75, 0.7) * (diffuse + 0.15 * sky);
+    return vec4<f32>(mix(color, fog_color, map_fog(fog, eye, direction, t)), 1.0);
+}
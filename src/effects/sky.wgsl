@@ -0,0 +1,93 @@
+// Preetham's analytic daylight model, see `effects::sky`. Replaces the
+// frame.
+
+// Perez's luminance distribution, with coefficients `abcd` and `e`.
+fn sky_perez(cos_theta: f32, gamma: f32, cos_gamma: f32, abcd: vec4<f32>, e: f32) -> f32 {
+    return (1.0 + abcd.x * exp(abcd.y / max(cos_theta, 0.01)))
+        * (1.0 + abcd.z * exp(abcd.w * gamma) + e * cos_gamma * cos_gamma);
+}
+
+// Linear sRGB of the sky towards `view`, with the sun towards `sun`.
+fn sky_color(view: vec3<f32>, sun: vec3<f32>, turbidity: f32) -> vec3<f32> {
+    let t = turbidity;
+    let theta_s = acos(clamp(sun.y, 0.0, 1.0));
+    let ts = theta_s;
+    let ts2 = ts * ts;
+    let ts3 = ts2 * ts;
+
+    // Zenith luminance in kcd/m², and chromaticity.
+    let chi = (4.0 / 9.0 - t / 120.0) * (3.14159 - 2.0 * theta_s);
+    let zenith_y = (4.0453 * t - 4.9710) * tan(chi) - 0.2155 * t + 2.4192;
+    let zenith_x = t * t * (0.00166 * ts3 - 0.00375 * ts2 + 0.00209 * ts)
+        + t * (-0.02903 * ts3 + 0.06377 * ts2 - 0.03202 * ts + 0.00394)
+        + (0.11693 * ts3 - 0.21196 * ts2 + 0.06052 * ts + 0.25886);
+    let zenith_cy = t * t * (0.00275 * ts3 - 0.00610 * ts2 + 0.00317 * ts)
+        + t * (-0.04214 * ts3 + 0.08970 * ts2 - 0.04153 * ts + 0.00516)
+        + (0.15346 * ts3 - 0.26756 * ts2 + 0.06670 * ts + 0.26688);
+
+    let abcd_y = vec4<f32>(0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771);
+    let e_y = -0.0670 * t + 0.3703;
+    let abcd_x = vec4<f32>(-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989);
+    let e_x = -0.0033 * t + 0.0452;
+    let abcd_cy = vec4<f32>(-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537);
+    let e_cy = -0.0109 * t + 0.0529;
+
+    // Below the horizon, carry on with the horizon's color.
+    let direction = normalize(vec3<f32>(view.x, max(view.y, 0.001), view.z));
+    let cos_gamma = clamp(dot(direction, sun), -1.0, 1.0);
+    let gamma = acos(cos_gamma);
+    let cos_s = cos(theta_s);
+
+    let luminance = zenith_y * sky_perez(direction.y, gamma, cos_gamma, abcd_y, e_y)
+        / sky_perez(1.0, theta_s, cos_s, abcd_y, e_y);
+    let x = zenith_x * sky_perez(direction.y, gamma, cos_gamma, abcd_x, e_x)
+        / sky_perez(1.0, theta_s, cos_s, abcd_x, e_x);
+    let y = zenith_cy * sky_perez(direction.y, gamma, cos_gamma, abcd_cy, e_cy)
+        / sky_perez(1.0, theta_s, cos_s, abcd_cy, e_cy);
+
+    // xyY to XYZ to linear sRGB, exposed so a clear noon sky is around 1.
+    let big_y = max(luminance, 0.0) * 0.05;
+    let big_x = x * big_y / y;
+    let big_z = (1.0 - x - y) * big_y / y;
+    var color = vec3<f32>(
+        3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z
+    );
+
+    // The sun's disk, and night once it has set.
+    color = color + vec3<f32>(20.0) * smoothstep(0.9998, 0.9999, cos_gamma);
+    color = color * smoothstep(-0.1, 0.05, sun.y);
+    if (view.y < 0.0) {
+        color = color * 0.5;
+    }
+    return max(color, vec3<f32>(0.0));
+}
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    // Yaw and pitch of the view, in radians.
+    let camera = params.slots[0];
+    // Azimuth and elevation of the sun in radians, then turbidity.
+    let sun_params = params.slots[1];
+
+    let forward = vec3<f32>(
+        cos(camera.y) * sin(camera.x),
+        sin(camera.y),
+        cos(camera.y) * cos(camera.x)
+    );
+    let right = normalize(cross(forward, vec3<f32>(0.0, 1.0, 0.0)));
+    let up = cross(right, forward);
+
+    let size = vec2<f32>(textureDimensions(frame));
+    let screen = (in.uv - 0.5) * vec2<f32>(size.x / size.y, -1.0);
+    let view = normalize(forward * 1.5 + right * screen.x + up * screen.y);
+
+    let sun = vec3<f32>(
+        cos(sun_params.y) * sin(sun_params.x),
+        sin(sun_params.y),
+        cos(sun_params.y) * cos(sun_params.x)
+    );
+    let turbidity = clamp(sun_params.z, 1.7, 10.0);
+    return vec4<f32>(sky_color(view, sun, turbidity), 1.0);
+}
@@ -0,0 +1,15 @@
+// Fading trails, see `effects::trails`.
+
+@group(2) @binding(0)
+var history: texture_2d<f32>;
+
+@group(2) @binding(1)
+var history_sampler: sampler;
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    let decay = params.slots[0].x;
+    let color = textureSample(frame, frame_sampler, in.uv);
+    let previous = textureSample(history, history_sampler, in.uv);
+    return max(color, previous * decay);
+}
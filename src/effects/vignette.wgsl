@@ -0,0 +1,13 @@
+// Vignette, see `effects::vignette`.
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    let strength = params.slots[0].x;
+    let radius = params.slots[1].x;
+    let color = textureSample(frame, frame_sampler, in.uv);
+
+    // 0 at the center and 1 in the corners.
+    let distance = length(in.uv - 0.5) * 1.41421356;
+    let falloff = smoothstep(radius, 1.0, distance);
+    return vec4<f32>(color.rgb * (1.0 - strength * falloff), color.a);
+}
@@ -0,0 +1,119 @@
+// Gerstner wave ocean, see `effects::water`. Above the horizon the frame
+// shows as it is; the water reflects it, and refracts it where the camera
+// looks down into the waves.
+
+struct WaterSurface {
+    height: f32,
+    normal: vec3<f32>,
+};
+
+// Four Gerstner waves in different directions, each smaller and shorter
+// than the last. `waves` is amplitude, wavelength, speed and steepness.
+fn water_surface(p: vec2<f32>, waves: vec4<f32>) -> WaterSurface {
+    var height = 0.0;
+    var tangent_x = vec3<f32>(1.0, 0.0, 0.0);
+    var tangent_z = vec3<f32>(0.0, 0.0, 1.0);
+    var amplitude = waves.x;
+    var wavelength = max(waves.y, 0.01);
+    // Spread over the waves, so a steepness of 1 is where crests get sharp.
+    let steepness = waves.w * 0.25;
+    var i = 0;
+    loop {
+        if (i >= 4) {
+            break;
+        }
+        let angle = f32(i) * 2.4;
+        let d = vec2<f32>(cos(angle), sin(angle));
+        let k = 6.28318 / wavelength;
+        // Deep water dispersion.
+        let c = sqrt(9.8 / k) * waves.z;
+        let f = k * (dot(d, p) - c * globals.time);
+        let slope = k * amplitude * cos(f);
+        let pinch = steepness * sin(f);
+
+        height = height + amplitude * sin(f);
+        tangent_x = tangent_x + vec3<f32>(-pinch * d.x * d.x, d.x * slope, -pinch * d.x * d.y);
+        tangent_z = tangent_z + vec3<f32>(-pinch * d.x * d.y, d.y * slope, -pinch * d.y * d.y);
+
+        amplitude = amplitude * 0.6;
+        wavelength = wavelength * 0.55;
+        i = i + 1;
+    }
+
+    var surface: WaterSurface;
+    surface.height = height;
+    surface.normal = normalize(cross(tangent_z, tangent_x));
+    return surface;
+}
+
+// Where the camera sees `direction`, clamped to the frame.
+fn water_project(
+    direction: vec3<f32>,
+    forward: vec3<f32>,
+    right: vec3<f32>,
+    up: vec3<f32>,
+    aspect: vec2<f32>
+) -> vec2<f32> {
+    let depth = max(dot(direction, forward), 0.1);
+    let screen = vec2<f32>(dot(direction, right), dot(direction, up)) * 1.5 / depth;
+    return clamp(screen / aspect + 0.5, vec2<f32>(0.0), vec2<f32>(1.0));
+}
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    // Yaw, pitch down towards the water in radians, then height.
+    let camera = params.slots[0];
+    let waves = params.slots[1];
+    let water_color = params.slots[2].xyz;
+
+    let eye = vec3<f32>(0.0, camera.z, 0.0);
+    let forward = vec3<f32>(
+        cos(camera.y) * sin(camera.x),
+        -sin(camera.y),
+        cos(camera.y) * cos(camera.x)
+    );
+    let right = normalize(cross(forward, vec3<f32>(0.0, 1.0, 0.0)));
+    let up = cross(right, forward);
+
+    let size = vec2<f32>(textureDimensions(frame));
+    let aspect = vec2<f32>(size.x / size.y, -1.0);
+    let screen = (in.uv - 0.5) * aspect;
+    let direction = normalize(forward * 1.5 + right * screen.x + up * screen.y);
+
+    if (direction.y > -0.001) {
+        return textureSample(frame, frame_sampler, in.uv);
+    }
+
+    // Intersect the height field, starting from the flat sea.
+    var t = eye.y / -direction.y;
+    var surface = water_surface((eye + direction * t).xz, waves);
+    var i = 0;
+    loop {
+        if (i >= 6) {
+            break;
+        }
+        t = (eye.y - surface.height) / -direction.y;
+        surface = water_surface((eye + direction * t).xz, waves);
+        i = i + 1;
+    }
+    let normal = surface.normal;
+
+    // Reflect the frame by projecting the reflected ray back onto it.
+    var reflected = reflect(direction, normal);
+    reflected = normalize(vec3<f32>(reflected.x, max(reflected.y, 0.01), reflected.z));
+    let reflected_uv = water_project(reflected, forward, right, up, aspect);
+    let reflection = textureSample(frame, frame_sampler, reflected_uv).rgb;
+
+    // What's under the water, wobbling with the waves and tinted.
+    let refracted_uv = clamp(in.uv + normal.xz * 0.03, vec2<f32>(0.0), vec2<f32>(1.0));
+    let refraction = mix(textureSample(frame, frame_sampler, refracted_uv).rgb, water_color, 0.8);
+
+    let fresnel = 0.02 + 0.98 * pow(1.0 - max(dot(-direction, normal), 0.0), 5.0);
+    let color = mix(refraction, reflection, fresnel);
+
+    // Fade into the horizon.
+    let level = normalize(vec3<f32>(direction.x, 0.0, direction.z));
+    let horizon_uv = water_project(level, forward, right, up, aspect);
+    let horizon = textureSample(frame, frame_sampler, horizon_uv).rgb;
+    return vec4<f32>(mix(color, horizon, 1.0 - exp(-t * 0.005)), 1.0);
+}
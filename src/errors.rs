@@ -0,0 +1,90 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structured reporting of wgpu errors.
+//!
+//! wgpu's default handler for uncaptured errors panics. The widget installs
+//! its own instead and forwards every error to the app as a [`GPU_ERROR`]
+//! command with a global target, so an `AppDelegate` can log or display it.
+
+use std::fmt;
+use std::sync::mpsc::{channel, Receiver};
+
+use druid::Selector;
+
+/// Sent with global target for every error the device reports.
+pub const GPU_ERROR: Selector<GpuError> = Selector::new("druid-wgpu.gpu-error");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuErrorKind {
+    Validation,
+    OutOfMemory,
+    /// A frame didn't finish within `GpuOptions::frame_timeout`.
+    Timeout,
+    /// The device stopped responding and was replaced.
+    DeviceLost,
+    /// No adapter could be found, or it refused to create a device.
+    Unavailable,
+}
+
+#[derive(Clone, Debug)]
+pub struct GpuError {
+    pub kind: GpuErrorKind,
+    pub message: String,
+    /// The frame being rendered, when the error was caught by the per-frame
+    /// validation scope.
+    pub frame: Option<u64>,
+}
+
+impl GpuError {
+    pub(crate) fn new(error: wgpu::Error, frame: Option<u64>) -> Self {
+        let (kind, message) = match error {
+            wgpu::Error::Validation { description, .. } => (GpuErrorKind::Validation, description),
+            wgpu::Error::OutOfMemory { source } => (GpuErrorKind::OutOfMemory, source.to_string()),
+        };
+        Self {
+            kind,
+            message,
+            frame,
+        }
+    }
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            GpuErrorKind::Validation => "validation error",
+            GpuErrorKind::OutOfMemory => "out of memory",
+            GpuErrorKind::Timeout => "timeout",
+            GpuErrorKind::DeviceLost => "device lost",
+            GpuErrorKind::Unavailable => "unavailable",
+        };
+        match self.frame {
+            Some(frame) => write!(f, "GPU {} in frame {}: {}", kind, frame, self.message),
+            None => write!(f, "GPU {}: {}", kind, self.message),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+/// Route `device`'s uncaptured errors into a channel.
+pub(crate) fn capture_uncaptured(device: &wgpu::Device) -> Receiver<GpuError> {
+    let (tx, rx) = channel();
+    device.on_uncaptured_error(move |error| {
+        // The receiver only goes away with the widget.
+        let _ = tx.send(GpuError::new(error, None));
+    });
+    rx
+}
@@ -0,0 +1,108 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A stand-in for the viewport on machines without a usable GPU
+//! (`fallback` feature).
+//!
+//! When `WgpuWidget::try_new` fails, put a [`FallbackViewport`] where the
+//! viewport would have been. It draws with piet alone: a placeholder saying
+//! why rendering is unavailable and listing the adapters wgpu could see,
+//! so the rest of the app keeps working on VMs and CI machines.
+
+use druid::piet::{FontFamily, Text, TextLayoutBuilder};
+use druid::widget::prelude::*;
+use druid::LocalizedString;
+
+use crate::theme;
+use crate::{GpuError, ViewportState};
+
+pub struct FallbackViewport {
+    error: GpuError,
+    /// One line per adapter found, collected once since it doesn't change.
+    adapters: Vec<String>,
+}
+
+impl FallbackViewport {
+    pub fn new(error: GpuError) -> Self {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapters = instance
+            .enumerate_adapters(wgpu::Backends::all())
+            .map(|adapter| {
+                let info = adapter.get_info();
+                format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+            })
+            .collect();
+        Self { error, adapters }
+    }
+
+    pub fn error(&self) -> &GpuError {
+        &self.error
+    }
+}
+
+impl Widget<ViewportState> for FallbackViewport {
+    fn event(&mut self, _: &mut EventCtx, _: &Event, _: &mut ViewportState, _: &Env) {}
+
+    fn lifecycle(&mut self, _: &mut LifeCycleCtx, _: &LifeCycle, _: &ViewportState, _: &Env) {}
+
+    fn update(&mut self, _: &mut UpdateCtx, _: &ViewportState, _: &ViewportState, _: &Env) {}
+
+    fn layout(
+        &mut self,
+        _: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _: &ViewportState,
+        _: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &ViewportState, env: &Env) {
+        let background = env
+            .try_get(theme::VIEWPORT_BACKGROUND)
+            .unwrap_or(theme::DARK_BACKGROUND);
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &background);
+
+        let mut title = LocalizedString::new("druid-wgpu-gpu-unavailable")
+            .with_placeholder("GPU rendering is unavailable");
+        title.resolve(data, env);
+        let mut adapters_title = LocalizedString::new("druid-wgpu-gpu-unavailable-adapters")
+            .with_placeholder("Adapters found:");
+        adapters_title.resolve(data, env);
+
+        let adapters = if self.adapters.is_empty() {
+            "none".to_string()
+        } else {
+            self.adapters.join("\n")
+        };
+        let text = format!(
+            "{}\n\n{}\n\n{}\n{}",
+            title.localized_str(),
+            self.error,
+            adapters_title.localized_str(),
+            adapters
+        );
+        let layout = ctx
+            .text()
+            .new_text_layout(text)
+            .font(FontFamily::SYSTEM_UI, 14.0)
+            .text_color(env.get(druid::theme::TEXT_COLOR))
+            .max_width(rect.width() - 32.0)
+            .build();
+        if let Ok(layout) = layout {
+            ctx.draw_text(&layout, (16.0, 16.0));
+        }
+    }
+}
@@ -0,0 +1,545 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that runs compute filters over an image.
+//!
+//! [`ImageFilter`] uploads an image once, then reruns the selected
+//! [`Kernel`] whenever its [`FilterSettings`] change and shows the result.
+//! Filters work on the image's stored values, without decoding sRGB first.
+
+use std::sync::mpsc::Receiver;
+
+use druid::piet::{ImageFormat, InterpolationMode, PietImage};
+use druid::widget::prelude::*;
+use druid::{Data, ImageBuf, Lens, Selector};
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+use crate::compat;
+use crate::effects::BLUR_WGSL;
+use crate::errors::{self, GpuError, GpuErrorKind};
+use crate::gpu::{self, ReadbackError};
+use crate::GpuOptions;
+
+/// Save the filtered image to the path given, in a format picked from its
+/// extension.
+pub const EXPORT_IMAGE: Selector<String> = Selector::new("druid-wgpu.export-image");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data, Serialize, Deserialize)]
+pub enum Kernel {
+    /// Gaussian blur, `amount` pixels in radius.
+    Blur,
+    /// Unsharp mask, adding `amount` times the local detail.
+    Sharpen,
+    /// Sobel edge detection, with edges scaled by `amount`.
+    Sobel,
+    /// Remap `black` to `white` onto the full range, then apply `gamma`.
+    Levels,
+}
+
+/// Every entry point in `filter.wgsl`.
+const ENTRY_POINTS: &[&str] = &[
+    "blur_horizontal",
+    "blur_vertical",
+    "sharpen",
+    "sobel",
+    "levels",
+];
+
+impl Kernel {
+    /// The entry points to dispatch, in order. Kernels have at most two.
+    fn passes(self) -> &'static [&'static str] {
+        match self {
+            Kernel::Blur => &["blur_horizontal", "blur_vertical"],
+            Kernel::Sharpen => &["sharpen"],
+            Kernel::Sobel => &["sobel"],
+            Kernel::Levels => &["levels"],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+pub struct FilterSettings {
+    pub kernel: Kernel,
+    pub amount: f64,
+    pub black: f64,
+    pub white: f64,
+    pub gamma: f64,
+}
+
+impl FilterSettings {
+    pub fn new(kernel: Kernel) -> Self {
+        Self {
+            kernel,
+            amount: 1.0,
+            black: 0.0,
+            white: 1.0,
+            gamma: 1.0,
+        }
+    }
+}
+
+/// `FilterSettings` as the shader sees them, see `Settings` in
+/// `filter.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SettingsUniforms {
+    amount: f32,
+    black: f32,
+    white: f32,
+    gamma: f32,
+}
+
+pub struct ImageFilter {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    options: GpuOptions,
+    pipelines: Vec<(&'static str, wgpu::ComputePipeline)>,
+    /// From the source to the output, for single pass kernels.
+    direct_bind_group: wgpu::BindGroup,
+    /// The first pass of two, from the source to the intermediate texture.
+    to_intermediate_bind_group: wgpu::BindGroup,
+    /// The second pass of two, from the intermediate texture to the output.
+    from_intermediate_bind_group: wgpu::BindGroup,
+    settings_buffer: wgpu::Buffer,
+    output_texture: wgpu::Texture,
+    readback_buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_row_size: u32,
+    errors: Receiver<GpuError>,
+    /// Whether the settings changed since the last run.
+    dirty: bool,
+    filtered: Option<(ImageBuf, PietImage)>,
+}
+
+impl ImageFilter {
+    pub async fn new(source: &ImageBuf) -> Self {
+        Self::with_options(source, GpuOptions::default()).await
+    }
+
+    /// Panics when there's no usable GPU or it can't hold `source`; see
+    /// [`try_with_options`].
+    ///
+    /// [`try_with_options`]: ImageFilter::try_with_options
+    pub async fn with_options(source: &ImageBuf, options: GpuOptions) -> Self {
+        match Self::try_with_options(source, options).await {
+            Ok(filter) => filter,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub async fn try_new(source: &ImageBuf) -> Result<Self, GpuError> {
+        Self::try_with_options(source, GpuOptions::default()).await
+    }
+
+    /// Fails with a [`GpuErrorKind::Unavailable`] error when no adapter can
+    /// run the filters, and a [`GpuErrorKind::Validation`] one when
+    /// `source` is empty or larger than the device's textures.
+    pub async fn try_with_options(
+        source: &ImageBuf,
+        options: GpuOptions,
+    ) -> Result<Self, GpuError> {
+        let (device, queue, _, _) = gpu::try_request_device(&options).await?;
+
+        let width = source.width() as u32;
+        let height = source.height() as u32;
+        let max_size = device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > max_size || height > max_size {
+            return Err(GpuError {
+                kind: GpuErrorKind::Validation,
+                message: format!(
+                    "can't filter a {}x{} image, the device allows 1 to {} pixels a side",
+                    width, height, max_size
+                ),
+                frame: None,
+            });
+        }
+        let errors = errors::capture_uncaptured(&device);
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let source_texture = device.create_texture_with_data(
+            &queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Filter Source"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+            &to_rgba(source),
+        );
+        let output_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Filter Output"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        });
+        let intermediate_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Filter Intermediate"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Filter Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let settings_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Filter Settings Buffer"),
+            contents: bytemuck::bytes_of(&SettingsUniforms {
+                amount: 1.0,
+                black: 0.0,
+                white: 1.0,
+                gamma: 1.0,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row_size = (width * 4 + alignment - 1) / alignment * alignment;
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Filter Readback Buffer"),
+            size: (padded_row_size * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Filter Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let source_view = source_texture.create_view(&Default::default());
+        let intermediate_view = intermediate_texture.create_view(&Default::default());
+        let output_view = output_texture.create_view(&Default::default());
+        let create_bind_group = |input: &wgpu::TextureView, output: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Filter Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(input),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(output),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: settings_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            })
+        };
+        let direct_bind_group = create_bind_group(&source_view, &output_view);
+        let to_intermediate_bind_group = create_bind_group(&source_view, &intermediate_view);
+        let from_intermediate_bind_group = create_bind_group(&intermediate_view, &output_view);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Filter Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                format!("{}{}", BLUR_WGSL, include_str!("filter.wgsl")).into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Filter Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipelines = ENTRY_POINTS
+            .iter()
+            .map(|&entry_point| {
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(entry_point),
+                    layout: Some(&pipeline_layout),
+                    module: &shader,
+                    entry_point,
+                });
+                (entry_point, pipeline)
+            })
+            .collect();
+
+        Ok(Self {
+            device,
+            queue,
+            options,
+            pipelines,
+            direct_bind_group,
+            to_intermediate_bind_group,
+            from_intermediate_bind_group,
+            settings_buffer,
+            output_texture,
+            readback_buffer,
+            width,
+            height,
+            padded_row_size,
+            errors,
+            dirty: true,
+            filtered: None,
+        })
+    }
+
+    /// The most recent result, at the source image's size.
+    pub fn filtered(&self) -> Option<&ImageBuf> {
+        self.filtered.as_ref().map(|(image, _)| image)
+    }
+
+    /// Run the filter and read the result back.
+    fn run(&mut self, settings: &FilterSettings) -> Result<ImageBuf, ReadbackError> {
+        let uniforms = SettingsUniforms {
+            amount: settings.amount as f32,
+            black: settings.black as f32,
+            white: settings.white as f32,
+            gamma: settings.gamma as f32,
+        };
+        self.queue
+            .write_buffer(&self.settings_buffer, 0, bytemuck::bytes_of(&uniforms));
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Filter Encoder"),
+            });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Filter Pass"),
+            });
+            let passes = settings.kernel.passes();
+            for (index, entry_point) in passes.iter().enumerate() {
+                let pipeline = self
+                    .pipelines
+                    .iter()
+                    .find(|(name, _)| name == entry_point)
+                    .map(|(_, pipeline)| pipeline)
+                    .expect("a pipeline for every entry point");
+                let bind_group = match (index == 0, index + 1 == passes.len()) {
+                    (true, true) => &self.direct_bind_group,
+                    (true, false) => &self.to_intermediate_bind_group,
+                    (false, _) => &self.from_intermediate_bind_group,
+                };
+
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.dispatch_workgroups((self.width + 7) / 8, (self.height + 7) / 8, 1);
+            }
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.output_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback_buffer,
+                layout: compat::image_layout(self.padded_row_size, self.height),
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        gpu::map_read(
+            &self.device,
+            &self.readback_buffer,
+            self.options.frame_timeout,
+        )?;
+
+        let row_size = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row_size * self.height as usize);
+        {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            for row in data.chunks(self.padded_row_size as usize) {
+                pixels.extend_from_slice(&row[..row_size]);
+            }
+        }
+        self.readback_buffer.unmap();
+
+        Ok(ImageBuf::from_raw(
+            pixels,
+            ImageFormat::RgbaSeparate,
+            self.width as usize,
+            self.height as usize,
+        ))
+    }
+
+    fn export(&self, path: &str) {
+        let image = match self.filtered() {
+            Some(image) => image,
+            None => return,
+        };
+        if let Err(err) = image::save_buffer(
+            path,
+            image.raw_pixels(),
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+        ) {
+            eprintln!("Failed to export image to {}: {}", path, err);
+        }
+    }
+}
+
+impl Widget<FilterSettings> for ImageFilter {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _: &mut FilterSettings, _: &Env) {
+        if let Event::Command(cmd) = event {
+            if let Some(path) = cmd.get(EXPORT_IMAGE) {
+                self.export(path);
+                ctx.set_handled();
+            }
+        }
+    }
+
+    fn lifecycle(&mut self, _: &mut LifeCycleCtx, _: &LifeCycle, _: &FilterSettings, _: &Env) {}
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &FilterSettings,
+        data: &FilterSettings,
+        _: &Env,
+    ) {
+        if !old_data.same(data) {
+            self.dirty = true;
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _: &FilterSettings,
+        _: &Env,
+    ) -> Size {
+        bc.constrain_aspect_ratio(self.height as f64 / self.width as f64, self.width as f64)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &FilterSettings, _: &Env) {
+        if std::mem::take(&mut self.dirty) {
+            match self.run(data) {
+                Ok(image) => {
+                    let uploaded = image.to_image(ctx.render_ctx);
+                    self.filtered = Some((image, uploaded));
+                }
+                Err(err) => eprintln!("Image filter failed: {:?}", err),
+            }
+            while let Ok(error) = self.errors.try_recv() {
+                eprintln!("{}", error);
+            }
+        }
+
+        if let Some((_, image)) = &self.filtered {
+            ctx.draw_image(image, ctx.size().to_rect(), InterpolationMode::Bilinear);
+        }
+    }
+}
+
+/// The image's pixels as separate-alpha RGBA.
+fn to_rgba(image: &ImageBuf) -> Vec<u8> {
+    let pixels = image.raw_pixels();
+    match image.format() {
+        ImageFormat::RgbaSeparate => pixels.to_vec(),
+        ImageFormat::RgbaPremul => pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let alpha = pixel[3];
+                let unpremultiply = |value: u8| match alpha {
+                    0 => 0,
+                    _ => (value as u32 * 255 / alpha as u32).min(255) as u8,
+                };
+                [
+                    unpremultiply(pixel[0]),
+                    unpremultiply(pixel[1]),
+                    unpremultiply(pixel[2]),
+                    alpha,
+                ]
+            })
+            .collect(),
+        ImageFormat::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect(),
+        ImageFormat::Grayscale => pixels
+            .iter()
+            .flat_map(|&value| [value, value, value, 255])
+            .collect(),
+        // `ImageFormat` is non-exhaustive.
+        _ => vec![0; image.width() * image.height() * 4],
+    }
+}
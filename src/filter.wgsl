@@ -0,0 +1,107 @@
+// Image filters for `ImageFilter`. Follows `blur.wgsl`.
+
+struct Settings {
+    amount: f32,
+    black: f32,
+    white: f32,
+    gamma: f32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var output: texture_storage_2d<rgba8unorm, write>;
+
+@group(0) @binding(2)
+var<uniform> settings: Settings;
+
+@group(0) @binding(3)
+var source_sampler: sampler;
+
+fn size() -> vec2<i32> {
+    return vec2<i32>(textureDimensions(source));
+}
+
+// A source pixel, repeating the edges outwards.
+fn load(position: vec2<i32>) -> vec4<f32> {
+    return textureLoad(source, clamp(position, vec2<i32>(0), size() - 1), 0);
+}
+
+fn luminance(color: vec4<f32>) -> f32 {
+    return dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// `amount` is the radius in pixels. Blurs run as a horizontal pass into an
+// intermediate texture and a vertical one from it.
+fn blur(id: vec3<u32>, direction: vec2<f32>) {
+    let position = vec2<i32>(id.xy);
+    if (any(position >= size())) {
+        return;
+    }
+
+    let texel = 1.0 / vec2<f32>(size());
+    let uv = (vec2<f32>(position) + 0.5) * texel;
+    let color = blur_linear(source, source_sampler, uv, direction * texel, settings.amount);
+    textureStore(output, position, color);
+}
+
+@compute @workgroup_size(8, 8)
+fn blur_horizontal(@builtin(global_invocation_id) id: vec3<u32>) {
+    blur(id, vec2<f32>(1.0, 0.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn blur_vertical(@builtin(global_invocation_id) id: vec3<u32>) {
+    blur(id, vec2<f32>(0.0, 1.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn sharpen(@builtin(global_invocation_id) id: vec3<u32>) {
+    let position = vec2<i32>(id.xy);
+    if (any(position >= size())) {
+        return;
+    }
+
+    // Unsharp mask against the four neighbours, `amount` times the detail.
+    let center = load(position);
+    let neighbours = (load(position + vec2<i32>(1, 0))
+        + load(position - vec2<i32>(1, 0))
+        + load(position + vec2<i32>(0, 1))
+        + load(position - vec2<i32>(0, 1))) / 4.0;
+    let sharpened = center + (center - neighbours) * settings.amount;
+    textureStore(output, position, vec4<f32>(clamp(sharpened.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), center.a));
+}
+
+@compute @workgroup_size(8, 8)
+fn sobel(@builtin(global_invocation_id) id: vec3<u32>) {
+    let position = vec2<i32>(id.xy);
+    if (any(position >= size())) {
+        return;
+    }
+
+    var samples: array<f32, 9>;
+    for (var i = 0; i < 9; i = i + 1) {
+        samples[i] = luminance(load(position + vec2<i32>(i % 3 - 1, i / 3 - 1)));
+    }
+    let gx = samples[2] + 2.0 * samples[5] + samples[8] - samples[0] - 2.0 * samples[3] - samples[6];
+    let gy = samples[6] + 2.0 * samples[7] + samples[8] - samples[0] - 2.0 * samples[1] - samples[2];
+
+    // `amount` scales the edge strength.
+    let edge = clamp(length(vec2<f32>(gx, gy)) * settings.amount, 0.0, 1.0);
+    textureStore(output, position, vec4<f32>(vec3<f32>(edge), 1.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn levels(@builtin(global_invocation_id) id: vec3<u32>) {
+    let position = vec2<i32>(id.xy);
+    if (any(position >= size())) {
+        return;
+    }
+
+    let color = load(position);
+    let range = max(settings.white - settings.black, 0.0001);
+    let normalized = clamp((color.rgb - settings.black) / range, vec3<f32>(0.0), vec3<f32>(1.0));
+    let adjusted = pow(normalized, vec3<f32>(1.0 / max(settings.gamma, 0.01)));
+    textureStore(output, position, vec4<f32>(adjusted, color.a));
+}
@@ -0,0 +1,614 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GPU resources owned by the widget.
+//!
+//! Everything tied to a `wgpu::Device` lives in [`Gpu`], so the widget can
+//! throw it all away and start over when the device stops responding.
+
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+use wgpu::util::DeviceExt;
+
+use crate::audio::AudioFrame;
+use crate::color::ColorProfile;
+use crate::compat;
+use crate::errors::{self, GpuError, GpuErrorKind};
+use crate::params::PARAM_SLOTS;
+use crate::{GpuCapabilities, GpuOptions};
+
+/// The format the scene renders to: linear, and with headroom for colors
+/// outside the sRGB gamut.
+pub const SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// The format frames are read back in, after the present pass.
+pub(crate) const OUTPUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// How often to check on a frame being read back.
+const POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+const VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [0.0, 0.5, 0.0],
+        color: [1.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [-0.5, -0.5, 0.0],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, -0.5, 0.0],
+        color: [0.0, 0.0, 1.0],
+    },
+];
+
+/// Per-frame values shared with the shader, see `Globals` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct Globals {
+    pub(crate) time: f32,
+    /// 1.0 when `theme::HIGH_CONTRAST` is set.
+    pub(crate) high_contrast: f32,
+    /// Index of the sample being accumulated, see `WgpuWidget::with_accumulation`.
+    pub(crate) sample: f32,
+    /// Copies of the scene to draw, see `WgpuWidget::with_instance_param`.
+    pub(crate) instances: f32,
+    /// From 0 to 1 across the widget, see `WgpuWidget::with_cursor_input`.
+    pub(crate) cursor: [f32; 2],
+    /// Offset of the sample in clip space, zero except in `still::EXPORT_STILL`.
+    pub(crate) jitter: [f32; 2],
+    /// Scale then offset from the whole image's clip space to the target's,
+    /// other than `IDENTITY_TILE` only in `still::EXPORT_PRINT`.
+    pub(crate) tile: [f32; 4],
+}
+
+pub(crate) const IDENTITY_TILE: [f32; 4] = [1.0, 1.0, 0.0, 0.0];
+
+impl Default for Globals {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            high_contrast: 0.0,
+            sample: 0.0,
+            instances: 0.0,
+            cursor: [0.0; 2],
+            jitter: [0.0; 2],
+            tile: IDENTITY_TILE,
+        }
+    }
+}
+
+/// Parameters packed for the shader, see `Params` in `shader.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct ParamUniforms {
+    pub(crate) slots: [[f32; 4]; PARAM_SLOTS],
+}
+
+/// Uniforms of the present pass, see `Present` in `present.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PresentUniforms {
+    srgb_matrix: [[f32; 4]; 3],
+}
+
+/// Why the output buffer couldn't be read back.
+#[derive(Debug)]
+pub(crate) enum ReadbackError {
+    /// The GPU didn't finish the frame in time. The device may be hung.
+    Timeout,
+    /// wgpu refused to map the buffer, usually because the device was lost.
+    MapFailed,
+}
+
+/// Request a device for `options`, reporting a missing adapter or a
+/// refused device as a `GpuErrorKind::Unavailable` error. Also returns the
+/// adapter's description and what it can do.
+pub(crate) async fn try_request_device(
+    options: &GpuOptions,
+) -> Result<
+    (
+        wgpu::Device,
+        wgpu::Queue,
+        wgpu::AdapterInfo,
+        GpuCapabilities,
+    ),
+    GpuError,
+> {
+    let unavailable = |message: String| GpuError {
+        kind: GpuErrorKind::Unavailable,
+        message,
+        frame: None,
+    };
+
+    let instance = wgpu::Instance::new(options.backends);
+    let adapter = match &options.adapter {
+        Some(name) => {
+            let name = name.to_lowercase();
+            instance
+                .enumerate_adapters(options.backends)
+                .find(|adapter| adapter.get_info().name.to_lowercase().contains(&name))
+                .ok_or_else(|| unavailable(format!("no adapter named like {:?}", name)))?
+        }
+        None => instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: options.power_preference,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or_else(|| unavailable("no compatible adapter found".to_string()))?,
+    };
+
+    let info = adapter.get_info();
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                features: options.features(&adapter),
+                limits: options.limits(&info),
+                label: None,
+            },
+            None, // Trace path
+        )
+        .await
+        .map_err(|err| unavailable(format!("{}: {}", info.name, err)))?;
+    let capabilities = GpuCapabilities::new(&adapter, &device);
+    Ok((device, queue, info, capabilities))
+}
+
+/// Whether `adapter` rasterizes on the CPU, like llvmpipe or WARP.
+pub(crate) fn is_software(adapter: &wgpu::AdapterInfo) -> bool {
+    const SOFTWARE_NAMES: [&str; 4] = ["llvmpipe", "softpipe", "swiftshader", "basic render"];
+
+    let name = adapter.name.to_lowercase();
+    adapter.device_type == wgpu::DeviceType::Cpu
+        || SOFTWARE_NAMES
+            .iter()
+            .any(|software| name.contains(software))
+}
+
+/// The output buffer's size for a `width` x `height` frame: both padded to
+/// 256 pixels, which keeps rows aligned for the copy.
+pub(crate) fn padded_output_size(width: u32, height: u32) -> (u32, u32) {
+    let padded = |size: u32| (size + 255) / 256 * 256;
+    (padded(width), padded(height))
+}
+
+/// The first `height` rows of a padded readback, `row_size` bytes each.
+pub(crate) fn unpadded_rows(
+    data: &[u8],
+    row_size: usize,
+    padded_row_size: usize,
+    height: u32,
+) -> impl Iterator<Item = &[u8]> {
+    data.chunks(padded_row_size)
+        .take(height as usize)
+        .map(move |row| &row[..row_size])
+}
+
+/// Map `buffer` for reading, giving up after `timeout`.
+///
+/// This polls instead of waiting on the device, since a wait on a hung
+/// submission never returns.
+pub(crate) fn map_read(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    timeout: Duration,
+) -> Result<(), ReadbackError> {
+    let (tx, rx) = mpsc::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+
+    let start = Instant::now();
+    loop {
+        device.poll(wgpu::Maintain::Poll);
+        match rx.try_recv() {
+            Ok(result) => return result.map_err(|_| ReadbackError::MapFailed),
+            Err(TryRecvError::Disconnected) => return Err(ReadbackError::MapFailed),
+            Err(TryRecvError::Empty) => (),
+        }
+        if start.elapsed() >= timeout {
+            return Err(ReadbackError::Timeout);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+pub(crate) struct Gpu {
+    pub(crate) device: wgpu::Device,
+    pub(crate) queue: wgpu::Queue,
+    pub(crate) render_pipeline: wgpu::RenderPipeline,
+    /// Missing when the adapter can't draw lines as a polygon mode.
+    pub(crate) wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    /// Kept to build variants of the scene pipeline, like masked ones.
+    scene_shader: wgpu::ShaderModule,
+    pub(crate) render_pipeline_layout: wgpu::PipelineLayout,
+    pub(crate) vertex_buffer: wgpu::Buffer,
+    pub(crate) num_vertices: u32,
+    pub(crate) globals_buffer: wgpu::Buffer,
+    #[cfg(feature = "audio")]
+    pub(crate) audio_buffer: wgpu::Buffer,
+    pub(crate) params_buffer: wgpu::Buffer,
+    pub(crate) globals_bind_group: wgpu::BindGroup,
+    present_pipeline: wgpu::RenderPipeline,
+    present_bind_group_layout: wgpu::BindGroupLayout,
+    present_buffer: wgpu::Buffer,
+    pub(crate) errors: Receiver<GpuError>,
+    pub(crate) adapter: wgpu::AdapterInfo,
+    pub(crate) capabilities: GpuCapabilities,
+    pub(crate) output_buffer: wgpu::Buffer,
+    pub(crate) output_buffer_width: u32,
+    pub(crate) output_buffer_height: u32,
+}
+
+impl Gpu {
+    pub(crate) async fn try_new(options: &GpuOptions) -> Result<Self, GpuError> {
+        let num_vertices = VERTICES.len() as u32;
+        let (device, queue, adapter, capabilities) = try_request_device(options).await?;
+        let errors = errors::capture_uncaptured(&device);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+        });
+
+        let globals_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Globals Buffer"),
+            contents: bytemuck::bytes_of(&Globals::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let audio_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Audio Buffer"),
+            contents: bytemuck::bytes_of(&AudioFrame::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Params Buffer"),
+            contents: bytemuck::bytes_of(&ParamUniforms {
+                slots: [[0.0; 4]; PARAM_SLOTS],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let globals_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Globals Bind Group Layout"),
+                entries: &[uniform_entry(0), uniform_entry(1), uniform_entry(2)],
+            });
+
+        let globals_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Globals Bind Group"),
+            layout: &globals_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: globals_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: audio_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&globals_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = Gpu::create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader,
+            wgpu::PolygonMode::Fill,
+            None,
+        );
+        let wireframe_pipeline = device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+            .then(|| {
+                Gpu::create_render_pipeline(
+                    &device,
+                    &render_pipeline_layout,
+                    &shader,
+                    wgpu::PolygonMode::Line,
+                    None,
+                )
+            });
+
+        let present_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Present Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("present.wgsl").into()),
+        });
+
+        let present_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Present Buffer"),
+            contents: bytemuck::bytes_of(&PresentUniforms {
+                srgb_matrix: ColorProfile::default().srgb_matrix(),
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let present_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Present Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let present_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Present Pipeline Layout"),
+                bind_group_layouts: &[&present_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let present_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Present Pipeline"),
+            layout: Some(&present_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &present_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &present_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: OUTPUT_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let output_buffer = Gpu::create_output_buffer(&device, 256, 256);
+
+        Ok(Self {
+            device,
+            queue,
+            render_pipeline,
+            wireframe_pipeline,
+            scene_shader: shader,
+            render_pipeline_layout,
+            vertex_buffer,
+            num_vertices,
+            globals_buffer,
+            #[cfg(feature = "audio")]
+            audio_buffer,
+            params_buffer,
+            globals_bind_group,
+            present_pipeline,
+            present_bind_group_layout,
+            present_buffer,
+            errors,
+            adapter,
+            capabilities,
+            output_buffer,
+            output_buffer_width: 256,
+            output_buffer_height: 256,
+        })
+    }
+
+    pub(crate) fn set_color_profile(&self, profile: ColorProfile) {
+        let uniforms = PresentUniforms {
+            srgb_matrix: profile.srgb_matrix(),
+        };
+        self.queue
+            .write_buffer(&self.present_buffer, 0, bytemuck::bytes_of(&uniforms));
+    }
+
+    /// Convert the rendered `scene` to the color profile into `target`.
+    pub(crate) fn encode_present(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        scene: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+    ) {
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Present Bind Group"),
+            layout: &self.present_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(scene),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.present_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Present Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: compat::clear(wgpu::Color::TRANSPARENT),
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.present_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Grow or shrink the output buffer to hold `width` x `height` pixels.
+    pub(crate) fn resize_output_buffer(&mut self, width: u32, height: u32) {
+        if width != self.output_buffer_width || height != self.output_buffer_height {
+            self.output_buffer_width = width;
+            self.output_buffer_height = height;
+            self.output_buffer = Gpu::create_output_buffer(&self.device, width, height);
+        }
+    }
+
+    /// The scene pipeline for passes with a `depth_stencil` attachment.
+    pub(crate) fn create_scene_pipeline(
+        &self,
+        polygon_mode: wgpu::PolygonMode,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        Gpu::create_render_pipeline(
+            &self.device,
+            &self.render_pipeline_layout,
+            &self.scene_shader,
+            polygon_mode,
+            depth_stencil,
+        )
+    }
+
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        polygon_mode: wgpu::PolygonMode,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: SCENE_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn create_output_buffer(
+        device: &wgpu::Device,
+        buffer_width: u32,
+        buffer_height: u32,
+    ) -> wgpu::Buffer {
+        let u32_size = std::mem::size_of::<u32>() as u32;
+
+        let output_buffer_size = (u32_size * buffer_width * buffer_height) as wgpu::BufferAddress;
+        let output_buffer_desc = wgpu::BufferDescriptor {
+            size: output_buffer_size,
+            usage: wgpu::BufferUsages::COPY_DST
+            // this tells wpgu that we want to read this buffer from the cpu
+            | wgpu::BufferUsages::MAP_READ,
+            label: None,
+            mapped_at_creation: false,
+        };
+        device.create_buffer(&output_buffer_desc)
+    }
+}
@@ -0,0 +1,32 @@
+// Color grading with a 3D LUT, see `PostEffect::color_grade`.
+
+@group(1) @binding(0)
+var lut: texture_3d<f32>;
+
+@group(1) @binding(1)
+var lut_sampler: sampler;
+
+fn to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3<f32>(0.0031308));
+}
+
+fn to_linear(srgb: vec3<f32>) -> vec3<f32> {
+    let low = srgb / 12.92;
+    let high = pow((srgb + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, srgb <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_main(in: PostVertex) -> @location(0) vec4<f32> {
+    let color = textureSample(frame, frame_sampler, in.uv);
+
+    // .cube files are authored against display-encoded values. Sample at
+    // texel centers so the ends of the range hit the first and last entries.
+    let size = f32(textureDimensions(lut).x);
+    let encoded = to_srgb(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+    let coord = (encoded * (size - 1.0) + 0.5) / size;
+    let graded = textureSample(lut, lut_sampler, coord).rgb;
+    return vec4<f32>(to_linear(graded), color.a);
+}
@@ -0,0 +1,785 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A widget that lays out and draws graphs on the GPU.
+//!
+//! [`GraphView`] keeps node positions in storage buffers and steps a
+//! force-directed layout in a compute shader while [`GraphState::running`]
+//! is set. Nodes can be picked and dragged with the mouse; the layout
+//! carries on around a dragged node, which stays pinned under the cursor.
+
+use std::sync::mpsc::Receiver;
+
+use druid::piet::{ImageFormat, InterpolationMode, PietImage};
+use druid::widget::prelude::*;
+use druid::{Data, ImageBuf, Lens, Point};
+use wgpu::util::DeviceExt;
+
+use crate::compat;
+use crate::errors::{self, GpuError};
+use crate::gpu::{self, ReadbackError, OUTPUT_FORMAT};
+use crate::rng::Rng;
+use crate::theme;
+use crate::GpuOptions;
+
+/// Marks no node in `Simulation::dragged` and `View::selected`.
+const NO_NODE: u32 = u32::MAX;
+
+/// Threads per workgroup of `step_layout`.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Radius of a node as drawn and picked, in logical pixels.
+const NODE_RADIUS: f64 = 6.0;
+
+#[derive(Clone, Debug, Data, Lens)]
+pub struct GraphState {
+    /// The node last clicked.
+    pub selected: Option<u32>,
+    /// Whether the layout is being stepped.
+    pub running: bool,
+}
+
+impl GraphState {
+    pub fn new() -> Self {
+        Self {
+            selected: None,
+            running: true,
+        }
+    }
+}
+
+impl Default for GraphState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A node as laid out in `graph_layout.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Node {
+    position: [f32; 2],
+    velocity: [f32; 2],
+}
+
+/// See `Simulation` in `graph_layout.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimulationUniforms {
+    node_count: u32,
+    dragged: u32,
+    repulsion: f32,
+    spring_length: f32,
+    drag_position: [f32; 2],
+    stiffness: f32,
+    damping: f32,
+    dt: f32,
+    _padding: [f32; 3],
+}
+
+/// See `View` in `graph.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ViewUniforms {
+    size: [f32; 2],
+    zoom: f32,
+    node_radius: f32,
+    selected: u32,
+    _padding: [u32; 3],
+}
+
+/// The texture drawn into and the buffer it's read back through, sized to
+/// the widget.
+struct Target {
+    texture: wgpu::Texture,
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_row_size: u32,
+}
+
+impl Target {
+    fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Graph Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OUTPUT_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row_size = (width * 4 + alignment - 1) / alignment * alignment;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Graph Readback Buffer"),
+            size: (padded_row_size * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            texture,
+            buffer,
+            width,
+            height,
+            padded_row_size,
+        }
+    }
+}
+
+pub struct GraphView {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    options: GpuOptions,
+    node_count: u32,
+    edge_count: u32,
+    layout_pipeline: wgpu::ComputePipeline,
+    edge_pipeline: wgpu::RenderPipeline,
+    node_pipeline: wgpu::RenderPipeline,
+    node_buffers: [wgpu::Buffer; 2],
+    /// Steps from `node_buffers[i]` into the other one.
+    layout_bind_groups: [wgpu::BindGroup; 2],
+    /// Draws from `node_buffers[i]`.
+    render_bind_groups: [wgpu::BindGroup; 2],
+    /// Which of `node_buffers` holds the latest positions.
+    current: usize,
+    simulation_buffer: wgpu::Buffer,
+    view_buffer: wgpu::Buffer,
+    positions_buffer: wgpu::Buffer,
+    target: Option<Target>,
+    errors: Receiver<GpuError>,
+    /// Node positions as of the last paint, for picking.
+    positions: Vec<[f32; 2]>,
+    /// Pixels per layout unit.
+    zoom: f64,
+    /// The node being dragged and where to, in layout units.
+    drag: Option<(u32, [f32; 2])>,
+    /// Whether to step the layout in the next paint.
+    step: bool,
+    image: Option<PietImage>,
+}
+
+impl GraphView {
+    /// A view of `node_count` nodes joined by `edges`, pairs of node
+    /// indices. Edges naming a node past `node_count` are left out, with a
+    /// warning.
+    pub async fn new(node_count: u32, edges: &[(u32, u32)]) -> Self {
+        Self::with_options(node_count, edges, GpuOptions::default()).await
+    }
+
+    /// Panics when there's no usable GPU; see [`try_with_options`].
+    ///
+    /// [`try_with_options`]: GraphView::try_with_options
+    pub async fn with_options(node_count: u32, edges: &[(u32, u32)], options: GpuOptions) -> Self {
+        match Self::try_with_options(node_count, edges, options).await {
+            Ok(view) => view,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub async fn try_new(node_count: u32, edges: &[(u32, u32)]) -> Result<Self, GpuError> {
+        Self::try_with_options(node_count, edges, GpuOptions::default()).await
+    }
+
+    /// Fails with a [`GpuErrorKind::Unavailable`] error when no adapter can
+    /// run the layout, so apps can show their own error state instead.
+    ///
+    /// [`GpuErrorKind::Unavailable`]: crate::GpuErrorKind::Unavailable
+    pub async fn try_with_options(
+        node_count: u32,
+        edges: &[(u32, u32)],
+        options: GpuOptions,
+    ) -> Result<Self, GpuError> {
+        let (device, queue, _, _) = gpu::try_request_device(&options).await?;
+        let errors = errors::capture_uncaptured(&device);
+
+        // Start from a seeded scatter, so the same graph lays out the same way.
+        let mut rng = Rng::new(node_count);
+        let spread = (node_count as f32).sqrt();
+        let nodes: Vec<Node> = (0..node_count)
+            .map(|_| Node {
+                position: [rng.range(-spread, spread), rng.range(-spread, spread)],
+                velocity: [0.0; 2],
+            })
+            .collect();
+        let positions = nodes.iter().map(|node| node.position).collect();
+
+        let (edges, invalid): (Vec<(u32, u32)>, Vec<(u32, u32)>) = edges
+            .iter()
+            .copied()
+            .partition(|&(a, b)| a < node_count && b < node_count);
+        if !invalid.is_empty() {
+            eprintln!(
+                "Left out {} graph edges naming nodes past {}, the first {:?}",
+                invalid.len(),
+                node_count,
+                invalid[0]
+            );
+        }
+
+        // Neighbor lists, both ways round, in compressed rows.
+        let mut degrees = vec![0u32; node_count as usize];
+        for &(a, b) in &edges {
+            degrees[a as usize] += 1;
+            degrees[b as usize] += 1;
+        }
+        let mut offsets = Vec::with_capacity(node_count as usize + 1);
+        offsets.push(0u32);
+        for degree in &degrees {
+            offsets.push(offsets[offsets.len() - 1] + degree);
+        }
+        let mut fill = offsets.clone();
+        let mut neighbors = vec![0u32; edges.len() * 2];
+        for &(a, b) in &edges {
+            neighbors[fill[a as usize] as usize] = b;
+            fill[a as usize] += 1;
+            neighbors[fill[b as usize] as usize] = a;
+            fill[b as usize] += 1;
+        }
+        let edge_pairs: Vec<[u32; 2]> = edges.iter().map(|&(a, b)| [a, b]).collect();
+
+        // Storage buffers can't be empty, and an empty graph still binds
+        // them as arrays, so pad them to a whole `Node`, the largest stride.
+        let storage = |label, contents: &[u8], usage| {
+            let padding = [0u8; std::mem::size_of::<Node>()];
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: if contents.is_empty() {
+                    &padding
+                } else {
+                    contents
+                },
+                usage: wgpu::BufferUsages::STORAGE | usage,
+            })
+        };
+        let node_usage = wgpu::BufferUsages::COPY_SRC;
+        let node_buffers = [
+            storage(
+                "Graph Node Buffer",
+                bytemuck::cast_slice(&nodes),
+                node_usage,
+            ),
+            storage(
+                "Graph Node Buffer",
+                bytemuck::cast_slice(&nodes),
+                node_usage,
+            ),
+        ];
+        let offsets_buffer = storage(
+            "Graph Offsets Buffer",
+            bytemuck::cast_slice(&offsets),
+            wgpu::BufferUsages::empty(),
+        );
+        let neighbors_buffer = storage(
+            "Graph Neighbors Buffer",
+            bytemuck::cast_slice(&neighbors),
+            wgpu::BufferUsages::empty(),
+        );
+        let edges_buffer = storage(
+            "Graph Edges Buffer",
+            bytemuck::cast_slice(&edge_pairs),
+            wgpu::BufferUsages::empty(),
+        );
+
+        let simulation_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Graph Simulation Buffer"),
+            size: std::mem::size_of::<SimulationUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Graph View Buffer"),
+            size: std::mem::size_of::<ViewUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let positions_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Graph Positions Buffer"),
+            size: node_buffers[0].size(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding, visibility, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let layout_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Graph Layout Bind Group Layout"),
+            entries: &[
+                storage_entry(0, compute, true),
+                storage_entry(1, compute, false),
+                storage_entry(2, compute, true),
+                storage_entry(3, compute, true),
+                uniform_entry(4, compute),
+            ],
+        });
+        let layout_bind_group = |from: usize| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Graph Layout Bind Group"),
+                layout: &layout_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: node_buffers[from].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: node_buffers[1 - from].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: offsets_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: neighbors_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: simulation_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let layout_bind_groups = [layout_bind_group(0), layout_bind_group(1)];
+
+        let drawing = wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT;
+        let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Graph Render Bind Group Layout"),
+            entries: &[
+                storage_entry(0, wgpu::ShaderStages::VERTEX, true),
+                storage_entry(1, wgpu::ShaderStages::VERTEX, true),
+                uniform_entry(2, drawing),
+            ],
+        });
+        let render_bind_group = |from: usize| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Graph Render Bind Group"),
+                layout: &render_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: node_buffers[from].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: edges_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: view_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let render_bind_groups = [render_bind_group(0), render_bind_group(1)];
+
+        let layout_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Graph Layout Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("graph_layout.wgsl").into()),
+        });
+        let layout_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Graph Layout Pipeline Layout"),
+                bind_group_layouts: &[&layout_layout],
+                push_constant_ranges: &[],
+            });
+        let layout_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Graph Layout Pipeline"),
+            layout: Some(&layout_pipeline_layout),
+            module: &layout_shader,
+            entry_point: "step_layout",
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Graph Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("graph.wgsl").into()),
+        });
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Graph Render Pipeline Layout"),
+                bind_group_layouts: &[&render_layout],
+                push_constant_ranges: &[],
+            });
+        let create_pipeline = |label, vertex, fragment, topology| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vertex,
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fragment,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: OUTPUT_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let edge_pipeline = create_pipeline(
+            "Graph Edge Pipeline",
+            "vs_edge",
+            "fs_edge",
+            wgpu::PrimitiveTopology::LineList,
+        );
+        let node_pipeline = create_pipeline(
+            "Graph Node Pipeline",
+            "vs_node",
+            "fs_node",
+            wgpu::PrimitiveTopology::TriangleStrip,
+        );
+
+        Ok(Self {
+            device,
+            queue,
+            options,
+            node_count,
+            edge_count: edges.len() as u32,
+            layout_pipeline,
+            edge_pipeline,
+            node_pipeline,
+            node_buffers,
+            layout_bind_groups,
+            render_bind_groups,
+            current: 0,
+            simulation_buffer,
+            view_buffer,
+            positions_buffer,
+            target: None,
+            errors,
+            positions,
+            zoom: 40.0,
+            drag: None,
+            step: true,
+            image: None,
+        })
+    }
+
+    /// Node positions in layout units as of the last paint, with the origin
+    /// at the center of the widget and y pointing up.
+    pub fn positions(&self) -> &[[f32; 2]] {
+        &self.positions
+    }
+
+    /// Where `point`, in widget coordinates, is in layout units.
+    fn to_layout(&self, size: Size, point: Point) -> [f32; 2] {
+        let offset = point - size.to_rect().center();
+        [
+            (offset.x / self.zoom) as f32,
+            (-offset.y / self.zoom) as f32,
+        ]
+    }
+
+    /// The node drawn under `point`, if any, preferring the one on top.
+    fn pick(&self, size: Size, point: Point) -> Option<u32> {
+        let [x, y] = self.to_layout(size, point);
+        let radius = (NODE_RADIUS / self.zoom) as f32;
+        self.positions
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, [px, py])| (px - x).powi(2) + (py - y).powi(2) <= radius * radius)
+            .map(|(index, _)| index as u32)
+    }
+
+    /// Step the layout if asked to, draw the graph and read back the frame
+    /// and the node positions.
+    fn render(
+        &mut self,
+        selected: Option<u32>,
+        clear_color: wgpu::Color,
+    ) -> Result<ImageBuf, ReadbackError> {
+        let target = self.target.as_ref().expect("a target sized in paint");
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Graph Encoder"),
+            });
+
+        if std::mem::take(&mut self.step) && self.node_count > 0 {
+            let (dragged, drag_position) = self.drag.unwrap_or((NO_NODE, [0.0; 2]));
+            let simulation = SimulationUniforms {
+                node_count: self.node_count,
+                dragged,
+                repulsion: 1.0,
+                spring_length: 1.0,
+                drag_position,
+                stiffness: 2.0,
+                damping: 0.9,
+                dt: 1.0 / 60.0,
+                _padding: [0.0; 3],
+            };
+            self.queue
+                .write_buffer(&self.simulation_buffer, 0, bytemuck::bytes_of(&simulation));
+
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Graph Layout Pass"),
+                });
+                pass.set_pipeline(&self.layout_pipeline);
+                pass.set_bind_group(0, &self.layout_bind_groups[self.current], &[]);
+                pass.dispatch_workgroups(
+                    (self.node_count + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE,
+                    1,
+                    1,
+                );
+            }
+            self.current = 1 - self.current;
+        }
+
+        let view = ViewUniforms {
+            size: [target.width as f32, target.height as f32],
+            zoom: self.zoom as f32,
+            node_radius: NODE_RADIUS as f32,
+            selected: selected.unwrap_or(NO_NODE),
+            _padding: [0; 3],
+        };
+        self.queue
+            .write_buffer(&self.view_buffer, 0, bytemuck::bytes_of(&view));
+
+        let target_view = target.texture.create_view(&Default::default());
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Graph Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target_view,
+                    resolve_target: None,
+                    ops: compat::clear(clear_color),
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_bind_group(0, &self.render_bind_groups[self.current], &[]);
+            render_pass.set_pipeline(&self.edge_pipeline);
+            render_pass.draw(0..2, 0..self.edge_count);
+            render_pass.set_pipeline(&self.node_pipeline);
+            render_pass.draw(0..4, 0..self.node_count);
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &target.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &target.buffer,
+                layout: compat::image_layout(target.padded_row_size, target.height),
+            },
+            wgpu::Extent3d {
+                width: target.width,
+                height: target.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        // An empty graph has no positions to read back.
+        let read_positions = self.node_count > 0;
+        if read_positions {
+            encoder.copy_buffer_to_buffer(
+                &self.node_buffers[self.current],
+                0,
+                &self.positions_buffer,
+                0,
+                self.positions_buffer.size(),
+            );
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // A buffer left mapped, or with its map still pending, would fail
+        // the next frame's copy into it.
+        if let Err(err) = gpu::map_read(&self.device, &target.buffer, self.options.frame_timeout) {
+            target.buffer.unmap();
+            return Err(err);
+        }
+        if read_positions {
+            if let Err(err) = gpu::map_read(
+                &self.device,
+                &self.positions_buffer,
+                self.options.frame_timeout,
+            ) {
+                target.buffer.unmap();
+                self.positions_buffer.unmap();
+                return Err(err);
+            }
+        }
+
+        if read_positions {
+            {
+                let data = self.positions_buffer.slice(..).get_mapped_range();
+                let nodes: &[Node] = bytemuck::cast_slice(&data[..]);
+                self.positions.clear();
+                self.positions.extend(
+                    nodes
+                        .iter()
+                        .take(self.node_count as usize)
+                        .map(|node| node.position),
+                );
+            }
+            self.positions_buffer.unmap();
+        }
+
+        let row_size = (target.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row_size * target.height as usize);
+        {
+            let data = target.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(target.padded_row_size as usize) {
+                pixels.extend_from_slice(&row[..row_size]);
+            }
+        }
+        target.buffer.unmap();
+
+        Ok(ImageBuf::from_raw(
+            pixels,
+            ImageFormat::RgbaPremul,
+            target.width as usize,
+            target.height as usize,
+        ))
+    }
+}
+
+impl Widget<GraphState> for GraphView {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut GraphState, env: &Env) {
+        match event {
+            Event::WindowConnected => {
+                if data.running {
+                    ctx.request_anim_frame();
+                }
+            }
+            Event::AnimFrame(_) => {
+                if data.running {
+                    self.step = true;
+                    ctx.request_paint();
+                    ctx.request_anim_frame();
+                }
+            }
+            Event::MouseDown(mouse) => {
+                if let Some(node) = self.pick(ctx.size(), mouse.pos) {
+                    data.selected = Some(node);
+                    self.drag = Some((node, self.to_layout(ctx.size(), mouse.pos)));
+                    ctx.set_active(true);
+                }
+            }
+            Event::MouseMove(mouse) => {
+                if let Some((node, _)) = self.drag {
+                    self.drag = Some((node, self.to_layout(ctx.size(), mouse.pos)));
+                    // Keep up with the mouse even while the layout is paused.
+                    self.step = true;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseUp(_) => {
+                if self.drag.take().is_some() {
+                    ctx.set_active(false);
+                }
+            }
+            Event::Wheel(mouse) => {
+                self.zoom = (self.zoom * 1.1f64.powf(-mouse.wheel_delta.y / 100.0)).clamp(1.0, 1e4);
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            _ => (),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &GraphState,
+        env: &Env,
+    ) {
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &GraphState, data: &GraphState, env: &Env) {
+        if data.running && !old_data.running {
+            ctx.request_anim_frame();
+        }
+        if old_data.selected != data.selected {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &GraphState,
+        env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &GraphState, env: &Env) {
+        let size = ctx.size();
+        let width = (size.width.ceil() as u32).max(1);
+        let height = (size.height.ceil() as u32).max(1);
+        if !matches!(&self.target, Some(target) if target.width == width && target.height == height)
+        {
+            self.target = Some(Target::new(&self.device, width, height));
+        }
+
+        let background = env
+            .try_get(theme::VIEWPORT_BACKGROUND)
+            .unwrap_or(theme::DARK_BACKGROUND);
+        match self.render(data.selected, theme::to_linear(&background)) {
+            Ok(image) => self.image = Some(image.to_image(ctx.render_ctx)),
+            Err(err) => eprintln!("Graph rendering failed: {:?}", err),
+        }
+        while let Ok(error) = self.errors.try_recv() {
+            eprintln!("{}", error);
+        }
+
+        if let Some(image) = &self.image {
+            ctx.draw_image(image, size.to_rect(), InterpolationMode::NearestNeighbor);
+        }
+    }
+}
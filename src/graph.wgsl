@@ -0,0 +1,74 @@
+// Drawing for `GraphView`: edges as lines, nodes as instanced circles.
+
+struct Node {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+};
+
+struct View {
+    // In pixels.
+    size: vec2<f32>,
+    // Pixels per layout unit.
+    zoom: f32,
+    // In pixels.
+    node_radius: f32,
+    // The highlighted node, or 0xffffffff for none.
+    selected: u32,
+};
+
+@group(0) @binding(0)
+var<storage, read> nodes: array<Node>;
+
+@group(0) @binding(1)
+var<storage, read> edges: array<vec2<u32>>;
+
+@group(0) @binding(2)
+var<uniform> view: View;
+
+fn to_clip(position: vec2<f32>) -> vec2<f32> {
+    return position * view.zoom * 2.0 / view.size * vec2<f32>(1.0, -1.0);
+}
+
+@vertex
+fn vs_edge(@builtin(vertex_index) vertex: u32, @builtin(instance_index) edge: u32) -> @builtin(position) vec4<f32> {
+    let ends = edges[edge];
+    let node = select(ends.x, ends.y, vertex == 1u);
+    return vec4<f32>(to_clip(nodes[node].position), 0.0, 1.0);
+}
+
+@fragment
+fn fs_edge() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.25, 0.25, 0.3, 1.0);
+}
+
+struct NodeVertex {
+    @builtin(position) position: vec4<f32>,
+    // -1 to 1 across the node's quad.
+    @location(0) corner: vec2<f32>,
+    @location(1) @interpolate(flat) selected: u32,
+};
+
+@vertex
+fn vs_node(@builtin(vertex_index) vertex: u32, @builtin(instance_index) node: u32) -> NodeVertex {
+    // A triangle strip of four corners.
+    let corner = vec2<f32>(f32(vertex & 1u), f32((vertex >> 1u) & 1u)) * 2.0 - 1.0;
+    let center = to_clip(nodes[node].position);
+
+    var out: NodeVertex;
+    out.position = vec4<f32>(center + corner * view.node_radius * 2.0 / view.size, 0.0, 1.0);
+    out.corner = corner;
+    out.selected = u32(node == view.selected);
+    return out;
+}
+
+@fragment
+fn fs_node(in: NodeVertex) -> @location(0) vec4<f32> {
+    let distance = length(in.corner);
+    if (distance > 1.0) {
+        discard;
+    }
+    let fill = select(vec3<f32>(0.3, 0.55, 0.9), vec3<f32>(1.0, 0.6, 0.1), in.selected == 1u);
+    // A darker rim so overlapping nodes stay distinct.
+    let rim = select(1.0, 0.5, distance > 0.8);
+    return vec4<f32>(fill * rim, 1.0);
+}
@@ -0,0 +1,72 @@
+// Force-directed layout for `GraphView`, one step per dispatch.
+
+struct Node {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+};
+
+struct Simulation {
+    node_count: u32,
+    // The node pinned under the mouse, or 0xffffffff for none.
+    dragged: u32,
+    repulsion: f32,
+    spring_length: f32,
+    drag_position: vec2<f32>,
+    stiffness: f32,
+    damping: f32,
+    dt: f32,
+};
+
+@group(0) @binding(0)
+var<storage, read> nodes_in: array<Node>;
+
+@group(0) @binding(1)
+var<storage, read_write> nodes_out: array<Node>;
+
+// Each node's neighbors are `neighbors[offsets[i]..offsets[i + 1]]`.
+@group(0) @binding(2)
+var<storage, read> offsets: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> neighbors: array<u32>;
+
+@group(0) @binding(4)
+var<uniform> simulation: Simulation;
+
+@compute @workgroup_size(64)
+fn step_layout(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= simulation.node_count) {
+        return;
+    }
+
+    var node = nodes_in[index];
+    if (index == simulation.dragged) {
+        node.position = simulation.drag_position;
+        node.velocity = vec2<f32>(0.0);
+        nodes_out[index] = node;
+        return;
+    }
+
+    // Every node pushes every other away, with inverse square falloff.
+    var force = vec2<f32>(0.0);
+    for (var other = 0u; other < simulation.node_count; other = other + 1u) {
+        let delta = node.position - nodes_in[other].position;
+        let distance_squared = max(dot(delta, delta), 0.0001);
+        force = force + delta * (simulation.repulsion / (distance_squared * sqrt(distance_squared)));
+    }
+
+    // Edges are springs.
+    for (var edge = offsets[index]; edge < offsets[index + 1u]; edge = edge + 1u) {
+        let delta = nodes_in[neighbors[edge]].position - node.position;
+        let distance = max(length(delta), 0.0001);
+        force = force + delta / distance * (distance - simulation.spring_length) * simulation.stiffness;
+    }
+
+    // A gentle pull to the origin keeps disconnected parts in view.
+    force = force - node.position * 0.01;
+
+    node.velocity = (node.velocity + force * simulation.dt) * simulation.damping;
+    node.position = node.position + node.velocity * simulation.dt;
+    nodes_out[index] = node;
+}
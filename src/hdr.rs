@@ -0,0 +1,131 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading back the linear scene for HDR screenshots.
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::compat;
+use crate::gpu::{self, Gpu, ReadbackError, SCENE_FORMAT};
+
+/// Bytes per texel of `SCENE_FORMAT`.
+const TEXEL_SIZE: u32 = 8;
+
+/// A copy of the scene texture on its way back to the CPU.
+pub(crate) struct HdrReadback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_row_size: u32,
+}
+
+impl HdrReadback {
+    /// Record a copy of `scene`, a `width` x `height` texture in
+    /// `SCENE_FORMAT`, into a new buffer.
+    pub(crate) fn encode(
+        gpu: &Gpu,
+        encoder: &mut wgpu::CommandEncoder,
+        scene: &wgpu::Texture,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        debug_assert_eq!(SCENE_FORMAT, wgpu::TextureFormat::Rgba16Float);
+
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row_size = (width * TEXEL_SIZE + alignment - 1) / alignment * alignment;
+        let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("HDR Readback Buffer"),
+            size: (padded_row_size * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: scene,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: compat::image_layout(padded_row_size, height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_row_size,
+        }
+    }
+
+    /// Wait for the copy and write it to `path` as an OpenEXR file.
+    pub(crate) fn save_exr(
+        self,
+        gpu: &Gpu,
+        timeout: Duration,
+        path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        gpu::map_read(&gpu.device, &self.buffer, timeout).map_err(|err| match err {
+            ReadbackError::Timeout => "timed out reading back the frame",
+            ReadbackError::MapFailed => "couldn't read back the frame",
+        })?;
+
+        let row_size = (self.width * TEXEL_SIZE) as usize;
+        let mut pixels = Vec::with_capacity((self.width * self.height * 4) as usize);
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(self.padded_row_size as usize) {
+                let halves: &[u16] = bytemuck::cast_slice(&row[..row_size]);
+                pixels.extend(halves.iter().map(|&half| f16_to_f32(half)));
+            }
+        }
+        self.buffer.unmap();
+
+        image::save_buffer(
+            path,
+            bytemuck::cast_slice(&pixels),
+            self.width,
+            self.height,
+            image::ColorType::Rgba32F,
+        )?;
+        Ok(())
+    }
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exponent = ((half as u32) >> 10) & 0x1f;
+    let mantissa = (half as u32) & 0x03ff;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        // Subnormal: scale the mantissa up into a normal f32.
+        0 => {
+            let shift = mantissa.leading_zeros() - 21;
+            sign | ((113 - shift) << 23) | ((mantissa << shift) & 0x03ff) << 13
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
@@ -0,0 +1,100 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Textures that carry over from one frame to the next.
+//!
+//! Post effects get one with `PostEffect::with_history`. A `WgpuScene`
+//! that needs its own previous frames, for TAA, motion blur or feedback,
+//! keeps a [`History`] per texture it reads back, color, depth or
+//! anything else, calls [`History::begin_frame`] from
+//! `WgpuScene::prepare` with `SceneFrame`'s size, renders into
+//! [`current`](History::current) and samples
+//! [`previous`](History::previous).
+
+/// A pair of textures swapped every frame, so a pass can read what was
+/// written last frame while writing this one.
+///
+/// The textures are created on first use and recreated, cleared, whenever
+/// the frame size changes.
+pub struct History {
+    label: &'static str,
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsages,
+    textures: Option<([wgpu::Texture; 2], wgpu::Extent3d)>,
+    current: usize,
+}
+
+impl History {
+    /// Textures of `format` that can be rendered into and sampled.
+    pub fn new(label: &'static str, format: wgpu::TextureFormat) -> Self {
+        Self {
+            label,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            textures: None,
+            current: 0,
+        }
+    }
+
+    /// Also allow `usage`, like `COPY_DST` to copy frames in or
+    /// `STORAGE_BINDING` to write them from compute passes.
+    pub fn with_usage(mut self, usage: wgpu::TextureUsages) -> Self {
+        self.usage |= usage;
+        self.textures = None;
+        self
+    }
+
+    /// Start a frame of `size`, making last frame's `current` the new
+    /// `previous`. Returns false when there is no previous frame, on the
+    /// first frame and after a resize, and `previous` is transparent black.
+    pub fn begin_frame(&mut self, device: &wgpu::Device, size: wgpu::Extent3d) -> bool {
+        if let Some((_, history_size)) = &self.textures {
+            if *history_size == size {
+                self.current = 1 - self.current;
+                return true;
+            }
+        }
+
+        let create = || {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(self.label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: self.usage,
+            })
+        };
+        self.textures = Some(([create(), create()], size));
+        self.current = 0;
+        false
+    }
+
+    /// Written this frame. Panics before the first `begin_frame`.
+    pub fn current(&self) -> &wgpu::Texture {
+        &self.textures().0[self.current]
+    }
+
+    /// Written last frame. Panics before the first `begin_frame`.
+    pub fn previous(&self) -> &wgpu::Texture {
+        &self.textures().0[1 - self.current]
+    }
+
+    fn textures(&self) -> &([wgpu::Texture; 2], wgpu::Extent3d) {
+        self.textures
+            .as_ref()
+            .expect("History used before begin_frame")
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cheaper frames while the layout around the viewport is being dragged.
+//!
+//! A druid `Split` resizes the viewport on every mouse move of its divider,
+//! and rendering each of those sizes at full quality makes the drag stutter.
+//! Wrapping the `Split` in [`DragWatcher`] sends [`INTERACTING`] while the
+//! divider is held, and the viewport follows its
+//! `WgpuWidget::with_drag_policy` until it's let go. Apps with their own
+//! draggable layouts can send [`INTERACTING`] themselves.
+
+use druid::widget::prelude::*;
+use druid::widget::Controller;
+use druid::Selector;
+
+/// Sent to the window, true when a drag resizing the viewport starts and
+/// false when it ends. Left unhandled, so every viewport in the window
+/// sees it.
+pub const INTERACTING: Selector<bool> = Selector::new("druid-wgpu.interacting");
+
+/// What the viewport renders while [`INTERACTING`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DragPolicy {
+    /// Keep rendering every frame at full quality.
+    #[default]
+    Render,
+    /// Stop rendering and stretch the last frame over the widget.
+    Pause,
+    /// Render at this fraction of the resolution, from 0.05 to 1, without
+    /// accumulation or post effects.
+    Preview(f64),
+}
+
+/// Sends [`INTERACTING`] while the wrapped widget itself is active, which
+/// for a `Split` is exactly while its divider is dragged.
+#[derive(Default)]
+pub struct DragWatcher {
+    dragging: bool,
+}
+
+impl DragWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, W: Widget<T>> Controller<T, W> for DragWatcher {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        child.event(ctx, event, data, env);
+
+        if ctx.is_active() != self.dragging {
+            self.dragging = ctx.is_active();
+            ctx.submit_command(INTERACTING.with(self.dragging));
+        }
+    }
+}
@@ -0,0 +1,278 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rebindable keyboard shortcuts for viewport actions.
+
+use druid::{KbKey, KeyEvent, Selector};
+use serde::{Deserialize, Serialize};
+
+/// Perform a viewport action, as if its shortcut had been pressed.
+pub const VIEWPORT_ACTION: Selector<ViewportAction> = Selector::new("druid-wgpu.viewport-action");
+
+/// Something the viewport can do in response to a shortcut or command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ViewportAction {
+    TogglePlayback,
+    SeekToStart,
+    /// Save the last frame as a PNG in the working directory.
+    Screenshot,
+    /// Save the next frame's linear scene, before post effects, as an
+    /// OpenEXR file in the working directory.
+    HdrScreenshot,
+    /// Draw edges only, on adapters that support line polygon mode.
+    ToggleWireframe,
+    /// Render again after a panic put the viewport in its error state.
+    RetryRender,
+}
+
+/// A key plus the modifiers that must be held with it.
+///
+/// `key` is the key's name as druid reports it ("F12", "ArrowLeft") or the
+/// character it produces; "Space" names the space bar.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub key: String,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub alt: bool,
+}
+
+impl KeyBinding {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    pub fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    pub fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// The binding for a key press, for shortcut editors that record the
+    /// next key the user presses.
+    pub fn from_event(event: &KeyEvent) -> Self {
+        let key = match &event.key {
+            KbKey::Character(c) if c == " " => "Space".to_string(),
+            key => key.to_string(),
+        };
+        Self {
+            key,
+            ctrl: event.mods.ctrl(),
+            shift: event.mods.shift(),
+            alt: event.mods.alt(),
+        }
+    }
+
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        if event.mods.ctrl() != self.ctrl
+            || event.mods.alt() != self.alt
+            // Shift is part of which character was typed, so only named
+            // keys compare it.
+            || (!matches!(event.key, KbKey::Character(_)) && event.mods.shift() != self.shift)
+        {
+            return false;
+        }
+
+        match &event.key {
+            KbKey::Character(c) if c == " " => self.key.eq_ignore_ascii_case("Space"),
+            KbKey::Character(c) => self.key.eq_ignore_ascii_case(c),
+            key => self.key == key.to_string(),
+        }
+    }
+}
+
+/// The shortcuts the viewport responds to while focused.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, ViewportAction)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new()
+            .bind(KeyBinding::new("Space"), ViewportAction::TogglePlayback)
+            .bind(KeyBinding::new("Home"), ViewportAction::SeekToStart)
+            .bind(KeyBinding::new("F12"), ViewportAction::Screenshot)
+            .bind(
+                KeyBinding::new("F12").shift(),
+                ViewportAction::HdrScreenshot,
+            )
+            .bind(KeyBinding::new("w"), ViewportAction::ToggleWireframe)
+            .bind(KeyBinding::new("r"), ViewportAction::RetryRender)
+    }
+}
+
+impl Keymap {
+    /// A keymap with no bindings.
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Add a binding, replacing any existing binding for the same keys.
+    pub fn bind(mut self, binding: KeyBinding, action: ViewportAction) -> Self {
+        self.bindings.retain(|(existing, _)| *existing != binding);
+        self.bindings.push((binding, action));
+        self
+    }
+
+    /// Remove every binding for `action`.
+    pub fn unbind(mut self, action: ViewportAction) -> Self {
+        self.bindings.retain(|(_, existing)| *existing != action);
+        self
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (&KeyBinding, ViewportAction)> {
+        self.bindings
+            .iter()
+            .map(|(binding, action)| (binding, *action))
+    }
+
+    /// The action bound to the key in `event`, if any.
+    pub fn action_for(&self, event: &KeyEvent) -> Option<ViewportAction> {
+        self.bindings
+            .iter()
+            .find(|(binding, _)| binding.matches(event))
+            .map(|(_, action)| *action)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::Modifiers;
+
+    use super::*;
+
+    fn press(key: KbKey, mods: Modifiers) -> KeyEvent {
+        KeyEvent {
+            key,
+            mods,
+            ..Default::default()
+        }
+    }
+
+    fn character(c: &str, mods: Modifiers) -> KeyEvent {
+        press(KbKey::Character(c.into()), mods)
+    }
+
+    #[test]
+    fn characters_ignore_shift_and_case() {
+        let binding = KeyBinding::new("w");
+        assert!(binding.matches(&character("w", Modifiers::empty())));
+        assert!(binding.matches(&character("W", Modifiers::SHIFT)));
+        assert!(!binding.matches(&character("q", Modifiers::empty())));
+    }
+
+    #[test]
+    fn ctrl_and_alt_must_match() {
+        let plain = KeyBinding::new("s");
+        let ctrl = KeyBinding::new("s").ctrl();
+        assert!(!plain.matches(&character("s", Modifiers::CONTROL)));
+        assert!(ctrl.matches(&character("s", Modifiers::CONTROL)));
+        assert!(!ctrl.matches(&character("s", Modifiers::empty())));
+        assert!(!plain.matches(&character("s", Modifiers::ALT)));
+        assert!(KeyBinding::new("s")
+            .alt()
+            .matches(&character("s", Modifiers::ALT)));
+    }
+
+    #[test]
+    fn named_keys_compare_shift() {
+        let binding = KeyBinding::new("F12");
+        assert!(binding.matches(&press(KbKey::F12, Modifiers::empty())));
+        assert!(!binding.matches(&press(KbKey::F12, Modifiers::SHIFT)));
+        assert!(KeyBinding::new("F12")
+            .shift()
+            .matches(&press(KbKey::F12, Modifiers::SHIFT)));
+    }
+
+    #[test]
+    fn space_has_a_name() {
+        let event = character(" ", Modifiers::empty());
+        assert!(KeyBinding::new("Space").matches(&event));
+        assert!(KeyBinding::new("space").matches(&event));
+        assert_eq!(KeyBinding::from_event(&event), KeyBinding::new("Space"));
+        assert_eq!(
+            KeyBinding::from_event(&press(KbKey::F12, Modifiers::SHIFT)),
+            KeyBinding::new("F12").shift()
+        );
+    }
+
+    #[test]
+    fn shift_f12_and_f12_are_different_actions() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action_for(&press(KbKey::F12, Modifiers::empty())),
+            Some(ViewportAction::Screenshot)
+        );
+        assert_eq!(
+            keymap.action_for(&press(KbKey::F12, Modifiers::SHIFT)),
+            Some(ViewportAction::HdrScreenshot)
+        );
+        assert_eq!(
+            keymap.action_for(&press(KbKey::F12, Modifiers::CONTROL)),
+            None
+        );
+    }
+
+    #[test]
+    fn bind_replaces_and_unbind_removes() {
+        let keymap = Keymap::new()
+            .bind(KeyBinding::new("w"), ViewportAction::ToggleWireframe)
+            .bind(KeyBinding::new("w"), ViewportAction::Screenshot)
+            .bind(KeyBinding::new("p"), ViewportAction::Screenshot);
+        assert_eq!(keymap.bindings().count(), 2);
+        assert_eq!(
+            keymap.action_for(&character("w", Modifiers::empty())),
+            Some(ViewportAction::Screenshot)
+        );
+
+        let keymap = keymap.unbind(ViewportAction::Screenshot);
+        assert_eq!(keymap.bindings().count(), 0);
+        assert_eq!(keymap.action_for(&character("w", Modifiers::empty())), None);
+    }
+
+    #[test]
+    fn round_trips_through_ron() {
+        let keymap = Keymap::default();
+        let ron = keymap.to_ron().unwrap();
+        assert_eq!(Keymap::from_ron(&ron).unwrap(), keymap);
+    }
+}
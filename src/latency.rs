@@ -0,0 +1,120 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Input latency, measured and partly hidden.
+//!
+//! Frames are read back before they're drawn, so what's on screen trails
+//! the input by at least a frame. The widget measures how far behind it
+//! is, and can extrapolate the cursor by that much for shaders that draw
+//! under it, like paint tools.
+
+use std::time::{Duration, Instant};
+
+use druid::{Point, Selector, Vec2};
+
+/// Notification sent with each new measurement of the time from an input
+/// event until a frame showing its effect was drawn.
+pub const INPUT_LATENCY: Selector<Duration> = Selector::new("druid-wgpu.input-latency");
+
+/// How much each measurement moves the average.
+const SMOOTHING: f64 = 0.1;
+
+/// About a frame: the furthest the cursor is extrapolated, and how long
+/// without a move before it's taken to have stopped.
+const PREDICTION_LIMIT: Duration = Duration::from_millis(16);
+
+/// Times input until the next changed frame.
+pub(crate) struct LatencyMeter {
+    /// The oldest input no frame has answered yet.
+    input: Option<Instant>,
+    average: Option<Duration>,
+}
+
+impl LatencyMeter {
+    pub(crate) fn new() -> Self {
+        Self {
+            input: None,
+            average: None,
+        }
+    }
+
+    pub(crate) fn input(&mut self) {
+        self.input.get_or_insert_with(Instant::now);
+    }
+
+    /// A frame was drawn. Returns the latency of the input it answered, if
+    /// it `changed` and there was one.
+    pub(crate) fn frame(&mut self, changed: bool) -> Option<Duration> {
+        let input = self.input.take()?;
+        if !changed {
+            return None;
+        }
+        let latency = input.elapsed();
+        self.average = Some(match self.average {
+            Some(average) => average.mul_f64(1.0 - SMOOTHING) + latency.mul_f64(SMOOTHING),
+            None => latency,
+        });
+        Some(latency)
+    }
+
+    /// The smoothed latency, once there's been a measurement.
+    pub(crate) fn average(&self) -> Option<Duration> {
+        self.average
+    }
+}
+
+/// Extrapolates the cursor from its last two positions.
+pub(crate) struct CursorPredictor {
+    last: Option<(Instant, Point)>,
+    /// In points per second.
+    velocity: Vec2,
+}
+
+impl CursorPredictor {
+    pub(crate) fn new() -> Self {
+        Self {
+            last: None,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    pub(crate) fn moved(&mut self, pos: Point) {
+        let now = Instant::now();
+        if let Some((time, last)) = self.last {
+            let dt = now.duration_since(time);
+            if dt > PREDICTION_LIMIT {
+                // Starting again after a stop.
+                self.velocity = Vec2::ZERO;
+            } else if dt > Duration::ZERO {
+                self.velocity = (pos - last) / dt.as_secs_f64();
+            }
+        }
+        self.last = Some((now, pos));
+    }
+
+    /// Where the cursor will be `ahead` of its last move, at most about a
+    /// frame, if it keeps going. A cursor that hasn't moved for longer than
+    /// that stays where it is.
+    pub(crate) fn predict(&self, ahead: Duration) -> Option<Point> {
+        let (time, pos) = self.last?;
+        if time.elapsed() > PREDICTION_LIMIT {
+            return Some(pos);
+        }
+        Some(pos + self.velocity * ahead.min(PREDICTION_LIMIT).as_secs_f64())
+    }
+
+    pub(crate) fn position(&self) -> Option<Point> {
+        self.last.map(|(_, pos)| pos)
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A druid widget rendering with wgpu.
+//!
+//! Frames are rendered offscreen, read back to the CPU and drawn with piet.
+
+mod accumulate;
+pub mod audio;
+mod backdrop;
+#[doc(hidden)]
+pub mod bench;
+pub mod bridge;
+pub mod bvh;
+mod capabilities;
+mod color;
+mod compat;
+pub mod diagnostics;
+pub mod effects;
+mod errors;
+#[cfg(feature = "fallback")]
+pub mod fallback;
+pub mod filter;
+mod gpu;
+pub mod graph;
+mod hdr;
+pub mod history;
+pub mod interaction;
+pub mod keymap;
+mod latency;
+pub mod mask;
+mod options;
+pub mod params;
+mod playback;
+pub mod post;
+pub mod recording;
+pub mod resources;
+pub mod rng;
+mod rulers;
+pub mod scene;
+pub mod settings;
+#[cfg(feature = "shm")]
+pub mod shm;
+pub mod sink;
+mod state;
+pub mod still;
+#[cfg(feature = "stream")]
+pub mod stream;
+mod targets;
+pub mod theme;
+pub mod timestep;
+pub mod variants;
+mod widget;
+
+pub use backdrop::WgpuBackdrop;
+pub use capabilities::GpuCapabilities;
+pub use color::ColorProfile;
+pub use errors::{GpuError, GpuErrorKind, GPU_ERROR};
+pub use latency::INPUT_LATENCY;
+pub use options::{DeviceProfile, GpuOptions, SOFTWARE_MAX_TEXTURE_SIZE};
+pub use params::{ParamValue, Params};
+pub use playback::Playback;
+pub use rulers::Guides;
+pub use state::ViewportState;
+pub use widget::{WgpuWidget, FRAME_AVAILABLE, SOFTWARE_RENDERER};
@@ -17,368 +17,463 @@
 // On Windows platform, don't show a console when opening the app.
 #![windows_subsystem = "windows"]
 
-use std::num::NonZeroU32;
-use std::time::Duration;
-use std::time::Instant;
+use std::error::Error;
+use std::path::{Path, PathBuf};
 
-use druid::piet::ImageFormat;
-use druid::piet::InterpolationMode;
 use druid::widget::prelude::*;
-use druid::widget::Align;
-use druid::widget::Container;
-use druid::widget::Label;
-use druid::widget::Split;
-use druid::ImageBuf;
-use druid::{AppLauncher, LocalizedString, TimerToken, WindowDesc};
-
-use wgpu::util::DeviceExt;
-
-static TIMER_INTERVAL: Duration = Duration::from_millis(10);
-
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct Vertex {
-    position: [f32; 3],
-    color: [f32; 3],
+use druid::widget::{Button, Checkbox, Container, Flex, Label, Slider, Split};
+use druid::{
+    AppDelegate, AppLauncher, Command, DelegateCtx, Handled, LocalizedString, Target, WidgetExt,
+    WindowDesc,
+};
+
+use druid_wgpu::effects;
+use druid_wgpu::interaction::{DragPolicy, DragWatcher};
+use druid_wgpu::post::{Lut, PostEffect};
+use druid_wgpu::recording::{GifExport, EXPORT_GIF};
+use druid_wgpu::sink::PngSequence;
+use druid_wgpu::still::{StillExport, EXPORT_STILL};
+use druid_wgpu::{
+    GpuCapabilities, GpuOptions, Params, Playback, ViewportState, WgpuWidget, GPU_ERROR,
+    SOFTWARE_RENDERER,
+};
+
+const USAGE: &str = "\
+Usage: druid-wgpu [OPTIONS]
+
+Options:
+  --backend <NAME>   vulkan, metal, dx12, dx11, gl or all
+  --adapter <NAME>   use the first adapter whose name contains NAME
+  --size <W>x<H>     initial window size, in logical pixels
+  --preset <FILE>    start with the parameters of a RON preset, instead of
+                     the ones remembered from last time
+  --record <DIR>     write every frame to DIR as numbered PNGs
+  --max-fps <N>      render at most N frames a second
+  --headless <FILE>  render the first frame to FILE as a PNG, at --size or
+                     1280x720 pixels, and exit without opening a window
+  --diagnose <FILE>  write a report on the GPU to FILE, for bug reports,
+                     and exit
+  --help             print this and exit";
+
+/// The demo's command line, see `USAGE`.
+#[derive(Default)]
+struct Args {
+    options: GpuOptions,
+    size: Option<Size>,
+    preset: Option<Params>,
+    record: Option<PathBuf>,
+    max_fps: Option<f64>,
+    headless: Option<PathBuf>,
+    diagnose: Option<PathBuf>,
 }
 
-impl Vertex {
-    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+            match arg.as_str() {
+                "--backend" => {
+                    parsed.options.backends = match value()?.to_lowercase().as_str() {
+                        "vulkan" => wgpu::Backends::VULKAN,
+                        "metal" => wgpu::Backends::METAL,
+                        "dx12" => wgpu::Backends::DX12,
+                        "dx11" => wgpu::Backends::DX11,
+                        "gl" => wgpu::Backends::GL,
+                        "all" => wgpu::Backends::all(),
+                        other => return Err(format!("unknown backend {:?}", other)),
+                    }
+                }
+                "--adapter" => parsed.options.adapter = Some(value()?),
+                "--size" => {
+                    let size = value()?;
+                    let (width, height) = size
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                        .ok_or_else(|| format!("--size {:?} isn't <W>x<H>", size))?;
+                    parsed.size = Some(Size::new(width, height));
+                }
+                "--preset" => {
+                    let path = value()?;
+                    let source = std::fs::read_to_string(&path)
+                        .map_err(|err| format!("couldn't read {}: {}", path, err))?;
+                    let params = Params::from_ron(&source)
+                        .map_err(|err| format!("couldn't load {}: {}", path, err))?;
+                    parsed.preset = Some(params);
+                }
+                "--record" => parsed.record = Some(value()?.into()),
+                "--max-fps" => {
+                    let fps = value()?;
+                    let fps = fps
+                        .parse()
+                        .ok()
+                        .filter(|fps: &f64| *fps > 0.0)
+                        .ok_or_else(|| format!("--max-fps {:?} isn't a positive number", fps))?;
+                    parsed.max_fps = Some(fps);
+                }
+                "--headless" => parsed.headless = Some(value()?.into()),
+                "--diagnose" => parsed.diagnose = Some(value()?.into()),
+                other => return Err(format!("unknown option {:?}", other)),
+            }
         }
+        Ok(parsed)
     }
 }
 
-const VERTICES: &[Vertex] = &[
-    Vertex {
-        position: [0.0, 0.5, 0.0],
-        color: [1.0, 0.0, 0.0],
-    },
-    Vertex {
-        position: [-0.5, -0.5, 0.0],
-        color: [0.0, 1.0, 0.0],
-    },
-    Vertex {
-        position: [0.5, -0.5, 0.0],
-        color: [0.0, 0.0, 1.0],
-    },
-];
-
-struct WgpuWidget {
-    timer_id: TimerToken,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    num_vertices: u32,
-    output_buffer: wgpu::Buffer,
-    output_buffer_width: u32,
-    output_buffer_height: u32,
-}
-
-impl WgpuWidget {
-    async fn new() -> Self {
-        let num_vertices = VERTICES.len() as u32;
-        let instance = wgpu::Instance::new(wgpu::Backends::all());
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None,
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
-                    label: None,
-                },
-                None, // Trace path
-            )
-            .await
-            .unwrap();
-
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[],
-            });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(VERTICES),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        let output_buffer = WgpuWidget::create_output_buffer(&device, 256, 256);
-
-        Self {
-            timer_id: TimerToken::INVALID,
-            device,
-            queue,
-            render_pipeline,
-            vertex_buffer,
-            num_vertices,
-            output_buffer,
-            output_buffer_width: 256,
-            output_buffer_height: 256,
+struct Delegate;
+
+impl AppDelegate<ViewportState> for Delegate {
+    fn command(
+        &mut self,
+        _ctx: &mut DelegateCtx,
+        _target: Target,
+        cmd: &Command,
+        _data: &mut ViewportState,
+        _env: &Env,
+    ) -> Handled {
+        if let Some(error) = cmd.get(GPU_ERROR) {
+            eprintln!("{}", error);
+            return Handled::Yes;
         }
-    }
-
-    fn create_output_buffer(
-        device: &wgpu::Device,
-        buffer_width: u32,
-        buffer_height: u32,
-    ) -> wgpu::Buffer {
-        let u32_size = std::mem::size_of::<u32>() as u32;
-
-        let output_buffer_size = (u32_size * buffer_width * buffer_height) as wgpu::BufferAddress;
-        let output_buffer_desc = wgpu::BufferDescriptor {
-            size: output_buffer_size,
-            usage: wgpu::BufferUsages::COPY_DST
-            // this tells wpgu that we want to read this buffer from the cpu
-            | wgpu::BufferUsages::MAP_READ,
-            label: None,
-            mapped_at_creation: false,
-        };
-        device.create_buffer(&output_buffer_desc)
+        if let Some(adapter) = cmd.get(SOFTWARE_RENDERER) {
+            eprintln!(
+                "Rendering on {}, a software rasterizer; expect it to be slow",
+                adapter
+            );
+            return Handled::Yes;
+        }
+        Handled::No
     }
 }
 
-impl Widget<u32> for WgpuWidget {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut u32, env: &Env) {
-        match event {
-            Event::WindowConnected => {
-                // Start the timer when the application launches
-                self.timer_id = ctx.request_timer(TIMER_INTERVAL);
-            }
-            // Event::Timer(id) => {
-            //     if *id == self.timer_id {
-            //         ctx.request_layout();
-            //         self.timer_id = ctx.request_timer(TIMER_INTERVAL);
-            //     }
-            // }
-            _ => (),
-        }
-    }
+const PRESET_PATH: &str = "preset.ron";
+const LUT_PATH: &str = "grade.cube";
+const GIF_PATH: &str = "preview.gif";
+const STILL_PATH: &str = "still.png";
+const SETTINGS_PATH: &str = "viewport.ron";
+
+/// Length of the demo's loop, in seconds.
+const DURATION: f64 = 10.0;
+/// Size of `--headless` renders without `--size`, in pixels.
+const HEADLESS_SIZE: (u32, u32) = (1280, 720);
+
+/// The shader's parameters, in `with_param_layout` order, with their slider
+/// ranges and starting values.
+const PARAMS: [(&str, f64, f64, f64); 2] = [("speed", -4.0, 4.0, 1.0), ("scale", 0.1, 2.0, 1.0)];
+
+fn default_params() -> Params {
+    PARAMS
+        .iter()
+        .fold(Params::new(), |params, &(name, _, _, value)| {
+            params.with(name, value)
+        })
+        .with("trails.decay", 0.9)
+        .with("chromatic_aberration.amount", 0.01)
+        .with("grain.amount", 0.05)
+        .with("vignette.strength", 0.5)
+        .with("vignette.radius", 0.5)
+}
 
-    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &u32, env: &Env) {}
+fn param_slider(name: &'static str, min: f64, max: f64) -> impl Widget<Params> {
+    Flex::column()
+        .with_child(Label::dynamic(move |data: &Params, _| {
+            format!("{}: {:.2}", name, data.float(name).unwrap_or_default())
+        }))
+        .with_child(
+            Slider::new()
+                .with_range(min, max)
+                .lens(Params::float_lens(name))
+                .expand_width(),
+        )
+}
 
-    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &u32, data: &u32, env: &Env) {}
+fn effect_toggle(label: &'static str, name: &'static str) -> impl Widget<Params> {
+    Checkbox::new(label).lens(Params::bool_lens(name))
+}
 
-    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &u32, env: &Env) -> Size {
-        bc.max()
-    }
+fn effects_controls() -> impl Widget<Params> {
+    Flex::column()
+        .cross_axis_alignment(druid::widget::CrossAxisAlignment::Start)
+        .with_child(effect_toggle("Trails", "trails.enabled"))
+        .with_child(param_slider("trails.decay", 0.0, 0.99))
+        .with_spacer(8.0)
+        .with_child(effect_toggle(
+            "Chromatic aberration",
+            "chromatic_aberration.enabled",
+        ))
+        .with_child(param_slider("chromatic_aberration.amount", 0.0, 0.05))
+        .with_spacer(8.0)
+        .with_child(effect_toggle("Grain", "grain.enabled"))
+        .with_child(param_slider("grain.amount", 0.0, 0.2))
+        .with_spacer(8.0)
+        .with_child(effect_toggle("Vignette", "vignette.enabled"))
+        .with_child(param_slider("vignette.strength", 0.0, 1.0))
+        .with_child(param_slider("vignette.radius", 0.0, 1.0))
+}
 
-    fn paint(&mut self, ctx: &mut PaintCtx, data: &u32, env: &Env) {
-        let i = Instant::now();
+fn params_controls() -> impl Widget<Params> {
+    let presets = Flex::row()
+        .with_child(
+            Button::new("Save preset").on_click(|_ctx, data: &mut Params, _env| {
+                let result = data
+                    .to_ron()
+                    .map_err(|err| err.to_string())
+                    .and_then(|ron| {
+                        std::fs::write(PRESET_PATH, ron).map_err(|err| err.to_string())
+                    });
+                if let Err(err) = result {
+                    eprintln!("Failed to save preset: {}", err);
+                }
+            }),
+        )
+        .with_spacer(8.0)
+        .with_child(
+            Button::new("Load preset").on_click(|_ctx, data: &mut Params, _env| {
+                let result = std::fs::read_to_string(PRESET_PATH)
+                    .map_err(|err| err.to_string())
+                    .and_then(|ron| Params::from_ron(&ron).map_err(|err| err.to_string()));
+                match result {
+                    Ok(params) => *data = params,
+                    Err(err) => eprintln!("Failed to load preset: {}", err),
+                }
+            }),
+        );
 
-        let texture_width = ctx.size().width.ceil() as u32;
-        let texture_height = ctx.size().height.ceil() as u32;
+    let mut column = Flex::column();
+    for (name, min, max, _) in PARAMS {
+        column.add_child(param_slider(name, min, max));
+        column.add_spacer(8.0);
+    }
+    column
+        .with_spacer(8.0)
+        .with_child(effects_controls())
+        .with_spacer(16.0)
+        .with_child(presets)
+}
 
-        let mut texture_width_padded = texture_width;
-        let mut texture_height_padded = texture_height;
+fn controls() -> impl Widget<ViewportState> {
+    let playback = Flex::column()
+        .with_child(Label::dynamic(|data: &Playback, _| {
+            format!("{:.2}s / {:.2}s", data.time, data.duration)
+        }))
+        .with_spacer(8.0)
+        .with_child(
+            Button::dynamic(|data: &Playback, _| {
+                if data.playing { "Pause" } else { "Play" }.into()
+            })
+            .on_click(|_ctx, data: &mut Playback, _env| data.toggle()),
+        )
+        .with_spacer(8.0)
+        .with_child(
+            Slider::new()
+                .with_range(0.0, DURATION)
+                .lens(Playback::time)
+                .expand_width(),
+        )
+        .with_spacer(8.0)
+        .with_child(
+            Button::new("Export GIF").on_click(|ctx, data: &mut Playback, _env| {
+                ctx.submit_command(EXPORT_GIF.with(GifExport::new(GIF_PATH, data.duration)));
+            }),
+        )
+        .with_spacer(8.0)
+        .with_child(
+            Button::new("Export still").on_click(|ctx, _data: &mut Playback, _env| {
+                ctx.submit_command(EXPORT_STILL.with(StillExport::new(STILL_PATH).with_scale(4)));
+            }),
+        )
+        .lens(ViewportState::playback);
+
+    let gpu = Label::dynamic(|data: &Option<GpuCapabilities>, _| match data {
+        Some(gpu) => gpu.to_string(),
+        None => String::new(),
+    })
+    .with_line_break_mode(druid::widget::LineBreaking::WordWrap)
+    .lens(ViewportState::gpu);
+
+    Flex::column()
+        .with_child(playback)
+        .with_spacer(16.0)
+        .with_child(params_controls().lens(ViewportState::params))
+        .with_spacer(16.0)
+        .with_child(gpu)
+        .padding(8.0)
+}
 
-        while texture_width_padded % 256 != 0 {
-            texture_width_padded += 1;
-        }
+/// Set up the demo's parameters and effects, which headless renders share
+/// with the window.
+fn scene(wgpu_widget: WgpuWidget) -> WgpuWidget {
+    let wgpu_widget = wgpu_widget.with_param_layout(PARAMS.map(|(name, ..)| name));
+
+    // Grade with a LUT from the working directory, if there is one.
+    let wgpu_widget = match std::fs::read_to_string(LUT_PATH) {
+        Ok(source) => match Lut::from_cube(&source) {
+            Ok(lut) => wgpu_widget.with_post_effect(PostEffect::color_grade(lut)),
+            Err(err) => {
+                eprintln!("Failed to load {}: {}", LUT_PATH, err);
+                wgpu_widget
+            }
+        },
+        Err(_) => wgpu_widget,
+    };
+
+    // Stylistic effects go on top of the graded image.
+    wgpu_widget
+        .with_post_effect(effects::trails())
+        .with_post_effect(effects::chromatic_aberration())
+        .with_post_effect(effects::grain())
+        .with_post_effect(effects::vignette())
+}
 
-        while texture_height_padded % 256 != 0 {
-            texture_height_padded += 1;
+/// Set up the viewport with the demo's scene, controls and outputs.
+fn viewport(wgpu_widget: WgpuWidget, args: &Args) -> WgpuWidget {
+    let wgpu_widget = scene(wgpu_widget)
+        .with_rulers()
+        .with_drag_policy(DragPolicy::Preview(0.5));
+    let wgpu_widget = match args.max_fps {
+        Some(fps) => wgpu_widget.with_max_fps(fps),
+        None => wgpu_widget,
+    };
+
+    // A preset from the command line wins over remembered settings.
+    let wgpu_widget = match args.preset {
+        Some(_) => wgpu_widget,
+        None => wgpu_widget.with_persistence(SETTINGS_PATH, "main"),
+    };
+    let wgpu_widget = match &args.record {
+        Some(dir) => wgpu_widget.with_frame_sink(PngSequence::new(dir)),
+        None => wgpu_widget,
+    };
+
+    #[cfg(feature = "audio")]
+    let wgpu_widget = match druid_wgpu::audio::AudioInput::default_input() {
+        Ok(input) => wgpu_widget.with_audio_input(input),
+        Err(err) => {
+            eprintln!("Audio input unavailable: {}", err);
+            wgpu_widget
         }
-
-        if texture_width_padded != self.output_buffer_width
-            || texture_height_padded != self.output_buffer_height
-        {
-            self.output_buffer_width = texture_width_padded;
-            self.output_buffer_height = texture_height_padded;
-            self.output_buffer = WgpuWidget::create_output_buffer(
-                &self.device,
-                texture_width_padded,
-                texture_height_padded,
-            );
+    };
+
+    #[cfg(feature = "stream")]
+    let wgpu_widget = match druid_wgpu::stream::FrameServer::listen("127.0.0.1:8080") {
+        Ok(server) => wgpu_widget.with_frame_sink(server.with_max_fps(30.0)),
+        Err(err) => {
+            eprintln!("Frame server unavailable: {}", err);
+            wgpu_widget
         }
-
-        let texture_desc = wgpu::TextureDescriptor {
-            size: wgpu::Extent3d {
-                width: texture_width,
-                height: texture_height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
-            label: None,
-        };
-
-        let texture = self.device.create_texture(&texture_desc);
-        let texture_view = texture.create_view(&Default::default());
-
-        // we need to store this for later
-        let u32_size = std::mem::size_of::<u32>() as u32;
-
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &texture_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..self.num_vertices, 0..1);
+    };
+
+    #[cfg(feature = "http")]
+    let wgpu_widget = match druid_wgpu::bridge::HttpBridge::listen("127.0.0.1:8081") {
+        Ok(bridge) => wgpu_widget.with_http_bridge(bridge),
+        Err(err) => {
+            eprintln!("HTTP bridge unavailable: {}", err);
+            wgpu_widget
         }
+    };
 
-        encoder.copy_texture_to_buffer(
-            wgpu::ImageCopyTexture {
-                aspect: wgpu::TextureAspect::All,
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-            },
-            wgpu::ImageCopyBuffer {
-                buffer: &self.output_buffer,
-                layout: wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: NonZeroU32::new(u32_size * texture_width_padded),
-                    rows_per_image: NonZeroU32::new(texture_height_padded),
-                },
-            },
-            texture_desc.size,
-        );
-
-        self.queue.submit(std::iter::once(encoder.finish()));
-
-        {
-            let buffer_slice = self.output_buffer.slice(..);
-
-            let (tx, rx) = futures_intrusive::channel::shared::oneshot_channel();
-            buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-                tx.send(result).unwrap();
-            });
-
-            self.device.poll(wgpu::Maintain::Wait);
+    wgpu_widget
+}
 
-            pollster::block_on(rx.receive()).unwrap().unwrap();
+/// Render the first frame of the demo to `path`, for `--headless`.
+fn render_headless(args: &Args, path: &Path) -> Result<(), Box<dyn Error>> {
+    let (width, height) = match args.size {
+        Some(size) => (size.width as u32, size.height as u32),
+        None => HEADLESS_SIZE,
+    };
+    let wgpu_widget = pollster::block_on(WgpuWidget::try_with_options(args.options.clone()))?;
+    let mut wgpu_widget = scene(wgpu_widget);
+    let params = args.preset.clone().unwrap_or_else(default_params);
+    let data = ViewportState::new(Playback::new(DURATION), params);
+    let image = wgpu_widget.render_headless(&data, width, height, 1)?;
+    image.save(path)?;
+    Ok(())
+}
 
-            let data = buffer_slice.get_mapped_range();
+pub fn main() {
+    if std::env::args().any(|arg| arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
 
-            let image_buff = ImageBuf::from_raw(
-                &*data,
-                ImageFormat::RgbaPremul,
-                texture_width_padded as usize,
-                texture_height_padded as usize,
-            );
+    if let Some(path) = &args.diagnose {
+        let report = pollster::block_on(druid_wgpu::diagnostics::report(&args.options));
+        if let Err(err) = std::fs::write(path, report) {
+            eprintln!("Failed to write {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-            let image = image_buff.to_image(ctx.render_ctx);
-            let image_size_padded =
-                Size::new(texture_width_padded as f64, texture_height_padded as f64);
-            let image_size = Size::new(texture_width as f64, texture_height as f64);
-            ctx.with_save(|ctx| {
-                ctx.clip(image_size.to_rect());
-                ctx.draw_image(
-                    &image,
-                    image_size_padded.to_rect(),
-                    InterpolationMode::NearestNeighbor,
-                );
-            });
-        };
-        self.output_buffer.unmap();
-
-        println!("Time: {:?}", i.elapsed());
+    if let Some(path) = &args.headless {
+        if let Err(err) = render_headless(&args, path) {
+            eprintln!("Failed to render {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+        return;
     }
-}
 
-pub fn main() {
-    let wgpu_widget = pollster::block_on(WgpuWidget::new());
+    let viewport = match pollster::block_on(WgpuWidget::try_with_options(args.options.clone())) {
+        Ok(wgpu_widget) => viewport(wgpu_widget, &args).boxed(),
+        #[cfg(feature = "fallback")]
+        Err(err) => {
+            eprintln!("{}", err);
+            druid_wgpu::fallback::FallbackViewport::new(err).boxed()
+        }
+        #[cfg(not(feature = "fallback"))]
+        Err(err) => panic!("{}", err),
+    };
+
     let window = WindowDesc::new(Container::new(
-        Split::columns(wgpu_widget, Align::centered(Label::new("Right Split")))
+        Split::columns(viewport, controls())
             .split_point(0.7)
-            .draggable(true),
+            .draggable(true)
+            .controller(DragWatcher::new()),
     ))
     .with_min_size((200., 200.))
     .title(LocalizedString::new("timer-demo-window-title").with_placeholder("Look at it go!"));
-
-    AppLauncher::with_window(window)
+    let window = match args.size {
+        Some(size) => window.window_size(size),
+        None => window,
+    };
+
+    let mut playback = Playback::new(DURATION);
+    playback.play();
+
+    let launcher = AppLauncher::with_window(window);
+
+    #[cfg(feature = "osc")]
+    let _osc =
+        druid_wgpu::bridge::OscBridge::listen("127.0.0.1:9000", launcher.get_external_handle())
+            .map_err(|err| eprintln!("OSC bridge unavailable: {}", err));
+
+    #[cfg(feature = "midi")]
+    let _midi = druid_wgpu::bridge::MidiBridge::connect(
+        "",
+        vec![druid_wgpu::bridge::MidiMapping {
+            channel: 0,
+            controller: 1,
+            name: druid_wgpu::bridge::PLAYBACK_TIME.into(),
+            range: (0.0, DURATION),
+        }],
+        launcher.get_external_handle(),
+    )
+    .map_err(|err| eprintln!("MIDI bridge unavailable: {}", err));
+
+    launcher
+        .delegate(Delegate)
         .log_to_console()
-        .launch(0u32)
+        .configure_env(|env, _| druid_wgpu::theme::configure_dark(env))
+        .launch(ViewportState::new(
+            playback,
+            args.preset.unwrap_or_else(default_params),
+        ))
         .expect("launch failed");
 }
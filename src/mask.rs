@@ -0,0 +1,170 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Clipping the scene to a shape.
+//!
+//! A [`Mask`] is drawn into a stencil buffer before the scene, and the
+//! scene is then only drawn where the mask is, or where it isn't for an
+//! inverted mask. Masks are WGSL, so they can be any shape, animate with
+//! `globals.time` and follow `params` like the scene does: portals, peek
+//! holes, split views.
+
+use crate::gpu::Gpu;
+
+/// The stencil buffer's format. wgpu 0.14 has no stencil-only format that
+/// every backend supports.
+pub(crate) const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+/// What the scene is clipped to, see [`WgpuWidget::with_mask`].
+///
+/// [`WgpuWidget::with_mask`]: crate::WgpuWidget::with_mask
+#[derive(Clone, Debug)]
+pub struct Mask {
+    source: String,
+    inverted: bool,
+}
+
+impl Mask {
+    /// `source` is WGSL defining `fn mask(uv: vec2<f32>) -> bool`, true
+    /// where the scene should show. `uv` goes from 0 to 1 across the frame,
+    /// and `globals`, `params` and the `sd_*` functions of
+    /// [`SDF_WGSL`] are available to it.
+    ///
+    /// [`SDF_WGSL`]: crate::effects::SDF_WGSL
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            inverted: false,
+        }
+    }
+
+    /// Show the scene only outside the mask instead.
+    pub fn inverted(mut self) -> Self {
+        self.inverted = !self.inverted;
+        self
+    }
+}
+
+/// The pipelines drawing a mask and the scene within it.
+pub(crate) struct MaskPass {
+    mask: Mask,
+    mask_pipeline: wgpu::RenderPipeline,
+    pub(crate) scene_pipeline: wgpu::RenderPipeline,
+    pub(crate) wireframe_pipeline: Option<wgpu::RenderPipeline>,
+}
+
+impl MaskPass {
+    pub(crate) fn new(gpu: &Gpu, mask: Mask) -> Self {
+        let source = format!(
+            "{}\n{}\n{}",
+            include_str!("mask.wgsl"),
+            crate::effects::SDF_WGSL,
+            mask.source
+        );
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Mask Shader"),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+
+        let mask_pipeline = gpu
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Mask Pipeline"),
+                layout: Some(&gpu.render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_mask",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_mask",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: crate::gpu::SCENE_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::empty(),
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(stencil_state(
+                    wgpu::CompareFunction::Always,
+                    wgpu::StencilOperation::Replace,
+                )),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+
+        let scene_stencil = || {
+            Some(stencil_state(
+                wgpu::CompareFunction::Equal,
+                wgpu::StencilOperation::Keep,
+            ))
+        };
+        let scene_pipeline = gpu.create_scene_pipeline(wgpu::PolygonMode::Fill, scene_stencil());
+        let wireframe_pipeline = gpu
+            .wireframe_pipeline
+            .is_some()
+            .then(|| gpu.create_scene_pipeline(wgpu::PolygonMode::Line, scene_stencil()));
+
+        Self {
+            mask,
+            mask_pipeline,
+            scene_pipeline,
+            wireframe_pipeline,
+        }
+    }
+
+    /// The same mask on another device.
+    pub(crate) fn rebuild(&self, gpu: &Gpu) -> Self {
+        Self::new(gpu, self.mask.clone())
+    }
+
+    /// Draw the mask into the pass's stencil buffer, and set the reference
+    /// value the scene pipelines compare against.
+    pub(crate) fn encode<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, gpu: &'a Gpu) {
+        render_pass.set_pipeline(&self.mask_pipeline);
+        render_pass.set_bind_group(0, &gpu.globals_bind_group, &[]);
+        render_pass.set_stencil_reference(1);
+        render_pass.draw(0..3, 0..1);
+
+        // The stencil is 1 inside the mask and 0 outside.
+        render_pass.set_stencil_reference(if self.mask.inverted { 0 } else { 1 });
+    }
+}
+
+fn stencil_state(
+    compare: wgpu::CompareFunction,
+    pass_op: wgpu::StencilOperation,
+) -> wgpu::DepthStencilState {
+    let face = wgpu::StencilFaceState {
+        compare,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op,
+    };
+    wgpu::DepthStencilState {
+        format: STENCIL_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Always,
+        stencil: wgpu::StencilState {
+            front: face,
+            back: face,
+            read_mask: 0xff,
+            write_mask: 0xff,
+        },
+        bias: wgpu::DepthBiasState::default(),
+    }
+}
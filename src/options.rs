@@ -0,0 +1,122 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Options for creating the widget's GPU device.
+
+use std::time::Duration;
+
+/// Which set of limits to request from the adapter.
+///
+/// The restricted profiles let developers check how their content degrades
+/// on older hardware without owning any.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceProfile {
+    /// `wgpu::Limits::default()`, which most desktop GPUs support.
+    Default,
+    /// Limits of most DX11-class and GLES 3.1 hardware.
+    Downlevel,
+    /// Limits of WebGL2 and GLES 3.0 hardware.
+    WebGl2,
+}
+
+impl DeviceProfile {
+    pub fn limits(self) -> wgpu::Limits {
+        match self {
+            DeviceProfile::Default => wgpu::Limits::default(),
+            DeviceProfile::Downlevel => wgpu::Limits::downlevel_defaults(),
+            DeviceProfile::WebGl2 => wgpu::Limits::downlevel_webgl2_defaults(),
+        }
+    }
+
+    /// Whether optional features like the wireframe view are requested.
+    pub fn optional_features(self) -> bool {
+        self == DeviceProfile::Default
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GpuOptions {
+    pub profile: DeviceProfile,
+    /// Restrict the largest texture below what the profile allows. Widgets
+    /// bigger than this render at a lower resolution and are scaled up.
+    pub max_texture_size: Option<u32>,
+    pub power_preference: wgpu::PowerPreference,
+    /// Which graphics APIs adapters may use.
+    pub backends: wgpu::Backends,
+    /// Use the first adapter whose name contains this, ignoring case,
+    /// instead of the one `power_preference` picks.
+    pub adapter: Option<String>,
+    /// Wrap every frame in a validation error scope, so errors are reported
+    /// with the frame they happened in. Backend validation layers follow
+    /// wgpu's own debug-build default either way.
+    pub validation: bool,
+    /// How long to wait for a frame before treating the device as hung and
+    /// recreating it, for example after a shader that never terminates.
+    pub frame_timeout: Duration,
+    /// On software rasterizers, like llvmpipe on CI machines, render at
+    /// most [`SOFTWARE_MAX_TEXTURE_SIZE`] pixels across and skip post
+    /// effects and accumulation. The widget sends [`SOFTWARE_RENDERER`]
+    /// either way.
+    ///
+    /// [`SOFTWARE_RENDERER`]: crate::SOFTWARE_RENDERER
+    pub reduce_quality_on_software: bool,
+}
+
+/// The largest texture rendered to on software rasterizers when
+/// `GpuOptions::reduce_quality_on_software` is set.
+pub const SOFTWARE_MAX_TEXTURE_SIZE: u32 = 1024;
+
+impl Default for GpuOptions {
+    fn default() -> Self {
+        Self {
+            profile: DeviceProfile::Default,
+            max_texture_size: None,
+            power_preference: wgpu::PowerPreference::default(),
+            backends: wgpu::Backends::all(),
+            adapter: None,
+            validation: cfg!(debug_assertions),
+            frame_timeout: Duration::from_secs(2),
+            reduce_quality_on_software: true,
+        }
+    }
+}
+
+impl GpuOptions {
+    pub(crate) fn limits(&self, adapter: &wgpu::AdapterInfo) -> wgpu::Limits {
+        let mut limits = self.profile.limits();
+        if let Some(size) = self.max_texture_size {
+            limits.max_texture_dimension_2d = limits.max_texture_dimension_2d.min(size);
+        }
+        if self.reduced_quality(adapter) {
+            limits.max_texture_dimension_2d = limits
+                .max_texture_dimension_2d
+                .min(SOFTWARE_MAX_TEXTURE_SIZE);
+        }
+        limits
+    }
+
+    /// Whether to render at lower quality on `adapter`.
+    pub(crate) fn reduced_quality(&self, adapter: &wgpu::AdapterInfo) -> bool {
+        self.reduce_quality_on_software && crate::gpu::is_software(adapter)
+    }
+
+    pub(crate) fn features(&self, adapter: &wgpu::Adapter) -> wgpu::Features {
+        if self.profile.optional_features() {
+            // Only used for the optional wireframe view.
+            adapter.features() & wgpu::Features::POLYGON_MODE_LINE
+        } else {
+            wgpu::Features::empty()
+        }
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Named shader parameters.
+//!
+//! [`Params`] maps names to typed values. It lives in app data so druid
+//! widgets can edit it, the viewport packs the names it cares about into a
+//! uniform block every frame, and whole sets can be saved as RON presets.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use druid::lens::{self, Lens};
+use druid::Data;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Number of `vec4` slots in the shader's `params` uniform.
+pub const PARAM_SLOTS: usize = 16;
+
+#[derive(Clone, Debug, PartialEq, Data, Serialize, Deserialize)]
+pub enum ParamValue {
+    Float(f64),
+    Int(i64),
+    Bool(bool),
+    Vec4(f64, f64, f64, f64),
+}
+
+impl ParamValue {
+    /// The value as a scalar; vectors give their first component.
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            ParamValue::Float(value) => value,
+            ParamValue::Int(value) => value as f64,
+            ParamValue::Bool(value) => value as u8 as f64,
+            ParamValue::Vec4(x, ..) => x,
+        }
+    }
+
+    /// This value converted to the variant of `like`, for writes from
+    /// sources that only know numbers. Nonzero is true, integers round, and
+    /// a scalar written to a vector only sets its first component.
+    pub fn coerced_to(self, like: &ParamValue) -> ParamValue {
+        match (like, self) {
+            (ParamValue::Float(_), value) => ParamValue::Float(value.as_f64()),
+            (ParamValue::Int(_), ParamValue::Int(value)) => ParamValue::Int(value),
+            (ParamValue::Int(_), value) => ParamValue::Int(value.as_f64().round() as i64),
+            (ParamValue::Bool(_), ParamValue::Bool(value)) => ParamValue::Bool(value),
+            (ParamValue::Bool(_), value) => ParamValue::Bool(value.as_f64() != 0.0),
+            (ParamValue::Vec4(..), value @ ParamValue::Vec4(..)) => value,
+            (&ParamValue::Vec4(_, y, z, w), value) => ParamValue::Vec4(value.as_f64(), y, z, w),
+        }
+    }
+
+    /// Parse a value as written by `Display`: `true` or `false`, an integer,
+    /// a float, or four floats separated by spaces or commas.
+    pub fn parse(text: &str) -> Option<ParamValue> {
+        let text = text.trim();
+        match text {
+            "true" => return Some(ParamValue::Bool(true)),
+            "false" => return Some(ParamValue::Bool(false)),
+            _ => {}
+        }
+        if let Ok(value) = text.parse() {
+            return Some(ParamValue::Int(value));
+        }
+        if let Ok(value) = text.parse() {
+            return Some(ParamValue::Float(value));
+        }
+
+        let components = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|component| !component.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<f64>, _>>()
+            .ok()?;
+        match components[..] {
+            [x, y, z, w] => Some(ParamValue::Vec4(x, y, z, w)),
+            _ => None,
+        }
+    }
+
+    /// The value as it's laid out in one uniform slot.
+    pub fn to_slot(&self) -> [f32; 4] {
+        match *self {
+            ParamValue::Vec4(x, y, z, w) => [x as f32, y as f32, z as f32, w as f32],
+            _ => [self.as_f64() as f32, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamValue::Float(value) => write!(f, "{:?}", value),
+            ParamValue::Int(value) => write!(f, "{}", value),
+            ParamValue::Bool(value) => write!(f, "{}", value),
+            ParamValue::Vec4(x, y, z, w) => write!(f, "{:?} {:?} {:?} {:?}", x, y, z, w),
+        }
+    }
+}
+
+impl From<f64> for ParamValue {
+    fn from(value: f64) -> Self {
+        ParamValue::Float(value)
+    }
+}
+
+impl From<i64> for ParamValue {
+    fn from(value: i64) -> Self {
+        ParamValue::Int(value)
+    }
+}
+
+impl From<bool> for ParamValue {
+    fn from(value: bool) -> Self {
+        ParamValue::Bool(value)
+    }
+}
+
+impl From<[f64; 4]> for ParamValue {
+    fn from([x, y, z, w]: [f64; 4]) -> Self {
+        ParamValue::Vec4(x, y, z, w)
+    }
+}
+
+/// A registry of named parameters.
+#[derive(Clone, Debug, Default, Data)]
+pub struct Params {
+    values: Arc<BTreeMap<String, ParamValue>>,
+}
+
+impl Params {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder-style [`set`](Self::set).
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<ParamValue>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.values.get(name)
+    }
+
+    pub fn float(&self, name: &str) -> Option<f64> {
+        self.get(name).map(ParamValue::as_f64)
+    }
+
+    /// The parameter if it's a boolean.
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name) {
+            Some(ParamValue::Bool(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Set a parameter, leaving the registry untouched (and so `same` as
+    /// before) if it already has that value.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<ParamValue>) {
+        let name = name.into();
+        let value = value.into();
+        if self.values.get(&name) != Some(&value) {
+            Arc::make_mut(&mut self.values).insert(name, value);
+        }
+    }
+
+    /// [`set`](Self::set), keeping the variant already stored under `name`,
+    /// see [`ParamValue::coerced_to`]. New names take `value` as it is.
+    pub fn set_coerced(&mut self, name: impl Into<String>, value: impl Into<ParamValue>) {
+        let name = name.into();
+        let value = match self.get(&name) {
+            Some(current) => value.into().coerced_to(current),
+            None => value.into(),
+        };
+        self.set(name, value);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ParamValue> {
+        if self.values.contains_key(name) {
+            Arc::make_mut(&mut self.values).remove(name)
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ParamValue)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Pack the parameters named in `layout` into uniform slots, in order.
+    ///
+    /// Missing parameters are zero, and names past `PARAM_SLOTS` are ignored.
+    pub fn pack<S: AsRef<str>>(&self, layout: &[S]) -> [[f32; 4]; PARAM_SLOTS] {
+        let mut slots = [[0.0; 4]; PARAM_SLOTS];
+        for (slot, name) in slots.iter_mut().zip(layout) {
+            if let Some(value) = self.get(name.as_ref()) {
+                *slot = value.to_slot();
+            }
+        }
+        slots
+    }
+
+    /// A lens onto a parameter as a float, for binding sliders and the like.
+    pub fn float_lens(name: impl Into<String>) -> impl Lens<Params, f64> {
+        let name = name.into();
+        let put_name = name.clone();
+        lens::Map::new(
+            move |params: &Params| params.float(&name).unwrap_or_default(),
+            move |params: &mut Params, value| params.set(put_name.clone(), value),
+        )
+    }
+
+    /// A lens onto a boolean parameter, for binding checkboxes.
+    pub fn bool_lens(name: impl Into<String>) -> impl Lens<Params, bool> {
+        let name = name.into();
+        let put_name = name.clone();
+        lens::Map::new(
+            move |params: &Params| params.bool(&name).unwrap_or_default(),
+            move |params: &mut Params, value| params.set(put_name.clone(), value),
+        )
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(source)
+    }
+}
+
+impl Serialize for Params {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.values.as_ref().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Params {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        BTreeMap::deserialize(deserializer).map(|values| Self {
+            values: Arc::new(values),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(params: &Params) -> Vec<(String, ParamValue)> {
+        params
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn ron_round_trips() {
+        let params = Params::new()
+            .with("speed", 1.5)
+            .with("count", 3_i64)
+            .with("enabled", true)
+            .with("tint", [0.25, 0.5, 0.75, 1.0]);
+        let ron = params.to_ron().unwrap();
+        let parsed = Params::from_ron(&ron).unwrap();
+        assert_eq!(values(&parsed), values(&params));
+    }
+
+    #[test]
+    fn reads_handwritten_ron() {
+        let params =
+            Params::from_ron(r#"{ "speed": Float(2.0), "enabled": Bool(false) }"#).unwrap();
+        assert_eq!(params.float("speed"), Some(2.0));
+        assert_eq!(params.bool("enabled"), Some(false));
+        assert!(Params::from_ron(r#"{ "speed": Double(2.0) }"#).is_err());
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for value in [
+            ParamValue::Float(1.0),
+            ParamValue::Float(-0.125),
+            ParamValue::Int(-7),
+            ParamValue::Bool(true),
+            ParamValue::Vec4(1.0, 0.5, -2.0, 0.0),
+        ] {
+            assert_eq!(ParamValue::parse(&value.to_string()), Some(value));
+        }
+        assert_eq!(
+            ParamValue::parse("1, 2, 3, 4"),
+            Some(ParamValue::Vec4(1.0, 2.0, 3.0, 4.0))
+        );
+        assert_eq!(ParamValue::parse("1 2 3"), None);
+        assert_eq!(ParamValue::parse("fast"), None);
+    }
+
+    #[test]
+    fn coerces_to_the_stored_variant() {
+        let mut params = Params::new().with("count", 3_i64).with("on", false);
+        params.set_coerced("count", 4.6);
+        params.set_coerced("on", 1.0);
+        params.set_coerced("new", 0.5);
+        assert_eq!(params.get("count"), Some(&ParamValue::Int(5)));
+        assert_eq!(params.get("on"), Some(&ParamValue::Bool(true)));
+        assert_eq!(params.get("new"), Some(&ParamValue::Float(0.5)));
+    }
+}
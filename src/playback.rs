@@ -0,0 +1,103 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Playback state for animated content.
+
+use druid::{Data, Lens};
+
+/// The clock driving animated content in the viewport.
+///
+/// The widget only ever renders at `time`, so binding a slider to it gives
+/// deterministic scrubbing: the same time always produces the same frame.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct Playback {
+    /// Current time in seconds.
+    pub time: f64,
+    /// Length of the timeline in seconds.
+    pub duration: f64,
+    pub playing: bool,
+    /// Wrap around at the end of the timeline instead of stopping.
+    pub looping: bool,
+}
+
+impl Playback {
+    /// A paused timeline `duration` seconds long. Negative and NaN
+    /// durations make an empty one.
+    pub fn new(duration: f64) -> Self {
+        Self {
+            time: 0.0,
+            // `max` also turns NaN into zero.
+            duration: duration.max(0.0),
+            playing: false,
+            looping: true,
+        }
+    }
+
+    pub fn play(&mut self) {
+        // Restart when playing from the end of a non-looping timeline.
+        if !self.looping && self.time >= self.duration {
+            self.time = 0.0;
+        }
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn toggle(&mut self) {
+        if self.playing {
+            self.pause();
+        } else {
+            self.play();
+        }
+    }
+
+    /// Jump to `time`, clamped to the timeline.
+    pub fn seek(&mut self, time: f64) {
+        self.time = time.clamp(0.0, self.duration);
+    }
+
+    /// Advance the clock by `dt` seconds if playing.
+    pub fn advance(&mut self, dt: f64) {
+        if !self.playing {
+            return;
+        }
+
+        let time = self.time + dt;
+        if time < self.duration {
+            self.time = time;
+        } else if self.looping && self.duration > 0.0 {
+            self.time = time % self.duration;
+        } else {
+            self.time = self.duration;
+            self.playing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_durations_make_an_empty_timeline() {
+        for duration in [-1.0, f64::NAN] {
+            let mut playback = Playback::new(duration);
+            assert_eq!(playback.duration, 0.0);
+            playback.seek(5.0);
+            assert_eq!(playback.time, 0.0);
+        }
+    }
+}
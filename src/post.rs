@@ -0,0 +1,740 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Post effects run on the rendered frame before it's presented.
+//!
+//! An effect is a fragment shader drawn over the whole frame. Its source is
+//! appended to a prelude, `post.wgsl`, which declares the frame so far as
+//! `frame` and `frame_sampler`, the shared `globals`, the effect's own
+//! `params`, and a vertex shader passing `PostVertex` with a `uv` to the
+//! effect's `fs_main`. The [`rng`](crate::rng) functions are available too.
+//! Effects run in the order they were added, in linear color.
+//!
+//! Sources are [preprocessed](crate::variants) with the effect's defines,
+//! and effects sharing a source and defines share a shader module.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use druid::Selector;
+use wgpu::util::DeviceExt;
+
+use crate::compat;
+use crate::gpu::{Gpu, ParamUniforms, SCENE_FORMAT};
+use crate::history::History;
+use crate::params::{Params, PARAM_SLOTS};
+use crate::rng;
+use crate::targets::PostTargets;
+use crate::variants::{Defines, ShaderVariants};
+
+/// Replace the LUT of a named effect, see [`PostEffect::with_lut`].
+pub const SET_LUT: Selector<LutChange> = Selector::new("druid-wgpu.set-lut");
+
+/// Flip the toggle parameter of the named effect, see
+/// [`PostEffect::with_toggle`]. Effects without a toggle ignore this.
+pub const TOGGLE_EFFECT: Selector<String> = Selector::new("druid-wgpu.toggle-effect");
+
+#[derive(Clone, Debug)]
+pub struct LutChange {
+    /// Name of the effect to change.
+    pub effect: String,
+    pub lut: Lut,
+}
+
+/// A fullscreen pass over the frame.
+#[derive(Clone, Debug)]
+pub struct PostEffect {
+    name: String,
+    source: String,
+    param_layout: Vec<String>,
+    toggle: Option<String>,
+    lut: Option<Lut>,
+    history: bool,
+    defines: Defines,
+}
+
+impl PostEffect {
+    /// An effect from WGSL source defining `fs_main`, see the module docs.
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: source.into(),
+            param_layout: Vec::new(),
+            toggle: None,
+            lut: None,
+            history: false,
+            defines: Defines::new(),
+        }
+    }
+
+    /// Grade the frame with `lut`, which can be swapped later with [`SET_LUT`].
+    pub fn color_grade(lut: Lut) -> Self {
+        Self::new("color-grade", include_str!("grade.wgsl")).with_lut(lut)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Choose which parameters the effect sees, in `params.slots` order.
+    pub fn with_param_layout<S: Into<String>>(
+        mut self,
+        names: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.param_layout = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only run the effect while the boolean parameter `name` is true.
+    pub fn with_toggle(mut self, name: impl Into<String>) -> Self {
+        self.toggle = Some(name.into());
+        self
+    }
+
+    /// Bind `lut` as `lut` and `lut_sampler` in group 1.
+    pub fn with_lut(mut self, lut: Lut) -> Self {
+        self.lut = Some(lut);
+        self
+    }
+
+    /// Bind the effect's own output from the previous frame as `history`
+    /// and `history_sampler` in group 2, for feedback and temporal effects.
+    ///
+    /// The history is cleared to transparent black on the first frame and
+    /// whenever the viewport is resized.
+    pub fn with_history(mut self) -> Self {
+        self.history = true;
+        self
+    }
+
+    /// Define `name` for the effect's `#ifdef` blocks, see
+    /// [`variants`](crate::variants).
+    pub fn with_define(mut self, name: impl Into<String>) -> Self {
+        self.defines.insert(name);
+        self
+    }
+
+    fn enabled(&self, params: &Params) -> bool {
+        match &self.toggle {
+            Some(name) => params.bool(name).unwrap_or(false),
+            None => true,
+        }
+    }
+}
+
+/// A 3D color lookup table.
+#[derive(Clone, Debug)]
+pub struct Lut {
+    size: u32,
+    /// Output colors with red changing fastest, then green, then blue.
+    table: Arc<[[f32; 3]]>,
+}
+
+impl Lut {
+    /// A LUT that leaves colors unchanged.
+    pub fn identity(size: u32) -> Self {
+        let size = size.max(2);
+        let max = (size - 1) as f32;
+        let mut table = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    table.push([r as f32 / max, g as f32 / max, b as f32 / max]);
+                }
+            }
+        }
+        Self {
+            size,
+            table: table.into(),
+        }
+    }
+
+    /// A LUT snapping every color to the nearest one in `palette`. Colors
+    /// are display-encoded, 0 to 1, as in `.cube` files.
+    pub fn from_palette(palette: &[[f32; 3]], size: u32) -> Self {
+        let identity = Self::identity(size);
+        let nearest = |color: &[f32; 3]| {
+            let distance =
+                |entry: &&[f32; 3]| -> f32 { (0..3).map(|i| (entry[i] - color[i]).powi(2)).sum() };
+            palette
+                .iter()
+                .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+                .copied()
+                .unwrap_or_default()
+        };
+        Self {
+            size: identity.size,
+            table: identity.table.iter().map(nearest).collect(),
+        }
+    }
+
+    /// Parse a 3D LUT in the Adobe/Resolve `.cube` format.
+    pub fn from_cube(source: &str) -> Result<Self, CubeError> {
+        let mut size = None;
+        let mut table = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: &str| CubeError {
+                line: line_number,
+                message: message.to_string(),
+            };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            match keyword {
+                "TITLE" => (),
+                "LUT_3D_SIZE" => {
+                    let value = words
+                        .next()
+                        .and_then(|word| word.parse::<u32>().ok())
+                        .filter(|size| (2..=256).contains(size))
+                        .ok_or_else(|| error("expected a size between 2 and 256"))?;
+                    size = Some(value);
+                }
+                "LUT_1D_SIZE" => return Err(error("1D LUTs aren't supported")),
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let expected = if keyword == "DOMAIN_MIN" { 0.0 } else { 1.0 };
+                    let values = parse_triple(words).ok_or_else(|| error("expected 3 numbers"))?;
+                    if values.iter().any(|&value| value != expected) {
+                        return Err(error("only the default 0 to 1 domain is supported"));
+                    }
+                }
+                _ => {
+                    let values = parse_triple(line.split_whitespace())
+                        .ok_or_else(|| error("expected 3 numbers or a keyword"))?;
+                    if size.is_none() {
+                        return Err(error("table data before LUT_3D_SIZE"));
+                    }
+                    table.push(values);
+                }
+            }
+        }
+
+        let size = size.ok_or(CubeError {
+            line: 0,
+            message: "missing LUT_3D_SIZE".to_string(),
+        })?;
+        let expected = (size * size * size) as usize;
+        if table.len() != expected {
+            return Err(CubeError {
+                line: 0,
+                message: format!("expected {} table entries, found {}", expected, table.len()),
+            });
+        }
+
+        Ok(Self {
+            size,
+            table: table.into(),
+        })
+    }
+
+    /// Number of entries along each axis.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+fn parse_triple<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<[f32; 3]> {
+    let mut values = [0.0; 3];
+    for value in &mut values {
+        *value = words.next()?.parse().ok()?;
+    }
+    words.next().is_none().then_some(values)
+}
+
+/// Why a `.cube` file couldn't be read.
+#[derive(Clone, Debug)]
+pub struct CubeError {
+    /// 1-based line of the problem, or 0 for the file as a whole.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CubeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "invalid .cube file: {}", self.message),
+            line => write!(f, "invalid .cube file, line {}: {}", line, self.message),
+        }
+    }
+}
+
+impl std::error::Error for CubeError {}
+
+/// An effect and its resources on the current device.
+struct PostPass {
+    effect: PostEffect,
+    pipeline: wgpu::RenderPipeline,
+    params_buffer: wgpu::Buffer,
+    lut_bind_group: Option<wgpu::BindGroup>,
+    history: Option<History>,
+}
+
+/// The widget's effects, compiled for its device.
+pub(crate) struct PostChain {
+    passes: Vec<PostPass>,
+    input_layout: wgpu::BindGroupLayout,
+    lut_layout: wgpu::BindGroupLayout,
+    history_layout: wgpu::BindGroupLayout,
+    /// Fills group 1 for effects with history but no LUT.
+    empty_layout: wgpu::BindGroupLayout,
+    empty_bind_group: wgpu::BindGroup,
+    sampler: wgpu::Sampler,
+    /// Compiled effect sources, by the source before the prelude.
+    shaders: HashMap<String, ShaderVariants>,
+}
+
+impl PostChain {
+    pub(crate) fn new(gpu: &Gpu) -> Self {
+        let device = &gpu.device;
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let texture_entry = |binding, view_dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+
+        let input_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Input Bind Group Layout"),
+            entries: &[
+                texture_entry(0, wgpu::TextureViewDimension::D2),
+                sampler_entry(1),
+                uniform_entry(2),
+                uniform_entry(3),
+            ],
+        });
+        let lut_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post LUT Bind Group Layout"),
+            entries: &[
+                texture_entry(0, wgpu::TextureViewDimension::D3),
+                sampler_entry(1),
+            ],
+        });
+        let history_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post History Bind Group Layout"),
+            entries: &[
+                texture_entry(0, wgpu::TextureViewDimension::D2),
+                sampler_entry(1),
+            ],
+        });
+        let empty_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Empty Bind Group Layout"),
+            entries: &[],
+        });
+        let empty_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Empty Bind Group"),
+            layout: &empty_layout,
+            entries: &[],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            passes: Vec::new(),
+            input_layout,
+            lut_layout,
+            history_layout,
+            empty_layout,
+            empty_bind_group,
+            sampler,
+            shaders: HashMap::new(),
+        }
+    }
+
+    /// Compile the same effects again for a new device.
+    pub(crate) fn rebuild(&self, gpu: &Gpu) -> Self {
+        let mut chain = PostChain::new(gpu);
+        for pass in &self.passes {
+            chain.push(gpu, pass.effect.clone());
+        }
+        chain
+    }
+
+    pub(crate) fn push(&mut self, gpu: &Gpu, effect: PostEffect) {
+        let device = &gpu.device;
+        let variants = self
+            .shaders
+            .entry(effect.source.clone())
+            .or_insert_with(|| {
+                ShaderVariants::new(
+                    effect.name.as_str(),
+                    format!(
+                        "{}{}{}",
+                        include_str!("post.wgsl"),
+                        rng::WGSL,
+                        effect.source
+                    ),
+                )
+            });
+        let shader = match variants.get(device, &effect.defines) {
+            Ok(shader) => shader,
+            Err(err) => {
+                eprintln!("Skipping post effect {}: {}", effect.name, err);
+                return;
+            }
+        };
+
+        let mut bind_group_layouts = vec![&self.input_layout];
+        if effect.lut.is_some() {
+            bind_group_layouts.push(&self.lut_layout);
+        } else if effect.history {
+            bind_group_layouts.push(&self.empty_layout);
+        }
+        if effect.history {
+            bind_group_layouts.push(&self.history_layout);
+        }
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pipeline Layout"),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(effect.name.as_str()),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: SCENE_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Params Buffer"),
+            contents: bytemuck::bytes_of(&ParamUniforms {
+                slots: [[0.0; 4]; PARAM_SLOTS],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let lut_bind_group = effect.lut.as_ref().map(|lut| self.upload_lut(gpu, lut));
+        let history = effect
+            .history
+            .then(|| History::new("Post History", SCENE_FORMAT));
+
+        self.passes.push(PostPass {
+            effect,
+            pipeline,
+            params_buffer,
+            lut_bind_group,
+            history,
+        });
+    }
+
+    /// Swap the LUT of the effect named `name`. Returns false when there's
+    /// no such effect, or it wasn't created with a LUT.
+    pub(crate) fn set_lut(&mut self, gpu: &Gpu, name: &str, lut: Lut) -> bool {
+        let bind_group = self.upload_lut(gpu, &lut);
+        match self
+            .passes
+            .iter_mut()
+            .find(|pass| pass.effect.name == name && pass.effect.lut.is_some())
+        {
+            Some(pass) => {
+                pass.effect.lut = Some(lut);
+                pass.lut_bind_group = Some(bind_group);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The toggle parameter of the effect named `name`, if it has one.
+    pub(crate) fn toggle(&self, name: &str) -> Option<&str> {
+        self.passes
+            .iter()
+            .find(|pass| pass.effect.name == name)
+            .and_then(|pass| pass.effect.toggle.as_deref())
+    }
+
+    fn upload_lut(&self, gpu: &Gpu, lut: &Lut) -> wgpu::BindGroup {
+        let texels: Vec<u16> = lut
+            .table
+            .iter()
+            .flat_map(|&[r, g, b]| [r, g, b, 1.0])
+            .map(f16_bits)
+            .collect();
+
+        let size = wgpu::Extent3d {
+            width: lut.size,
+            height: lut.size,
+            depth_or_array_layers: lut.size,
+        };
+        let texture = gpu.device.create_texture_with_data(
+            &gpu.queue,
+            &wgpu::TextureDescriptor {
+                label: Some("LUT Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: wgpu::TextureFormat::Rgba16Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+            bytemuck::cast_slice(&texels),
+        );
+        let view = texture.create_view(&Default::default());
+
+        gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post LUT Bind Group"),
+            layout: &self.lut_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    /// Upload each effect's parameters.
+    pub(crate) fn write_params(&self, gpu: &Gpu, params: &Params) {
+        for pass in &self.passes {
+            let uniforms = ParamUniforms {
+                slots: params.pack(pass.effect.param_layout.as_slice()),
+            };
+            gpu.queue
+                .write_buffer(&pass.params_buffer, 0, bytemuck::bytes_of(&uniforms));
+        }
+    }
+
+    /// Run the enabled effects on `scene`, returning a view of the result,
+    /// or `None` when no effect ran.
+    pub(crate) fn encode(
+        &mut self,
+        gpu: &Gpu,
+        encoder: &mut wgpu::CommandEncoder,
+        scene: &wgpu::Texture,
+        size: wgpu::Extent3d,
+        params: &Params,
+        targets: &mut PostTargets,
+    ) -> Option<wgpu::TextureView> {
+        let mut passes = self
+            .passes
+            .iter_mut()
+            .filter(|pass| pass.effect.enabled(params))
+            .peekable();
+        passes.peek()?;
+
+        // Ping-pong between two targets, starting from the scene. Effects
+        // with history write to their own texture instead.
+        let targets = targets.get(&gpu.device, size);
+        let mut input = scene.create_view(&Default::default());
+        let mut input_target = None;
+
+        for pass in passes {
+            let (output, output_target) = match &mut pass.history {
+                Some(history) => {
+                    history.begin_frame(&gpu.device, size);
+                    (history.current().create_view(&Default::default()), None)
+                }
+                None => {
+                    let index = if input_target == Some(0) { 1 } else { 0 };
+                    (targets[index].create_view(&Default::default()), Some(index))
+                }
+            };
+
+            let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Post Input Bind Group"),
+                layout: &self.input_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&input),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: gpu.globals_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: pass.params_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let history_bind_group = pass.history.as_ref().map(|history| {
+                let previous = history.previous().create_view(&Default::default());
+                gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Post History Bind Group"),
+                    layout: &self.history_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&previous),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        },
+                    ],
+                })
+            });
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(pass.effect.name.as_str()),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &output,
+                    resolve_target: None,
+                    ops: compat::clear(wgpu::Color::TRANSPARENT),
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&pass.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            match &pass.lut_bind_group {
+                Some(lut_bind_group) => render_pass.set_bind_group(1, lut_bind_group, &[]),
+                None if history_bind_group.is_some() => {
+                    render_pass.set_bind_group(1, &self.empty_bind_group, &[])
+                }
+                None => (),
+            }
+            if let Some(history_bind_group) = &history_bind_group {
+                render_pass.set_bind_group(2, history_bind_group, &[]);
+            }
+            render_pass.draw(0..3, 0..1);
+            drop(render_pass);
+
+            input = output;
+            input_target = output_target;
+        }
+
+        Some(input)
+    }
+}
+
+/// Convert to a half float, truncating the mantissa and flushing values too
+/// small for a normal half to zero. Plenty for color tables.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = ((bits >> 13) & 0x03ff) as u16;
+
+    if exponent <= 0 {
+        sign
+    } else if exponent >= 0x1f {
+        sign | 0x7c00
+    } else {
+        sign | ((exponent as u16) << 10) | mantissa
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE: &str = "\
+# Swaps red and blue.
+TITLE \"swap\"
+LUT_3D_SIZE 2
+DOMAIN_MIN 0 0 0
+DOMAIN_MAX 1 1 1
+0 0 0
+0 0 1
+0 1 0
+0 1 1
+1 0 0
+1 0 1
+1 1 0
+1 1 1
+";
+
+    #[test]
+    fn parses_a_cube() {
+        let lut = Lut::from_cube(CUBE).unwrap();
+        assert_eq!(lut.size(), 2);
+        assert_eq!(lut.table.len(), 8);
+        // Red changes fastest.
+        assert_eq!(lut.table[1], [0.0, 0.0, 1.0]);
+        assert_eq!(lut.table[4], [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn identity_matches_its_cube() {
+        let identity = Lut::identity(2);
+        assert_eq!(identity.table[1], [1.0, 0.0, 0.0]);
+        assert_eq!(identity.table[7], [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn rejects_bad_cubes() {
+        let error = |source: &str| Lut::from_cube(source).unwrap_err();
+
+        assert_eq!(error("TITLE \"empty\"\n").line, 0);
+        assert_eq!(error("LUT_3D_SIZE 2\n0 0 0\n").line, 0);
+        assert_eq!(error("0 0 0\nLUT_3D_SIZE 2\n").line, 1);
+        assert_eq!(error("LUT_1D_SIZE 16\n").line, 1);
+        assert_eq!(error("LUT_3D_SIZE 1\n").line, 1);
+        assert_eq!(error("LUT_3D_SIZE 2\nDOMAIN_MAX 2 2 2\n").line, 2);
+        assert_eq!(error("LUT_3D_SIZE 2\n0 0\n").line, 2);
+        assert_eq!(error("LUT_3D_SIZE 2\n0 0 0 0\n").line, 2);
+    }
+}
@@ -0,0 +1,51 @@
+// Prelude shared by every post effect. The effect's own source follows and
+// defines `fs_main`.
+
+struct Globals {
+    time: f32,
+    high_contrast: f32,
+    sample: f32,
+    instances: f32,
+    // From 0 to 1 across the widget, see `WgpuWidget::with_cursor_input`.
+    cursor: vec2<f32>,
+    // Offset of this sample in clip space, see `still::EXPORT_STILL`.
+    jitter: vec2<f32>,
+    // Scale then offset from the whole image's clip space to the target's,
+    // see `still::EXPORT_PRINT`.
+    tile: vec4<f32>,
+};
+
+// Named parameters, in the order given to `PostEffect::with_param_layout`.
+struct Params {
+    slots: array<vec4<f32>, 16>,
+};
+
+// The frame so far, in linear color.
+@group(0) @binding(0)
+var frame: texture_2d<f32>;
+
+@group(0) @binding(1)
+var frame_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> globals: Globals;
+
+@group(0) @binding(3)
+var<uniform> params: Params;
+
+struct PostVertex {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> PostVertex {
+    // A single triangle covering the whole target.
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: PostVertex;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
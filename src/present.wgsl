@@ -0,0 +1,31 @@
+// Converts the linear scene to the output color profile.
+
+struct Present {
+    // Rows of the linear sRGB to target profile matrix.
+    from_srgb: array<vec4<f32>, 3>,
+};
+
+@group(0) @binding(0)
+var scene: texture_2d<f32>;
+
+@group(0) @binding(1)
+var<uniform> present: Present;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // A single triangle covering the whole target.
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(scene, vec2<i32>(position.xy), 0);
+    let rgb = vec3<f32>(
+        dot(present.from_srgb[0].xyz, color.rgb),
+        dot(present.from_srgb[1].xyz, color.rgb),
+        dot(present.from_srgb[2].xyz, color.rgb)
+    );
+    // The sRGB target applies the transfer curve on write.
+    return vec4<f32>(clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0)), color.a);
+}
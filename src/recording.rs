@@ -0,0 +1,148 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording animations to GIF.
+//!
+//! Sending [`EXPORT_GIF`] to the viewport steps playback through the
+//! requested span one frame at a time, regardless of how long each frame
+//! takes to render, then restores it and encodes the frames on a
+//! background thread. Frames are kept in memory until then.
+
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use druid::{ImageBuf, Selector};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+use crate::Playback;
+
+/// Record the viewport's animation to a GIF.
+pub const EXPORT_GIF: Selector<GifExport> = Selector::new("druid-wgpu.export-gif");
+
+#[derive(Clone, Debug)]
+pub struct GifExport {
+    pub path: PathBuf,
+    /// Playback time of the first frame, in seconds.
+    pub start: f64,
+    /// Length of the recording in seconds. Frames past the end of the
+    /// timeline repeat its last frame.
+    pub duration: f64,
+    pub fps: u32,
+    /// Loop forever when played, instead of once.
+    pub looping: bool,
+}
+
+impl GifExport {
+    /// Record `duration` seconds from the start of the timeline, at 25
+    /// frames per second, looping.
+    pub fn new(path: impl Into<PathBuf>, duration: f64) -> Self {
+        Self {
+            path: path.into(),
+            start: 0.0,
+            duration,
+            fps: 25,
+            looping: true,
+        }
+    }
+}
+
+/// A recording in progress.
+pub(crate) struct Recording {
+    export: GifExport,
+    /// Playback as it was before recording, to go back to afterwards.
+    restore: Playback,
+    /// Shared with the widget's own frames, and only converted for the
+    /// encoder once recording is done.
+    frames: Vec<ImageBuf>,
+    /// Whether the current frame has been captured, so other repaints
+    /// don't capture it again.
+    captured: bool,
+}
+
+impl Recording {
+    pub(crate) fn new(export: GifExport, playback: &Playback) -> Self {
+        Self {
+            export,
+            restore: playback.clone(),
+            frames: Vec::new(),
+            captured: false,
+        }
+    }
+
+    fn frame_count(&self) -> usize {
+        ((self.export.duration * self.export.fps.max(1) as f64).round() as usize).max(1)
+    }
+
+    /// Playback time of the frame to capture next.
+    pub(crate) fn time(&self) -> f64 {
+        self.export.start + self.frames.len() as f64 / self.export.fps.max(1) as f64
+    }
+
+    /// Capture `frame`, if the current frame hasn't been already. Returns
+    /// whether it was captured.
+    pub(crate) fn capture(&mut self, frame: &ImageBuf) -> bool {
+        if std::mem::replace(&mut self.captured, true) {
+            return false;
+        }
+        self.frames.push(frame.clone());
+        true
+    }
+
+    /// Move on to the next frame, returning false once all are captured.
+    pub(crate) fn advance(&mut self) -> bool {
+        self.captured = false;
+        self.frames.len() < self.frame_count()
+    }
+
+    pub(crate) fn restore(&self) -> &Playback {
+        &self.restore
+    }
+
+    /// Encode the frames on a background thread.
+    pub(crate) fn finish(self) {
+        std::thread::spawn(move || {
+            if let Err(err) = self.encode() {
+                eprintln!(
+                    "Failed to export GIF to {}: {}",
+                    self.export.path.display(),
+                    err
+                );
+            }
+        });
+    }
+
+    fn encode(&self) -> Result<(), Box<dyn Error>> {
+        let file = BufWriter::new(File::create(&self.export.path)?);
+        let mut encoder = GifEncoder::new(file);
+        if self.export.looping {
+            encoder.set_repeat(Repeat::Infinite)?;
+        }
+
+        let delay = Delay::from_numer_denom_ms(1000, self.export.fps.max(1));
+        for frame in &self.frames {
+            let image = RgbaImage::from_raw(
+                frame.width() as u32,
+                frame.height() as u32,
+                frame.raw_pixels().to_vec(),
+            );
+            if let Some(image) = image {
+                encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+            }
+        }
+        Ok(())
+    }
+}
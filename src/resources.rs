@@ -0,0 +1,481 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GPU resources referred to by handle.
+//!
+//! Raw wgpu objects die with their device, so a scene holding on to them
+//! breaks when the widget replaces a lost device. [`Resources`] keeps what
+//! each texture, buffer and mesh was made from instead, creates the objects
+//! on whichever device is current, and hands out small `Copy` handles that
+//! stay valid across device loss and can be serialized with a scene.
+//!
+//! Handles carry a generation, so a handle to a removed resource resolves to
+//! nothing rather than to whatever took its slot.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+use wgpu::util::DeviceExt;
+
+use crate::compat;
+
+/// Refers to a resource in [`Resources`].
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    #[serde(skip)]
+    kind: PhantomData<fn() -> T>,
+}
+
+pub type TextureHandle = Handle<Texture>;
+pub type BufferHandle = Handle<Buffer>;
+pub type MeshHandle = Handle<Mesh>;
+
+// Derived impls would require `T` to implement these too.
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// Resources of one kind, in slots reused after removal.
+struct Pool<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+}
+
+impl<T> Pool<T> {
+    fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, value: T) -> Handle<T> {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                self.slots.len() as u32 - 1
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.value = Some(value);
+        Handle {
+            index,
+            generation: slot.generation,
+            kind: PhantomData,
+        }
+    }
+
+    fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    fn remove(&mut self, handle: Handle<T>) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        Some(value)
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+}
+
+/// How to make a texture, see [`Resources::create_texture`].
+#[derive(Clone, Debug)]
+pub struct TextureDesc {
+    pub label: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsages,
+}
+
+/// A texture and what it was made from.
+pub struct Texture {
+    desc: TextureDesc,
+    /// Rows packed without padding, or empty for a texture only drawn to.
+    data: Vec<u8>,
+    created: Option<(wgpu::Texture, wgpu::TextureView)>,
+}
+
+impl Texture {
+    pub fn desc(&self) -> &TextureDesc {
+        &self.desc
+    }
+
+    fn create(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut usage = self.desc.usage;
+        if !self.data.is_empty() {
+            usage |= wgpu::TextureUsages::COPY_DST;
+        }
+        let size = wgpu::Extent3d {
+            width: self.desc.width,
+            height: self.desc.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: self.desc.label.as_deref(),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.desc.format,
+            usage,
+        });
+        if !self.data.is_empty() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &self.data,
+                compat::image_layout(
+                    self.data.len() as u32 / self.desc.height.max(1),
+                    self.desc.height,
+                ),
+                size,
+            );
+        }
+        let view = texture.create_view(&Default::default());
+        self.created = Some((texture, view));
+    }
+}
+
+/// A buffer and its contents, kept so it can be made again.
+pub struct Buffer {
+    label: Option<String>,
+    usage: wgpu::BufferUsages,
+    contents: Vec<u8>,
+    /// Set by `Resources::write_buffer` until the contents are uploaded.
+    dirty: bool,
+    created: Option<wgpu::Buffer>,
+}
+
+impl Buffer {
+    pub fn contents(&self) -> &[u8] {
+        &self.contents
+    }
+
+    fn create(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        match &self.created {
+            Some(buffer) if self.dirty => queue.write_buffer(buffer, 0, &self.contents),
+            Some(_) => (),
+            None => {
+                self.created = Some(
+                    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: self.label.as_deref(),
+                        contents: &self.contents,
+                        usage: self.usage | wgpu::BufferUsages::COPY_DST,
+                    }),
+                );
+            }
+        }
+        self.dirty = false;
+    }
+}
+
+/// Vertices, and optionally `u32` indices into them.
+pub struct Mesh {
+    vertices: Vec<u8>,
+    vertex_count: u32,
+    indices: Option<Vec<u32>>,
+    created: Option<(wgpu::Buffer, Option<wgpu::Buffer>)>,
+}
+
+impl Mesh {
+    fn create(&mut self, device: &wgpu::Device) {
+        if self.created.is_some() {
+            return;
+        }
+        let vertices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Vertices"),
+            contents: &self.vertices,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let indices = self.indices.as_ref().map(|indices| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Indices"),
+                contents: bytemuck::cast_slice(indices.as_slice()),
+                usage: wgpu::BufferUsages::INDEX,
+            })
+        });
+        self.created = Some((vertices, indices));
+    }
+
+    /// Bind the mesh to vertex buffer slot 0 and draw all of it.
+    pub fn draw<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, instances: std::ops::Range<u32>) {
+        let (vertices, indices) = match &self.created {
+            Some(created) => created,
+            None => return,
+        };
+        pass.set_vertex_buffer(0, vertices.slice(..));
+        match (indices, &self.indices) {
+            (Some(buffer), Some(indices)) => {
+                pass.set_index_buffer(buffer.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..indices.len() as u32, 0, instances);
+            }
+            _ => pass.draw(0..self.vertex_count, instances),
+        }
+    }
+}
+
+/// The textures, buffers and meshes of the widget's scene, see the module
+/// docs.
+///
+/// Objects for new resources are made when the widget next prepares a
+/// frame, so the `wgpu` accessors return `None` until then.
+pub struct Resources {
+    textures: Pool<Texture>,
+    buffers: Pool<Buffer>,
+    meshes: Pool<Mesh>,
+}
+
+impl Resources {
+    pub(crate) fn new() -> Self {
+        Self {
+            textures: Pool::new(),
+            buffers: Pool::new(),
+            meshes: Pool::new(),
+        }
+    }
+
+    /// A texture filled with `data`, rows of pixels packed without padding,
+    /// or left empty when `data` is.
+    pub fn create_texture(&mut self, desc: TextureDesc, data: Vec<u8>) -> TextureHandle {
+        self.textures.insert(Texture {
+            desc,
+            data,
+            created: None,
+        })
+    }
+
+    pub fn create_buffer(
+        &mut self,
+        label: Option<&str>,
+        usage: wgpu::BufferUsages,
+        contents: Vec<u8>,
+    ) -> BufferHandle {
+        self.buffers.insert(Buffer {
+            label: label.map(Into::into),
+            usage,
+            contents,
+            dirty: false,
+            created: None,
+        })
+    }
+
+    /// A mesh of `vertices`, each `vertex_size` bytes, drawn in order or by
+    /// `indices`.
+    pub fn create_mesh(
+        &mut self,
+        vertices: Vec<u8>,
+        vertex_size: usize,
+        indices: Option<Vec<u32>>,
+    ) -> MeshHandle {
+        self.meshes.insert(Mesh {
+            vertex_count: (vertices.len() / vertex_size.max(1)) as u32,
+            vertices,
+            indices,
+            created: None,
+        })
+    }
+
+    /// Replace part of a buffer's contents from `offset`, uploaded with
+    /// the next frame. Returns false for a stale handle or an overrun.
+    pub fn write_buffer(&mut self, handle: BufferHandle, offset: usize, data: &[u8]) -> bool {
+        match self.buffers.get_mut(handle) {
+            Some(buffer) if offset + data.len() <= buffer.contents.len() => {
+                buffer.contents[offset..offset + data.len()].copy_from_slice(data);
+                buffer.dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn remove_texture(&mut self, handle: TextureHandle) -> bool {
+        self.textures.remove(handle).is_some()
+    }
+
+    pub fn remove_buffer(&mut self, handle: BufferHandle) -> bool {
+        self.buffers.remove(handle).is_some()
+    }
+
+    pub fn remove_mesh(&mut self, handle: MeshHandle) -> bool {
+        self.meshes.remove(handle).is_some()
+    }
+
+    pub fn texture(&self, handle: TextureHandle) -> Option<&wgpu::Texture> {
+        let (texture, _) = self.textures.get(handle)?.created.as_ref()?;
+        Some(texture)
+    }
+
+    pub fn texture_view(&self, handle: TextureHandle) -> Option<&wgpu::TextureView> {
+        let (_, view) = self.textures.get(handle)?.created.as_ref()?;
+        Some(view)
+    }
+
+    pub fn texture_desc(&self, handle: TextureHandle) -> Option<&TextureDesc> {
+        self.textures.get(handle).map(Texture::desc)
+    }
+
+    pub fn buffer(&self, handle: BufferHandle) -> Option<&wgpu::Buffer> {
+        self.buffers.get(handle)?.created.as_ref()
+    }
+
+    /// The mesh, once it's been uploaded.
+    pub fn mesh(&self, handle: MeshHandle) -> Option<&Mesh> {
+        self.meshes
+            .get(handle)
+            .filter(|mesh| mesh.created.is_some())
+    }
+
+    /// Make the objects of new resources and upload changed buffers.
+    pub(crate) fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        for texture in self.textures.values_mut() {
+            if texture.created.is_none() {
+                texture.create(device, queue);
+            }
+        }
+        for buffer in self.buffers.values_mut() {
+            buffer.create(device, queue);
+        }
+        for mesh in self.meshes.values_mut() {
+            mesh.create(device);
+        }
+    }
+
+    /// Forget the objects of a lost device, to make them again on the next.
+    ///
+    /// They're leaked rather than dropped, like the device itself.
+    pub(crate) fn reset(&mut self) {
+        for texture in self.textures.values_mut() {
+            std::mem::forget(texture.created.take());
+        }
+        for buffer in self.buffers.values_mut() {
+            std::mem::forget(buffer.created.take());
+        }
+        for mesh in self.meshes.values_mut() {
+            std::mem::forget(mesh.created.take());
+        }
+    }
+}
+
+impl fmt::Debug for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resources")
+            .field("textures", &self.textures.len())
+            .field("buffers", &self.buffers.len())
+            .field("meshes", &self.meshes.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_handles_go_stale() {
+        let mut pool = Pool::new();
+        let first = pool.insert("first");
+        assert_eq!(pool.remove(first), Some("first"));
+        assert_eq!(pool.get(first), None);
+        assert_eq!(pool.remove(first), None);
+
+        // The slot is reused under a new generation.
+        let second = pool.insert("second");
+        assert_eq!(second.index, first.index);
+        assert_ne!(second, first);
+        assert_eq!(pool.get(first), None);
+        assert_eq!(pool.get(second), Some(&"second"));
+        assert_eq!(pool.remove(first), None);
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn handles_stay_valid_until_removed() {
+        let mut pool = Pool::new();
+        let handles: Vec<_> = (0..4).map(|i| pool.insert(i)).collect();
+        pool.remove(handles[1]);
+        if let Some(value) = pool.get_mut(handles[2]) {
+            *value = 20;
+        }
+        assert_eq!(pool.get(handles[0]), Some(&0));
+        assert_eq!(pool.get(handles[2]), Some(&20));
+        assert_eq!(pool.get(handles[3]), Some(&3));
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.values_mut().count(), 3);
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Seeded random numbers that match between Rust and WGSL.
+//!
+//! Procedural content built with these renders the same on every run and
+//! platform, whether it's generated on the CPU or in a shader. The WGSL
+//! versions are in [`WGSL`], which post effects get automatically; prepend
+//! it to other shaders to use it there.
+//!
+//! The hashes and generator match bit for bit. The noise functions match to
+//! within float rounding, since GPUs may fuse the multiplies and adds.
+
+/// The WGSL source of `pcg`, `hash2`, `hash3`, `to_unit`, the `rng_*`
+/// generator functions, `value_noise` and `gradient_noise`, mirroring the
+/// Rust ones here.
+pub const WGSL: &str = include_str!("rng.wgsl");
+
+/// PCG hash, from Jarzynski and Olano, "Hash Functions for GPU Rendering".
+pub fn pcg(value: u32) -> u32 {
+    let state = value.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277_803_737);
+    (word >> 22) ^ word
+}
+
+pub fn hash2(x: u32, y: u32) -> u32 {
+    pcg(x.wrapping_add(pcg(y)))
+}
+
+pub fn hash3(x: u32, y: u32, z: u32) -> u32 {
+    pcg(x.wrapping_add(pcg(y.wrapping_add(pcg(z)))))
+}
+
+/// The top 24 bits as a float in [0, 1), exact on every platform.
+pub fn to_unit(value: u32) -> f32 {
+    (value >> 8) as f32 / 16_777_216.0
+}
+
+/// A small generator producing the same sequence as the WGSL `rng_next`.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Self {
+        Self { state: pcg(seed) }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x9e37_79b9);
+        pcg(self.state)
+    }
+
+    /// A float in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        to_unit(self.next_u32())
+    }
+
+    /// A float in [low, high).
+    pub fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next_f32()
+    }
+}
+
+/// Smooth noise in [0, 1): a random value at every integer lattice point
+/// of `point`, blended between them. Each `seed` is a different pattern.
+pub fn value_noise(point: [f32; 2], seed: u32) -> f32 {
+    let (i, [u, v]) = lattice(point);
+    let corner =
+        |dx: u32, dy: u32| to_unit(hash3(i[0].wrapping_add(dx), i[1].wrapping_add(dy), seed));
+    let (u, v) = (fade(u), fade(v));
+    mix(
+        mix(corner(0, 0), corner(1, 0), u),
+        mix(corner(0, 1), corner(1, 1), u),
+        v,
+    )
+}
+
+/// Perlin-style noise in [-1, 1]: zero at every integer lattice point of
+/// `point`, with a random slope there. Each `seed` is a different pattern.
+pub fn gradient_noise(point: [f32; 2], seed: u32) -> f32 {
+    let (i, [x, y]) = lattice(point);
+    let corner = |dx: u32, dy: u32| {
+        let hash = hash3(i[0].wrapping_add(dx), i[1].wrapping_add(dy), seed);
+        gradient(hash, x - dx as f32, y - dy as f32)
+    };
+    let (u, v) = (fade(x), fade(y));
+    // Scaled from the largest the blend reaches, about 1.51.
+    0.65 * mix(
+        mix(corner(0, 0), corner(1, 0), u),
+        mix(corner(0, 1), corner(1, 1), u),
+        v,
+    )
+}
+
+/// The lattice cell containing `point`, wrapped to `u32`, and where in the
+/// cell it is.
+fn lattice(point: [f32; 2]) -> ([u32; 2], [f32; 2]) {
+    let cell = point.map(f32::floor);
+    (
+        cell.map(|c| c as i32 as u32),
+        [point[0] - cell[0], point[1] - cell[1]],
+    )
+}
+
+/// Quintic smoothstep, flat at both ends so noise has no visible grid.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// WGSL's `mix`, with the same rounding.
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a * (1.0 - t) + b * t
+}
+
+/// The dot product of `(x, y)` with one of eight gradients picked by
+/// `hash`.
+fn gradient(hash: u32, x: f32, y: f32) -> f32 {
+    let h = hash & 7;
+    let (u, v) = if h < 4 { (x, y) } else { (y, x) };
+    let u = if h & 1 != 0 { -u } else { u };
+    let v = if h & 2 != 0 { -2.0 * v } else { 2.0 * v };
+    u + v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pinned so the WGSL side, and anything generated from a seed, can't
+    // drift without a test noticing.
+    #[test]
+    fn pcg_is_pinned() {
+        assert_eq!(pcg(0), 129_708_002);
+        assert_eq!(pcg(1), 2_831_084_092);
+        assert_eq!(pcg(42), 1_223_963_391);
+        assert_eq!(pcg(u32::MAX), 3_861_530_882);
+    }
+
+    #[test]
+    fn hash3_is_pinned() {
+        assert_eq!(hash3(0, 0, 0), 2_145_236_065);
+        assert_eq!(hash3(1, 2, 3), 3_847_790_828);
+    }
+
+    #[test]
+    fn rng_is_pinned() {
+        let mut rng = Rng::new(7);
+        assert_eq!(rng.next_u32(), 2_066_840_103);
+        assert_eq!(rng.next_u32(), 1_159_798_347);
+        assert_eq!(rng.next_u32(), 626_971_919);
+        assert_eq!(rng.next_f32(), 0.441_112_64);
+    }
+
+    #[test]
+    fn to_unit_stays_below_one() {
+        assert_eq!(to_unit(0), 0.0);
+        assert!(to_unit(u32::MAX) < 1.0);
+    }
+
+    #[test]
+    fn noise_is_pinned() {
+        assert!((value_noise([0.5, 0.25], 1) - 0.521_992_15).abs() < 1e-6);
+        assert!((value_noise([-3.7, 12.1], 9) - 0.531_961_1).abs() < 1e-6);
+        assert!((gradient_noise([0.5, 0.25], 1) - 0.134_570_3).abs() < 1e-6);
+        assert!((gradient_noise([-3.7, 12.1], 9) + 0.006_596_64).abs() < 1e-6);
+    }
+
+    #[test]
+    fn noise_at_lattice_points() {
+        for (x, y) in [(0, 0), (3, -2), (-7, 5)] {
+            let point = [x as f32, y as f32];
+            assert_eq!(value_noise(point, 4), to_unit(hash3(x as u32, y as u32, 4)));
+            assert_eq!(gradient_noise(point, 4), 0.0);
+        }
+    }
+
+    #[test]
+    fn noise_stays_in_range_and_is_continuous() {
+        let step = 1.0 / 64.0;
+        for i in -256..256 {
+            for j in -4..4 {
+                let point = [i as f32 * step, j as f32 * 0.37];
+                let value = value_noise(point, 3);
+                let gradient = gradient_noise(point, 3);
+                assert!((0.0..1.0).contains(&value), "{:?}: {}", point, value);
+                assert!(
+                    (-1.0..=1.0).contains(&gradient),
+                    "{:?}: {}",
+                    point,
+                    gradient
+                );
+
+                let next = [point[0] + step, point[1]];
+                assert!((value_noise(next, 3) - value).abs() < 0.1);
+                assert!((gradient_noise(next, 3) - gradient).abs() < 0.2);
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_give_different_noise() {
+        let point = [1.3, 2.7];
+        assert_ne!(value_noise(point, 0), value_noise(point, 1));
+        assert_ne!(gradient_noise(point, 0), gradient_noise(point, 1));
+    }
+}
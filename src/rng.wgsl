@@ -0,0 +1,74 @@
+// Seeded random numbers, matching `druid_wgpu::rng` bit for bit.
+
+// PCG hash, from Jarzynski and Olano, "Hash Functions for GPU Rendering".
+fn pcg(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn hash2(x: u32, y: u32) -> u32 {
+    return pcg(x + pcg(y));
+}
+
+fn hash3(x: u32, y: u32, z: u32) -> u32 {
+    return pcg(x + pcg(y + pcg(z)));
+}
+
+// The top 24 bits as a float in [0, 1), exact on every platform.
+fn to_unit(value: u32) -> f32 {
+    return f32(value >> 8u) / 16777216.0;
+}
+
+// Advance a generator seeded with `rng_seed`.
+fn rng_next(state: ptr<function, u32>) -> u32 {
+    *state = *state + 2654435769u;
+    return pcg(*state);
+}
+
+fn rng_next_f32(state: ptr<function, u32>) -> f32 {
+    return to_unit(rng_next(state));
+}
+
+fn rng_seed(seed: u32) -> u32 {
+    return pcg(seed);
+}
+
+// Quintic smoothstep, flat at both ends so noise has no visible grid.
+fn noise_fade(t: vec2<f32>) -> vec2<f32> {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+// The dot product of `offset` with one of eight gradients picked by `hash`.
+fn noise_gradient(hash: u32, offset: vec2<f32>) -> f32 {
+    let h = hash & 7u;
+    let u = select(offset.y, offset.x, h < 4u);
+    let v = select(offset.x, offset.y, h < 4u);
+    return select(u, -u, (h & 1u) != 0u) + select(2.0 * v, -2.0 * v, (h & 2u) != 0u);
+}
+
+// Smooth noise in [0, 1), blending a random value at each lattice point.
+fn value_noise(p: vec2<f32>, seed: u32) -> f32 {
+    let cell = floor(p);
+    let f = p - cell;
+    let i = vec2<u32>(vec2<i32>(cell));
+    let a = to_unit(hash3(i.x, i.y, seed));
+    let b = to_unit(hash3(i.x + 1u, i.y, seed));
+    let c = to_unit(hash3(i.x, i.y + 1u, seed));
+    let d = to_unit(hash3(i.x + 1u, i.y + 1u, seed));
+    let u = noise_fade(f);
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// Perlin-style noise in [-1, 1], zero at each lattice point.
+fn gradient_noise(p: vec2<f32>, seed: u32) -> f32 {
+    let cell = floor(p);
+    let f = p - cell;
+    let i = vec2<u32>(vec2<i32>(cell));
+    let a = noise_gradient(hash3(i.x, i.y, seed), f);
+    let b = noise_gradient(hash3(i.x + 1u, i.y, seed), f - vec2<f32>(1.0, 0.0));
+    let c = noise_gradient(hash3(i.x, i.y + 1u, seed), f - vec2<f32>(0.0, 1.0));
+    let d = noise_gradient(hash3(i.x + 1u, i.y + 1u, seed), f - vec2<f32>(1.0, 1.0));
+    let u = noise_fade(f);
+    return 0.65 * mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
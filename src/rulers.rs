@@ -0,0 +1,238 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pixel rulers and draggable guides, drawn over the viewport.
+//!
+//! Dragging out of the top ruler adds a horizontal guide and out of the
+//! left one a vertical guide. Guides can be dragged around afterwards, and
+//! dragging one back onto its ruler removes it. Their positions are in
+//! [`Guides`], in the widget's logical pixels from its top left corner.
+
+use std::sync::Arc;
+
+use druid::kurbo::Line;
+use druid::piet::{FontFamily, Text, TextLayoutBuilder};
+use druid::widget::prelude::*;
+use druid::{Cursor, Data, Lens, Point};
+
+use crate::theme;
+
+/// Thickness of the rulers.
+const RULER_SIZE: f64 = 16.0;
+
+/// How close the mouse has to be to a guide to pick it up.
+const GUIDE_TOLERANCE: f64 = 3.0;
+
+#[derive(Clone, Debug, Default, Data, Lens)]
+pub struct Guides {
+    /// Positions of horizontal guides, from the top.
+    pub horizontal: Arc<Vec<f64>>,
+    /// Positions of vertical guides, from the left.
+    pub vertical: Arc<Vec<f64>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Guide {
+    Horizontal(usize),
+    Vertical(usize),
+}
+
+impl Guides {
+    fn nearest(&self, point: Point) -> Option<Guide> {
+        let near = |positions: &[f64], value: f64| {
+            positions
+                .iter()
+                .position(|position| (position - value).abs() <= GUIDE_TOLERANCE)
+        };
+        near(&self.horizontal, point.y)
+            .map(Guide::Horizontal)
+            .or_else(|| near(&self.vertical, point.x).map(Guide::Vertical))
+    }
+
+    fn add_horizontal(&mut self, position: f64) -> Guide {
+        let guides = Arc::make_mut(&mut self.horizontal);
+        guides.push(position);
+        Guide::Horizontal(guides.len() - 1)
+    }
+
+    fn add_vertical(&mut self, position: f64) -> Guide {
+        let guides = Arc::make_mut(&mut self.vertical);
+        guides.push(position);
+        Guide::Vertical(guides.len() - 1)
+    }
+
+    fn position(&self, guide: Guide) -> f64 {
+        match guide {
+            Guide::Horizontal(index) => self.horizontal[index],
+            Guide::Vertical(index) => self.vertical[index],
+        }
+    }
+
+    fn set(&mut self, guide: Guide, position: f64) {
+        match guide {
+            Guide::Horizontal(index) => Arc::make_mut(&mut self.horizontal)[index] = position,
+            Guide::Vertical(index) => Arc::make_mut(&mut self.vertical)[index] = position,
+        }
+    }
+
+    fn remove(&mut self, guide: Guide) {
+        match guide {
+            Guide::Horizontal(index) => {
+                Arc::make_mut(&mut self.horizontal).remove(index);
+            }
+            Guide::Vertical(index) => {
+                Arc::make_mut(&mut self.vertical).remove(index);
+            }
+        }
+    }
+}
+
+/// Mouse handling and drawing for the rulers, with the guide being dragged.
+#[derive(Default)]
+pub(crate) struct Rulers {
+    dragging: Option<Guide>,
+}
+
+impl Rulers {
+    /// Handle `event` if it's about the rulers or guides, returning whether
+    /// it was.
+    pub(crate) fn event(&mut self, ctx: &mut EventCtx, event: &Event, guides: &mut Guides) -> bool {
+        match event {
+            Event::MouseDown(mouse) => {
+                let pos = mouse.pos;
+                let guide = if pos.y < RULER_SIZE && pos.x >= RULER_SIZE {
+                    Some(guides.add_horizontal(pos.y))
+                } else if pos.x < RULER_SIZE && pos.y >= RULER_SIZE {
+                    Some(guides.add_vertical(pos.x))
+                } else {
+                    guides.nearest(pos)
+                };
+                self.dragging = guide;
+                if guide.is_some() {
+                    ctx.set_active(true);
+                }
+                guide.is_some()
+            }
+            Event::MouseMove(mouse) => match self.dragging {
+                Some(guide) => {
+                    let position = match guide {
+                        Guide::Horizontal(_) => mouse.pos.y,
+                        Guide::Vertical(_) => mouse.pos.x,
+                    };
+                    guides.set(guide, position.max(0.0));
+                    ctx.set_cursor(&cursor(guide));
+                    ctx.request_paint();
+                    true
+                }
+                None => {
+                    if let Some(guide) = guides.nearest(mouse.pos) {
+                        ctx.set_cursor(&cursor(guide));
+                    } else {
+                        ctx.clear_cursor();
+                    }
+                    false
+                }
+            },
+            Event::MouseUp(_) => match self.dragging.take() {
+                Some(guide) => {
+                    // Dropped back on the ruler.
+                    if guides.position(guide) < RULER_SIZE {
+                        guides.remove(guide);
+                    }
+                    ctx.set_active(false);
+                    ctx.request_paint();
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub(crate) fn paint(&self, ctx: &mut PaintCtx, guides: &Guides, env: &Env) {
+        let size = ctx.size();
+        let background = env
+            .try_get(theme::RULER_BACKGROUND)
+            .unwrap_or(theme::DARK_RULER_BACKGROUND);
+        let ticks_color = env
+            .try_get(theme::RULER_TICKS)
+            .unwrap_or(theme::DARK_RULER_TICKS);
+        let guide_color = env
+            .try_get(theme::GUIDE_COLOR)
+            .unwrap_or(theme::DARK_GUIDE_COLOR);
+
+        for &y in guides.horizontal.iter() {
+            let y = y.round() + 0.5;
+            ctx.stroke(Line::new((0.0, y), (size.width, y)), &guide_color, 1.0);
+        }
+        for &x in guides.vertical.iter() {
+            let x = x.round() + 0.5;
+            ctx.stroke(Line::new((x, 0.0), (x, size.height)), &guide_color, 1.0);
+        }
+
+        ctx.fill(Size::new(size.width, RULER_SIZE).to_rect(), &background);
+        ctx.fill(Size::new(RULER_SIZE, size.height).to_rect(), &background);
+
+        // Ticks every 10 pixels, longer every 50, with a label every 100.
+        let mut ticks = |length: f64, horizontal: bool| {
+            let mut position = 0.0;
+            while position < length {
+                let step = (position / 10.0).round() as u32;
+                let tick = if step % 10 == 0 {
+                    RULER_SIZE
+                } else if step % 5 == 0 {
+                    RULER_SIZE * 0.5
+                } else {
+                    RULER_SIZE * 0.25
+                };
+                let offset = position + 0.5;
+                let line = if horizontal {
+                    Line::new((offset, RULER_SIZE - tick), (offset, RULER_SIZE))
+                } else {
+                    Line::new((RULER_SIZE - tick, offset), (RULER_SIZE, offset))
+                };
+                if position >= RULER_SIZE {
+                    ctx.stroke(line, &ticks_color, 1.0);
+                }
+
+                if step % 10 == 0 && position > 0.0 {
+                    let layout = ctx
+                        .text()
+                        .new_text_layout(format!("{}", position))
+                        .font(FontFamily::SYSTEM_UI, 9.0)
+                        .text_color(ticks_color)
+                        .build();
+                    if let Ok(layout) = layout {
+                        let origin = if horizontal {
+                            (position + 2.0, 0.0)
+                        } else {
+                            (1.0, position + 1.0)
+                        };
+                        ctx.draw_text(&layout, origin);
+                    }
+                }
+                position += 10.0;
+            }
+        };
+        ticks(size.width, true);
+        ticks(size.height, false);
+    }
+}
+
+fn cursor(guide: Guide) -> Cursor {
+    match guide {
+        Guide::Horizontal(_) => Cursor::ResizeUpDown,
+        Guide::Vertical(_) => Cursor::ResizeLeftRight,
+    }
+}
@@ -0,0 +1,290 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Custom scenes, drawn instead of the built-in one.
+//!
+//! A [`WgpuScene`] records its own draw calls into the widget's scene pass,
+//! and everything after that, post effects, accumulation, the present pass
+//! and readback, still applies. Scenes are made by a closure given the
+//! widget's device and queue, see [`WgpuWidget::from_fn`], which runs again
+//! whenever the device has to be replaced. What should outlive the device,
+//! like textures loaded from disk, goes in [`Resources`] instead.
+//!
+//! Large scenes can also record command buffers on several threads at once
+//! in [`WgpuScene::encode`], see [`encode_parallel`].
+//!
+//! [`WgpuWidget::from_fn`]: crate::WgpuWidget::from_fn
+
+use druid::Event;
+
+use crate::gpu::Gpu;
+use crate::resources::Resources;
+use crate::rng;
+use crate::ViewportState;
+
+pub use crate::gpu::SCENE_FORMAT;
+
+/// What a scene gets to prepare a frame with.
+pub struct SceneContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    /// The widget's resources, which outlive the device, unlike the scene.
+    pub resources: &'a mut Resources,
+}
+
+/// What a scene gets to record command buffers with, see
+/// [`WgpuScene::encode`].
+pub struct EncodeContext<'a> {
+    pub device: &'a wgpu::Device,
+    /// The scene's target, already drawn by [`WgpuScene::render`]. Passes
+    /// into it should load rather than clear it.
+    pub target: &'a wgpu::TextureView,
+    pub resources: &'a Resources,
+    pub frame: SceneFrame,
+}
+
+/// What a frame is rendered at, given to [`WgpuScene::prepare`].
+#[derive(Clone, Copy, Debug)]
+pub struct SceneFrame {
+    /// Size of the target in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// `Playback::time` of the frame.
+    pub time: f64,
+    /// Counts up with every frame rendered.
+    pub index: u64,
+    /// How far the frame is between the last [`WgpuScene::on_update`] step
+    /// and the next, in `[0, 1)`, see [`Interpolated::get`].
+    ///
+    /// [`Interpolated::get`]: crate::timestep::Interpolated::get
+    pub alpha: f64,
+}
+
+/// A scene drawn into the widget's scene pass.
+///
+/// Besides drawing, a scene can keep its own cameras, clocks and input
+/// state, driven by the widget through the `on_*` hooks, and its own
+/// previous frames in a [`History`](crate::history::History).
+pub trait WgpuScene {
+    /// Called before the first frame, and before any frame whose size in
+    /// pixels differs from the last one.
+    fn on_resize(&mut self, _width: u32, _height: u32) {}
+
+    /// Called once per fixed simulation step while playback is playing,
+    /// with `dt` always the same, 60 steps per second of playback. See
+    /// [`timestep`](crate::timestep).
+    fn on_update(&mut self, _dt: f64, _data: &ViewportState) {}
+
+    /// Called when playback jumps to `time` instead of playing up to it:
+    /// when it's added, scrubbed, looped around, or stepped through by an
+    /// export. Simulations should restart from a state that depends only on
+    /// `time`, so the same time always renders the same frame.
+    fn on_seek(&mut self, _time: f64) {}
+
+    /// Called with every event the widget gets, before it handles them
+    /// itself. Return true to consume the event and repaint.
+    fn on_event(&mut self, _event: &Event) -> bool {
+        false
+    }
+
+    /// Called before each frame is encoded, to write uniforms and buffers.
+    /// Resources created here are uploaded before [`render`](Self::render).
+    fn prepare(&mut self, _ctx: &mut SceneContext, _frame: &SceneFrame) {}
+
+    /// Draw into `pass`, which has a single [`SCENE_FORMAT`] color target
+    /// and no depth or stencil attachment. Resources are looked up by handle
+    /// in `resources`.
+    ///
+    /// Group 0 is bound to the widget's `globals`, `audio` and `params`,
+    /// laid out as in `scene.wgsl`; scenes with their own bind groups just
+    /// set them over it.
+    fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, resources: &'a Resources);
+
+    /// Command buffers to submit after the scene pass, in order and before
+    /// the widget's own passes read the frame. For encoding parts of a large
+    /// scene on other threads with [`encode_parallel`].
+    fn encode(&self, _ctx: &EncodeContext) -> Vec<wgpu::CommandBuffer> {
+        Vec::new()
+    }
+}
+
+/// Run each of `jobs` on a thread of its own with an encoder of its own,
+/// and return the finished command buffers in the order of `jobs`.
+pub fn encode_parallel<J>(
+    device: &wgpu::Device,
+    jobs: impl IntoIterator<Item = J>,
+) -> Vec<wgpu::CommandBuffer>
+where
+    J: FnOnce(&mut wgpu::CommandEncoder) + Send,
+{
+    std::thread::scope(|scope| {
+        let threads: Vec<_> = jobs
+            .into_iter()
+            .map(|job| {
+                scope.spawn(move || {
+                    let mut encoder =
+                        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Scene Partition Encoder"),
+                        });
+                    job(&mut encoder);
+                    encoder.finish()
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+            })
+            .collect()
+    })
+}
+
+impl WgpuScene for Box<dyn WgpuScene> {
+    fn on_resize(&mut self, width: u32, height: u32) {
+        (**self).on_resize(width, height)
+    }
+
+    fn on_update(&mut self, dt: f64, data: &ViewportState) {
+        (**self).on_update(dt, data)
+    }
+
+    fn on_seek(&mut self, time: f64) {
+        (**self).on_seek(time)
+    }
+
+    fn on_event(&mut self, event: &Event) -> bool {
+        (**self).on_event(event)
+    }
+
+    fn prepare(&mut self, ctx: &mut SceneContext, frame: &SceneFrame) {
+        (**self).prepare(ctx, frame)
+    }
+
+    fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, resources: &'a Resources) {
+        (**self).render(pass, resources)
+    }
+
+    fn encode(&self, ctx: &EncodeContext) -> Vec<wgpu::CommandBuffer> {
+        (**self).encode(ctx)
+    }
+}
+
+type BuildScene = Box<dyn FnMut(&Gpu) -> Box<dyn WgpuScene>>;
+
+/// A custom scene and how to make it again on a new device.
+pub(crate) struct CustomScene {
+    build: BuildScene,
+    pub(crate) scene: Box<dyn WgpuScene>,
+    /// The size last given to `on_resize`.
+    size: Option<(u32, u32)>,
+}
+
+impl CustomScene {
+    pub(crate) fn new(gpu: &Gpu, mut build: BuildScene) -> Self {
+        let scene = build(gpu);
+        Self {
+            build,
+            scene,
+            size: None,
+        }
+    }
+
+    pub(crate) fn from_fn<S: WgpuScene + 'static>(
+        gpu: &Gpu,
+        mut build: impl FnMut(&wgpu::Device, &wgpu::Queue) -> S + 'static,
+    ) -> Self {
+        Self::new(
+            gpu,
+            Box::new(move |gpu: &Gpu| -> Box<dyn WgpuScene> {
+                Box::new(build(&gpu.device, &gpu.queue))
+            }),
+        )
+    }
+
+    pub(crate) fn shader(gpu: &Gpu, source: String) -> Self {
+        Self::new(
+            gpu,
+            Box::new(move |gpu: &Gpu| -> Box<dyn WgpuScene> {
+                Box::new(ShaderScene::new(gpu, &source))
+            }),
+        )
+    }
+
+    /// Make the scene again for a new device.
+    pub(crate) fn rebuild(&mut self, gpu: &Gpu) {
+        self.scene = (self.build)(gpu);
+        self.size = None;
+    }
+
+    /// Tell the scene about a frame of `width` by `height`, if that's new.
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        if self.size != Some((width, height)) {
+            self.size = Some((width, height));
+            self.scene.on_resize(width, height);
+        }
+    }
+}
+
+/// A fragment shader over the whole frame, see `WgpuWidget::with_shader`.
+struct ShaderScene {
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl ShaderScene {
+    fn new(gpu: &Gpu, source: &str) -> Self {
+        let shader = gpu
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Scene Shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    format!("{}{}{}", include_str!("scene.wgsl"), rng::WGSL, source).into(),
+                ),
+            });
+        let pipeline = gpu
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Scene Shader Pipeline"),
+                layout: Some(&gpu.render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: SCENE_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+        Self { pipeline }
+    }
+}
+
+impl WgpuScene for ShaderScene {
+    fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, _resources: &'a Resources) {
+        pass.set_pipeline(&self.pipeline);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,59 @@
+// Prelude of `WgpuWidget::with_shader`. The shader's own source follows and
+// defines `fs_main`, drawn over the whole frame.
+
+struct Globals {
+    time: f32,
+    high_contrast: f32,
+    sample: f32,
+    instances: f32,
+    // From 0 to 1 across the widget, see `WgpuWidget::with_cursor_input`.
+    cursor: vec2<f32>,
+    // Offset of this sample in clip space, see `still::EXPORT_STILL`.
+    jitter: vec2<f32>,
+    // Scale then offset from the whole image's clip space to the target's,
+    // see `still::EXPORT_PRINT`.
+    tile: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+// Spectrum bands and waveform samples, four per vector.
+struct Audio {
+    spectrum: array<vec4<f32>, 8>,
+    waveform: array<vec4<f32>, 8>,
+};
+
+@group(0) @binding(1)
+var<uniform> audio: Audio;
+
+// Named parameters, in the order given to `WgpuWidget::with_param_layout`.
+struct Params {
+    slots: array<vec4<f32>, 16>,
+};
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
+struct SceneVertex {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Where `position`, in the target's clip space, falls in the whole image,
+// undoing the sample's jitter and the tile of `still::EXPORT_PRINT`.
+fn image_uv(position: vec2<f32>) -> vec2<f32> {
+    let clip = (position - globals.jitter - globals.tile.zw) / globals.tile.xy;
+    return vec2<f32>(clip.x * 0.5 + 0.5, 0.5 - clip.y * 0.5);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> SceneVertex {
+    // A single triangle covering the whole target.
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: SceneVertex;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = image_uv(out.position.xy);
+    return out;
+}
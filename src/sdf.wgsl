@@ -0,0 +1,99 @@
+// Signed distance functions, after Inigo Quilez's articles on distance
+// functions and smooth minimums. Primitives are centered on the origin;
+// transform the point instead of the shape.
+
+fn sd_sphere(p: vec3<f32>, radius: f32) -> f32 {
+    return length(p) - radius;
+}
+
+// `half_size` is the distance from the center to each face.
+fn sd_box(p: vec3<f32>, half_size: vec3<f32>) -> f32 {
+    let q = abs(p) - half_size;
+    return length(max(q, vec3<f32>(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0);
+}
+
+fn sd_round_box(p: vec3<f32>, half_size: vec3<f32>, radius: f32) -> f32 {
+    return sd_box(p, half_size - radius) - radius;
+}
+
+// A torus around the y axis.
+fn sd_torus(p: vec3<f32>, major_radius: f32, minor_radius: f32) -> f32 {
+    let q = vec2<f32>(length(p.xz) - major_radius, p.y);
+    return length(q) - minor_radius;
+}
+
+// A capsule around the segment from `a` to `b`.
+fn sd_capsule(p: vec3<f32>, a: vec3<f32>, b: vec3<f32>, radius: f32) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / dot(ba, ba), 0.0, 1.0);
+    return length(pa - ba * h) - radius;
+}
+
+// A capped cylinder along the y axis.
+fn sd_cylinder(p: vec3<f32>, radius: f32, half_height: f32) -> f32 {
+    let d = abs(vec2<f32>(length(p.xz), p.y)) - vec2<f32>(radius, half_height);
+    return min(max(d.x, d.y), 0.0) + length(max(d, vec2<f32>(0.0)));
+}
+
+// The plane through the origin facing `normal`, which must be normalized.
+fn sd_plane(p: vec3<f32>, normal: vec3<f32>) -> f32 {
+    return dot(p, normal);
+}
+
+fn op_union(a: f32, b: f32) -> f32 {
+    return min(a, b);
+}
+
+// `a` with `b` cut out of it.
+fn op_subtract(a: f32, b: f32) -> f32 {
+    return max(a, -b);
+}
+
+fn op_intersect(a: f32, b: f32) -> f32 {
+    return max(a, b);
+}
+
+// Smooth versions blend the shapes over a distance of about `k`.
+fn op_smooth_union(a: f32, b: f32, k: f32) -> f32 {
+    let h = clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+    return mix(b, a, h) - k * h * (1.0 - h);
+}
+
+fn op_smooth_subtract(a: f32, b: f32, k: f32) -> f32 {
+    let h = clamp(0.5 - 0.5 * (a + b) / k, 0.0, 1.0);
+    return mix(a, -b, h) + k * h * (1.0 - h);
+}
+
+fn op_smooth_intersect(a: f32, b: f32, k: f32) -> f32 {
+    let h = clamp(0.5 - 0.5 * (b - a) / k, 0.0, 1.0);
+    return mix(b, a, h) + k * h * (1.0 - h);
+}
+
+// Hollow out a shape, leaving a shell `thickness` thick.
+fn op_shell(d: f32, thickness: f32) -> f32 {
+    return abs(d) - thickness;
+}
+
+// Repeat space every `period`, to evaluate a shape at the returned point.
+fn op_repeat(p: vec3<f32>, period: vec3<f32>) -> vec3<f32> {
+    return p - period * round(p / period);
+}
+
+fn rotate_x(p: vec3<f32>, angle: f32) -> vec3<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec3<f32>(p.x, c * p.y - s * p.z, s * p.y + c * p.z);
+}
+
+fn rotate_y(p: vec3<f32>, angle: f32) -> vec3<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec3<f32>(c * p.x + s * p.z, p.y, c * p.z - s * p.x);
+}
+
+fn rotate_z(p: vec3<f32>, angle: f32) -> vec3<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec3<f32>(c * p.x - s * p.y, s * p.x + c * p.y, p.z);
+}
@@ -0,0 +1,154 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Remembering a viewport's settings between runs.
+//!
+//! `WgpuWidget::with_persistence` keeps the wireframe toggle, the
+//! parameters and the guides in a RON file, under a key naming the
+//! viewport, and puts them back when the widget is added. Several
+//! viewports can share one file with different keys.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use druid::{Data, TimerToken};
+use serde::{Deserialize, Serialize};
+
+use crate::{Guides, Params, ViewportState};
+
+/// How long after the last change settings are written, so dragging a
+/// slider doesn't write the file on every step.
+pub(crate) const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// What's remembered of one viewport.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ViewportSettings {
+    #[serde(default)]
+    pub wireframe: bool,
+    #[serde(default)]
+    pub params: Params,
+    #[serde(default)]
+    pub horizontal_guides: Vec<f64>,
+    #[serde(default)]
+    pub vertical_guides: Vec<f64>,
+}
+
+impl ViewportSettings {
+    fn capture(data: &ViewportState, wireframe: bool) -> Self {
+        Self {
+            wireframe,
+            params: data.params.clone(),
+            horizontal_guides: data.guides.horizontal.to_vec(),
+            vertical_guides: data.guides.vertical.to_vec(),
+        }
+    }
+}
+
+pub(crate) struct Persistence {
+    path: PathBuf,
+    key: String,
+    /// The settings found when the widget was created, until restored.
+    loaded: Option<ViewportSettings>,
+    /// Changes not written yet.
+    pending: Option<ViewportSettings>,
+    save_timer: TimerToken,
+}
+
+impl Persistence {
+    pub(crate) fn new(path: PathBuf, key: String) -> Self {
+        let loaded = read(&path).and_then(|mut viewports| viewports.remove(&key));
+        Self {
+            path,
+            key,
+            loaded,
+            pending: None,
+            save_timer: TimerToken::INVALID,
+        }
+    }
+
+    /// Put the loaded settings into `data`, returning the wireframe toggle,
+    /// or `None` when there was nothing to restore.
+    pub(crate) fn restore(&mut self, data: &mut ViewportState) -> Option<bool> {
+        let settings = self.loaded.take()?;
+        if !settings.params.same(&data.params) {
+            data.params = settings.params;
+        }
+        data.guides = Guides {
+            horizontal: Arc::new(settings.horizontal_guides),
+            vertical: Arc::new(settings.vertical_guides),
+        };
+        Some(settings.wireframe)
+    }
+
+    /// Note a change, returning whether a save timer should be started.
+    pub(crate) fn changed(&mut self, data: &ViewportState, wireframe: bool) -> bool {
+        self.pending = Some(ViewportSettings::capture(data, wireframe));
+        self.save_timer == TimerToken::INVALID
+    }
+
+    pub(crate) fn set_timer(&mut self, token: TimerToken) {
+        self.save_timer = token;
+    }
+
+    /// Write pending changes if `token` is the save timer. Returns whether
+    /// it was.
+    pub(crate) fn timer(&mut self, token: TimerToken) -> bool {
+        if token != self.save_timer {
+            return false;
+        }
+        self.save_timer = TimerToken::INVALID;
+        self.save();
+        true
+    }
+
+    /// Write pending changes, keeping other viewports' settings in the file.
+    pub(crate) fn save(&mut self) {
+        let settings = match self.pending.take() {
+            Some(settings) => settings,
+            None => return,
+        };
+        let mut viewports = read(&self.path).unwrap_or_default();
+        viewports.insert(self.key.clone(), settings);
+
+        let written = ron::ser::to_string_pretty(&viewports, Default::default())
+            .map_err(|err| err.to_string())
+            .and_then(|ron| std::fs::write(&self.path, ron).map_err(|err| err.to_string()));
+        if let Err(err) = written {
+            eprintln!(
+                "Failed to save viewport settings to {}: {}",
+                self.path.display(),
+                err
+            );
+        }
+    }
+}
+
+impl Drop for Persistence {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+fn read(path: &PathBuf) -> Option<BTreeMap<String, ViewportSettings>> {
+    let source = std::fs::read_to_string(path).ok()?;
+    match ron::from_str(&source) {
+        Ok(viewports) => Some(viewports),
+        Err(err) => {
+            eprintln!("Ignoring viewport settings in {}: {}", path.display(), err);
+            None
+        }
+    }
+}
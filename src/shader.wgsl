@@ -1,3 +1,37 @@
+struct Globals {
+    time: f32,
+    high_contrast: f32,
+    sample: f32,
+    instances: f32,
+    // From 0 to 1 across the widget, see `WgpuWidget::with_cursor_input`.
+    cursor: vec2<f32>,
+    // Offset of this sample in clip space, see `still::EXPORT_STILL`.
+    jitter: vec2<f32>,
+    // Scale then offset from the whole image's clip space to the target's,
+    // see `still::EXPORT_PRINT`.
+    tile: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+// Spectrum bands and waveform samples, four per vector.
+struct Audio {
+    spectrum: array<vec4<f32>, 8>,
+    waveform: array<vec4<f32>, 8>,
+};
+
+@group(0) @binding(1)
+var<uniform> audio: Audio;
+
+// Named parameters, in the order given to `WgpuWidget::with_param_layout`.
+struct Params {
+    slots: array<vec4<f32>, 16>,
+};
+
+@group(0) @binding(2)
+var<uniform> params: Params;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
@@ -8,17 +42,49 @@ struct VertexOutput {
     @location(0) color: vec3<f32>,
 };
 
+// A ball dropped from 0.5 with gravity 2, bouncing forever. It only
+// depends on `time`, so seeking and exports render the same frame.
+fn bounce(time: f32) -> f32 {
+    let period = sqrt(2.0);
+    let t = time - period * floor(time / period + 0.5);
+    return 0.5 - t * t;
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
+    @builtin(instance_index) instance: u32
 ) -> VertexOutput {
+    let speed = params.slots[0].x;
+    // Pulse with the bass bands.
+    let scale = params.slots[1].x * (1.0 + 0.5 * audio.spectrum[0].y);
+    let c = cos(globals.time * speed) * scale;
+    let s = sin(globals.time * speed) * scale;
+    let position = vec2<f32>(
+        c * model.position.x - s * model.position.y,
+        s * model.position.x + c * model.position.y
+    ) + vec2<f32>(0.0, bounce(globals.time) - 0.25);
+
+    // Instances fill a square grid, each in its own cell.
+    let columns = ceil(sqrt(max(globals.instances, 1.0)));
+    let cell = vec2<f32>(f32(instance % u32(columns)), f32(instance / u32(columns)));
+    let cell_center = (cell + 0.5) / columns * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let placed = position / columns + cell_center;
+
     var out: VertexOutput;
     out.color = model.color;
-    out.clip_position = vec4<f32>(model.position, 1.0);
+    out.clip_position = vec4<f32>(
+        placed * globals.tile.xy + globals.tile.zw + globals.jitter,
+        model.position.z,
+        1.0
+    );
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (globals.high_contrast > 0.5) {
+        return vec4<f32>(1.0);
+    }
     return vec4<f32>(in.color, 1.0);
 }
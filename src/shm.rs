@@ -0,0 +1,176 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Frame output to shared memory (`shm` feature).
+//!
+//! A [`SharedFrameOutput`] writes every frame into a ring of slots in a
+//! file, which on Linux lives in `/dev/shm` and so never touches the disk.
+//! Compositors and VJ tools map the file and read the newest frame without
+//! going through the network or the GPU. Syphon and Spout, which share
+//! textures directly, need platform interop that isn't available here.
+//!
+//! The file starts with a 64 byte header, all fields little-endian:
+//!
+//! | offset | field                                            |
+//! |--------|--------------------------------------------------|
+//! | 0      | magic, `b"DWGPUSHM"`                             |
+//! | 8      | layout version, `u32`, currently 1               |
+//! | 12     | number of slots, `u32`                           |
+//! | 16     | largest frame width, `u32`                       |
+//! | 20     | largest frame height, `u32`                      |
+//! | 24     | bytes per slot after its header, `u32`           |
+//! | 32     | sequence number of the newest frame, `u64`       |
+//!
+//! Slots follow in order, each a 16 byte header of the frame's sequence
+//! number (`u64`), width and height (`u32`s), then tightly packed
+//! premultiplied RGBA8 rows in sRGB. Frame `n` is in slot `n % slots`.
+//! Readers should check the slot's sequence number again after copying
+//! the pixels, and retry when it changed underneath them.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+use druid::ImageBuf;
+
+use crate::sink::{FrameRef, FrameSink};
+
+const MAGIC: &[u8; 8] = b"DWGPUSHM";
+const VERSION: u32 = 1;
+const HEADER_SIZE: u64 = 64;
+const SLOT_HEADER_SIZE: u64 = 16;
+
+/// Byte offset of the newest sequence number in the header.
+const SEQUENCE_OFFSET: u64 = 32;
+
+pub struct SharedFrameOutput {
+    file: File,
+    slots: u32,
+    max_width: u32,
+    max_height: u32,
+    sequence: u64,
+    /// Whether a frame too big for the slots was already reported.
+    warned: bool,
+}
+
+impl SharedFrameOutput {
+    /// Create or replace the ring at `path`, with `slots` slots for frames
+    /// of up to `max_width` by `max_height` pixels.
+    pub fn create(
+        path: impl AsRef<Path>,
+        slots: u32,
+        max_width: u32,
+        max_height: u32,
+    ) -> io::Result<Self> {
+        let slots = slots.max(1);
+        let slot_size = max_width as u64 * max_height as u64 * 4;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(HEADER_SIZE + slots as u64 * (SLOT_HEADER_SIZE + slot_size))?;
+
+        let mut header = [0u8; HEADER_SIZE as usize];
+        header[0..8].copy_from_slice(MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&slots.to_le_bytes());
+        header[16..20].copy_from_slice(&max_width.to_le_bytes());
+        header[20..24].copy_from_slice(&max_height.to_le_bytes());
+        header[24..28].copy_from_slice(&(slot_size as u32).to_le_bytes());
+        write_at(&file, &header, 0)?;
+
+        Ok(Self {
+            file,
+            slots,
+            max_width,
+            max_height,
+            sequence: 0,
+            warned: false,
+        })
+    }
+
+    /// The `/dev/shm` path for a ring called `name`, or one in the
+    /// temporary directory where there's no `/dev/shm`.
+    pub fn default_path(name: &str) -> std::path::PathBuf {
+        let shm = Path::new("/dev/shm");
+        let dir = if shm.is_dir() {
+            shm.to_path_buf()
+        } else {
+            std::env::temp_dir()
+        };
+        dir.join(name)
+    }
+
+    /// Write `frame` into the next slot and make it the newest.
+    pub fn publish(&mut self, frame: &ImageBuf) {
+        let width = frame.width() as u32;
+        let height = frame.height() as u32;
+        if width > self.max_width || height > self.max_height {
+            if !std::mem::replace(&mut self.warned, true) {
+                eprintln!(
+                    "Frames of {}x{} don't fit the shared memory slots of {}x{}, skipping",
+                    width, height, self.max_width, self.max_height
+                );
+            }
+            return;
+        }
+
+        let sequence = self.sequence + 1;
+        let slot_size = SLOT_HEADER_SIZE + self.max_width as u64 * self.max_height as u64 * 4;
+        let slot = HEADER_SIZE + (sequence % self.slots as u64) * slot_size;
+
+        // Invalidate the slot while its pixels change.
+        let mut slot_header = [0u8; SLOT_HEADER_SIZE as usize];
+        slot_header[8..12].copy_from_slice(&width.to_le_bytes());
+        slot_header[12..16].copy_from_slice(&height.to_le_bytes());
+        let written = write_at(&self.file, &slot_header, slot)
+            .and_then(|_| write_at(&self.file, frame.raw_pixels(), slot + SLOT_HEADER_SIZE))
+            .and_then(|_| {
+                slot_header[0..8].copy_from_slice(&sequence.to_le_bytes());
+                write_at(&self.file, &slot_header, slot)
+            })
+            .and_then(|_| write_at(&self.file, &sequence.to_le_bytes(), SEQUENCE_OFFSET));
+
+        match written {
+            Ok(()) => self.sequence = sequence,
+            Err(err) => eprintln!("Failed to write a frame to shared memory: {}", err),
+        }
+    }
+}
+
+impl FrameSink for SharedFrameOutput {
+    fn submit(&mut self, frame: FrameRef) {
+        self.publish(frame.image);
+    }
+}
+
+#[cfg(unix)]
+fn write_at(file: &File, data: &[u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, data, offset)
+}
+
+#[cfg(windows)]
+fn write_at(file: &File, mut data: &[u8], mut offset: u64) -> io::Result<()> {
+    while !data.is_empty() {
+        let written = std::os::windows::fs::FileExt::seek_write(file, data, offset)?;
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        data = &data[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Taps on the viewport's frames.
+//!
+//! Every [`FrameSink`] given to `WgpuWidget::with_frame_sink` is handed
+//! each new frame after it's read back, in the order the sinks were added.
+//! Heavy sinks, like encoders and network outputs, can be given every Nth
+//! frame with `WgpuWidget::with_frame_sink_every` instead, while the
+//! viewport itself keeps updating at full rate.
+//! Streaming, shared memory output and image sequences are all sinks, and
+//! so can be combined freely.
+//!
+//! Frames are copied out of the GPU's mapped buffer once. Every sink, and
+//! the widget itself, then shares that copy: cloning an [`ImageBuf`] only
+//! bumps a reference count, so sinks that need a frame for longer than
+//! `submit` should keep [`FrameRef::share`] rather than copy the pixels.
+
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+
+use druid::ImageBuf;
+use image::{ImageBuffer, Rgba};
+
+/// A frame handed to a [`FrameSink`].
+#[derive(Clone, Copy)]
+pub struct FrameRef<'a> {
+    /// Premultiplied RGBA8 pixels, in sRGB.
+    pub image: &'a ImageBuf,
+    /// Counts paints since the widget was created.
+    pub index: u64,
+    /// Playback time of the frame, in seconds.
+    pub time: f64,
+}
+
+impl FrameRef<'_> {
+    /// Premultiplied RGBA8 rows, tightly packed.
+    pub fn pixels(&self) -> &[u8] {
+        self.image.raw_pixels()
+    }
+
+    /// The frame, for keeping past `submit`, without copying its pixels.
+    pub fn share(&self) -> ImageBuf {
+        self.image.clone()
+    }
+}
+
+pub trait FrameSink {
+    /// Called from paint for each new frame. Frames that didn't change
+    /// since the last one aren't submitted again.
+    fn submit(&mut self, frame: FrameRef);
+}
+
+impl<F: FnMut(FrameRef)> FrameSink for F {
+    fn submit(&mut self, frame: FrameRef) {
+        self(frame)
+    }
+}
+
+/// A sink and how often it's fed.
+pub(crate) struct SinkSlot {
+    sink: Box<dyn FrameSink>,
+    every: u32,
+    /// New frames since this sink was last fed.
+    skipped: u32,
+}
+
+impl SinkSlot {
+    pub(crate) fn new(sink: Box<dyn FrameSink>, every: u32) -> Self {
+        let every = every.max(1);
+        Self {
+            sink,
+            every,
+            // Feed the first frame.
+            skipped: every - 1,
+        }
+    }
+
+    pub(crate) fn submit(&mut self, frame: FrameRef) {
+        self.skipped += 1;
+        if self.skipped >= self.every {
+            self.skipped = 0;
+            self.sink.submit(frame);
+        }
+    }
+}
+
+/// Writes every frame to a numbered PNG in a directory, on a background
+/// thread.
+pub struct PngSequence {
+    frames: Sender<(u64, ImageBuf)>,
+}
+
+impl PngSequence {
+    /// Write frames to `dir` as `frame-000042.png`, named by frame index.
+    /// The directory must exist.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        let (frames, received) = mpsc::channel::<(u64, ImageBuf)>();
+        std::thread::spawn(move || {
+            for (index, frame) in received {
+                let path = dir.join(format!("frame-{:06}.png", index));
+                let image = ImageBuffer::<Rgba<u8>, _>::from_raw(
+                    frame.width() as u32,
+                    frame.height() as u32,
+                    frame.raw_pixels(),
+                );
+                if let Some(Err(err)) = image.map(|image| image.save(&path)) {
+                    eprintln!("Failed to write {}: {}", path.display(), err);
+                }
+            }
+        });
+        Self { frames }
+    }
+}
+
+impl FrameSink for PngSequence {
+    fn submit(&mut self, frame: FrameRef) {
+        let _ = self.frames.send((frame.index, frame.share()));
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Data shared between the app and the viewport widget.
+
+use druid::{Data, Lens};
+
+use crate::{GpuCapabilities, Guides, Params, Playback};
+
+/// State shared between the app and the viewport widget.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct ViewportState {
+    pub playback: Playback,
+    pub params: Params,
+    /// Guides dragged out of the rulers, see `WgpuWidget::with_rulers`.
+    pub guides: Guides,
+    /// The widget's GPU, once it's been added.
+    pub gpu: Option<GpuCapabilities>,
+}
+
+impl ViewportState {
+    pub fn new(playback: Playback, params: Params) -> Self {
+        Self {
+            playback,
+            params,
+            guides: Guides::default(),
+            gpu: None,
+        }
+    }
+}
@@ -0,0 +1,329 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Supersampled still images.
+//!
+//! Sending [`EXPORT_STILL`] to the viewport renders the current frame again
+//! at up to [`MAX_SCALE`] times its size, averaging many samples, each
+//! offset by a fraction of a pixel, into a smooth, noise-free PNG. This
+//! doesn't depend on the interactive settings: it works without
+//! `WgpuWidget::with_accumulation`, and on software rasterizers too.
+//!
+//! [`EXPORT_PRINT`] goes further, to any size in pixels: the image is cut
+//! into tiles the device can render, each drawn with the clip space of the
+//! whole image scaled and moved so only its part lands in the target, and
+//! the tiles are stitched together on the CPU. Post effects work on whole
+//! frames, so prints go without them, even when they fit in one tile, so
+//! the same print looks the same on every device.
+//!
+//! Shaders see each sample's offset as `globals.jitter`, in clip space, its
+//! index as `globals.sample`, and the tile as `globals.tile`, the scale
+//! then offset from the whole image's clip space to the target's. The
+//! built-in scene, masks and `WgpuWidget::with_shader` apply both
+//! themselves, so `uv` always runs across the whole image; custom
+//! `WgpuScene` pipelines need to do the same.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use druid::Selector;
+use image::RgbaImage;
+
+use crate::compat;
+use crate::gpu::{self, Gpu, ReadbackError};
+
+/// Render the viewport's current frame to a PNG, see the module docs.
+pub const EXPORT_STILL: Selector<StillExport> = Selector::new("druid-wgpu.export-still");
+
+/// Render a poster-sized image of the viewport's current frame, in tiles.
+pub const EXPORT_PRINT: Selector<PrintExport> = Selector::new("druid-wgpu.export-print");
+
+/// The most a still can be scaled up from the viewport's size.
+pub const MAX_SCALE: u32 = 8;
+
+/// Largest tile of a print, on devices allowing bigger textures, so each
+/// tile's readback stays a reasonable size.
+pub(crate) const MAX_TILE_SIZE: u32 = 4096;
+
+#[derive(Clone, Debug)]
+pub struct StillExport {
+    pub path: PathBuf,
+    /// How many times the viewport's size in pixels to render at, up to
+    /// [`MAX_SCALE`] and the largest that fits the device's textures. Use
+    /// [`EXPORT_PRINT`] for more.
+    pub scale: u32,
+    /// Jittered samples to average per pixel.
+    pub samples: u32,
+}
+
+impl StillExport {
+    /// Twice the viewport's size, with 16 samples.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            scale: 2,
+            samples: 16,
+        }
+    }
+
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PrintExport {
+    pub path: PathBuf,
+    /// Size of the image in pixels, unlimited by the device.
+    pub width: u32,
+    pub height: u32,
+    /// Jittered samples to average per pixel.
+    pub samples: u32,
+}
+
+impl PrintExport {
+    /// With 4 samples.
+    pub fn new(path: impl Into<PathBuf>, width: u32, height: u32) -> Self {
+        Self {
+            path: path.into(),
+            width,
+            height,
+            samples: 4,
+        }
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+}
+
+/// A part of an image, in pixels from its top left.
+pub(crate) struct Tile {
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// See `Globals::tile`.
+    pub(crate) transform: [f32; 4],
+}
+
+impl Tile {
+    pub(crate) fn whole(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            transform: gpu::IDENTITY_TILE,
+        }
+    }
+}
+
+/// Cut a `width` x `height` image into tiles no larger than `max_size`,
+/// row by row from the top left.
+pub(crate) fn tiles(width: u32, height: u32, max_size: u32) -> impl Iterator<Item = Tile> {
+    let max_size = max_size.max(1);
+    (0..height).step_by(max_size as usize).flat_map(move |y| {
+        (0..width).step_by(max_size as usize).map(move |x| {
+            let tile_width = max_size.min(width - x);
+            let tile_height = max_size.min(height - y);
+
+            // The tile's edges in the whole image's clip space, y up.
+            let left = x as f32 / width as f32 * 2.0 - 1.0;
+            let right = (x + tile_width) as f32 / width as f32 * 2.0 - 1.0;
+            let top = 1.0 - y as f32 / height as f32 * 2.0;
+            let bottom = 1.0 - (y + tile_height) as f32 / height as f32 * 2.0;
+
+            let scale_x = 2.0 / (right - left);
+            let scale_y = 2.0 / (top - bottom);
+            Tile {
+                x,
+                y,
+                width: tile_width,
+                height: tile_height,
+                transform: [
+                    scale_x,
+                    scale_y,
+                    -(left + right) / 2.0 * scale_x,
+                    -(top + bottom) / 2.0 * scale_y,
+                ],
+            }
+        })
+    })
+}
+
+/// The offset of sample `index` in a pixel, both from -0.5 to 0.5, from
+/// the Halton sequence in bases 2 and 3 so any number of samples covers
+/// the pixel evenly.
+pub(crate) fn jitter(index: u32) -> [f32; 2] {
+    let halton = |base: u32| {
+        let mut index = index + 1;
+        let mut fraction = 1.0;
+        let mut result = 0.0;
+        while index > 0 {
+            fraction /= base as f32;
+            result += fraction * (index % base) as f32;
+            index /= base;
+        }
+        result - 0.5
+    };
+    [halton(2), halton(3)]
+}
+
+/// A copy of the presented still, or tile, on its way back to the CPU.
+pub(crate) struct StillReadback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_row_size: u32,
+}
+
+impl StillReadback {
+    /// Bytes per texel of `OUTPUT_FORMAT`.
+    const TEXEL_SIZE: u32 = 4;
+
+    /// Record a copy of `output`, a `width` x `height` texture in
+    /// `OUTPUT_FORMAT`, into a new buffer.
+    pub(crate) fn encode(
+        gpu: &Gpu,
+        encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::Texture,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row_size = (width * Self::TEXEL_SIZE + alignment - 1) / alignment * alignment;
+        let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Still Readback Buffer"),
+            size: (padded_row_size * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: output,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: compat::image_layout(padded_row_size, height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_row_size,
+        }
+    }
+
+    /// Wait for the copy and return its pixels. Failing leaves the device
+    /// in the same state as a failed frame readback.
+    pub(crate) fn read(self, gpu: &Gpu, timeout: Duration) -> Result<RgbaImage, ReadbackError> {
+        gpu::map_read(&gpu.device, &self.buffer, timeout)?;
+
+        let row_size = (self.width * Self::TEXEL_SIZE) as usize;
+        let mut pixels = Vec::with_capacity(row_size * self.height as usize);
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(self.padded_row_size as usize) {
+                pixels.extend_from_slice(&row[..row_size]);
+            }
+        }
+        self.buffer.unmap();
+
+        // Every row was copied above, so the size always matches.
+        Ok(RgbaImage::from_raw(self.width, self.height, pixels).expect("still readback size"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_tile_is_the_whole_image() {
+        let tiles: Vec<_> = tiles(300, 200, 4096).collect();
+        assert_eq!(tiles.len(), 1);
+        let tile = &tiles[0];
+        assert_eq!((tile.x, tile.y, tile.width, tile.height), (0, 0, 300, 200));
+        assert_eq!(tile.transform, gpu::IDENTITY_TILE);
+    }
+
+    #[test]
+    fn tiles_cover_the_image_once() {
+        let (width, height) = (10, 7);
+        let mut covered = vec![0; (width * height) as usize];
+        for tile in tiles(width, height, 4) {
+            assert!(tile.width <= 4 && tile.height <= 4);
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    covered[(y * width + x) as usize] += 1;
+                }
+            }
+        }
+        assert!(covered.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn tile_transforms_fill_clip_space() {
+        let (width, height) = (10.0, 7.0);
+        for tile in tiles(10, 7, 4) {
+            let [scale_x, scale_y, offset_x, offset_y] = tile.transform;
+            // The tile's edges in the whole image's clip space, y up.
+            let left = tile.x as f32 / width * 2.0 - 1.0;
+            let right = (tile.x + tile.width) as f32 / width * 2.0 - 1.0;
+            let top = 1.0 - tile.y as f32 / height * 2.0;
+            let bottom = 1.0 - (tile.y + tile.height) as f32 / height * 2.0;
+
+            let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+            assert!(close(left * scale_x + offset_x, -1.0));
+            assert!(close(right * scale_x + offset_x, 1.0));
+            assert!(close(top * scale_y + offset_y, 1.0));
+            assert!(close(bottom * scale_y + offset_y, -1.0));
+        }
+    }
+
+    #[test]
+    fn jitter_stays_in_the_pixel() {
+        assert_eq!(jitter(0), [0.0, 1.0 / 3.0 - 0.5]);
+
+        let samples: Vec<_> = (0..64).map(jitter).collect();
+        for (i, sample) in samples.iter().enumerate() {
+            assert!(sample.iter().all(|offset| (-0.5..0.5).contains(offset)));
+            assert!(!samples[..i].contains(sample), "sample {} repeats", i);
+        }
+        // Evenly spread, so the average stays near the pixel's center.
+        for axis in 0..2 {
+            let mean = samples.iter().map(|sample| sample[axis]).sum::<f32>() / 64.0;
+            assert!(mean.abs() < 0.05, "axis {}: {}", axis, mean);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming the viewport over the network (`stream` feature).
+//!
+//! A [`FrameServer`] serves the latest frame as an MJPEG stream over plain
+//! HTTP, which browsers show in an `<img>` tag or on their own, so a
+//! rendering machine can be watched remotely. Give one to
+//! `WgpuWidget::with_frame_sink` to publish every new frame.
+//!
+//! Publishing only keeps a reference to the frame. Encoding happens on a
+//! background thread, and only while someone is watching; the newest frame
+//! is kept, so a client connecting to a scene that stopped changing still
+//! gets a picture.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use druid::ImageBuf;
+use image::codecs::jpeg::JpegEncoder;
+use image::ColorType;
+
+use crate::sink::{FrameRef, FrameSink};
+
+/// How often the server threads check whether they should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const BOUNDARY: &str = "druid-wgpu-frame";
+
+/// The latest encoded frame and how many have been published.
+#[derive(Default)]
+struct Latest {
+    jpeg: Option<Arc<Vec<u8>>>,
+    sequence: u64,
+}
+
+/// The newest published frame, waiting to be encoded.
+#[derive(Default)]
+struct Pending {
+    frame: Option<ImageBuf>,
+    sequence: u64,
+}
+
+struct Shared {
+    running: AtomicBool,
+    clients: AtomicUsize,
+    latest: Mutex<Latest>,
+    published: Condvar,
+    pending: Mutex<Pending>,
+    /// Wakes the encoder for a new frame or a new client.
+    wake: Condvar,
+}
+
+impl Shared {
+    fn wake_encoder(&self) {
+        // Taking the lock orders this with the encoder's check.
+        let _pending = self.pending.lock().unwrap();
+        self.wake.notify_all();
+    }
+}
+
+/// Serves published frames to every client as `multipart/x-mixed-replace`
+/// JPEGs. The server stops when this is dropped.
+pub struct FrameServer {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+    /// Started by the first frame, once the settings are final.
+    encoder: Option<JoinHandle<()>>,
+    quality: u8,
+    min_interval: Duration,
+}
+
+impl FrameServer {
+    pub fn listen(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        let shared = Arc::new(Shared {
+            running: AtomicBool::new(true),
+            clients: AtomicUsize::new(0),
+            latest: Mutex::new(Latest::default()),
+            published: Condvar::new(),
+            pending: Mutex::new(Pending::default()),
+            wake: Condvar::new(),
+        });
+        let thread = {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                while shared.running.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            let shared = shared.clone();
+                            std::thread::spawn(move || serve(stream, &shared));
+                        }
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                            std::thread::sleep(POLL_INTERVAL);
+                        }
+                        Err(err) => {
+                            eprintln!("Frame server stopped: {}", err);
+                            break;
+                        }
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            shared,
+            thread: Some(thread),
+            encoder: None,
+            quality: 80,
+            min_interval: Duration::ZERO,
+        })
+    }
+
+    /// JPEG quality from 1 to 100, 80 by default.
+    pub fn with_quality(mut self, quality: u8) -> Self {
+        self.quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Publish at most `fps` frames a second, dropping the rest.
+    pub fn with_max_fps(mut self, fps: f64) -> Self {
+        self.min_interval = Duration::from_secs_f64(1.0 / fps.max(0.001));
+        self
+    }
+
+    /// Send `frame` to every connected client, and to clients that connect
+    /// before the next one. This only keeps a reference to the pixels.
+    pub fn publish(&mut self, frame: &ImageBuf) {
+        if self.encoder.is_none() {
+            let shared = self.shared.clone();
+            let (quality, min_interval) = (self.quality, self.min_interval);
+            self.encoder = Some(std::thread::spawn(move || {
+                encode_frames(&shared, quality, min_interval)
+            }));
+        }
+
+        let mut pending = self.shared.pending.lock().unwrap();
+        pending.frame = Some(frame.clone());
+        pending.sequence += 1;
+        self.shared.wake.notify_all();
+    }
+}
+
+impl FrameSink for FrameServer {
+    fn submit(&mut self, frame: FrameRef) {
+        self.publish(frame.image);
+    }
+}
+
+impl Drop for FrameServer {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::Relaxed);
+        self.shared.published.notify_all();
+        self.shared.wake_encoder();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Some(encoder) = self.encoder.take() {
+            let _ = encoder.join();
+        }
+    }
+}
+
+/// Encode the newest pending frame whenever it changed and someone is
+/// watching, at most once every `min_interval`. Frames published while
+/// encoding or waiting are skipped in favour of the newest.
+fn encode_frames(shared: &Shared, quality: u8, min_interval: Duration) {
+    let mut encoded = 0;
+    let mut last_encode: Option<Instant> = None;
+    while shared.running.load(Ordering::Relaxed) {
+        if let Some(last) = last_encode {
+            let since = last.elapsed();
+            if since < min_interval {
+                std::thread::sleep((min_interval - since).min(POLL_INTERVAL));
+                continue;
+            }
+        }
+
+        let (frame, sequence) = {
+            let pending = shared.pending.lock().unwrap();
+            let (pending, _) = shared
+                .wake
+                .wait_timeout_while(pending, POLL_INTERVAL, |pending| {
+                    pending.sequence == encoded || shared.clients.load(Ordering::Relaxed) == 0
+                })
+                .unwrap();
+            if pending.sequence == encoded || shared.clients.load(Ordering::Relaxed) == 0 {
+                continue;
+            }
+            match &pending.frame {
+                Some(frame) => (frame.clone(), pending.sequence),
+                None => continue,
+            }
+        };
+        encoded = sequence;
+        last_encode = Some(Instant::now());
+
+        match encode_jpeg(&frame, quality) {
+            Ok(jpeg) => {
+                let mut latest = shared.latest.lock().unwrap();
+                latest.jpeg = Some(Arc::new(jpeg));
+                latest.sequence += 1;
+                shared.published.notify_all();
+            }
+            Err(err) => eprintln!("Failed to encode a streamed frame: {}", err),
+        }
+    }
+}
+
+fn encode_jpeg(frame: &ImageBuf, quality: u8) -> image::ImageResult<Vec<u8>> {
+    // JPEG has no alpha.
+    let rgb: Vec<u8> = frame
+        .raw_pixels()
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, quality).encode(
+        &rgb,
+        frame.width() as u32,
+        frame.height() as u32,
+        ColorType::Rgb8,
+    )?;
+    Ok(jpeg)
+}
+
+/// Stream frames to one client until it disconnects or the server stops.
+fn serve(mut stream: TcpStream, shared: &Shared) {
+    shared.clients.fetch_add(1, Ordering::Relaxed);
+    // Frames published while nobody watched haven't been encoded yet.
+    shared.wake_encoder();
+    if let Err(err) = stream_frames(&mut stream, shared) {
+        // Clients going away mid-frame is the normal way for this to end.
+        if err.kind() != io::ErrorKind::BrokenPipe && err.kind() != io::ErrorKind::ConnectionReset {
+            eprintln!("Frame stream ended: {}", err);
+        }
+    }
+    shared.clients.fetch_sub(1, Ordering::Relaxed);
+}
+
+fn stream_frames(stream: &mut TcpStream, shared: &Shared) -> io::Result<()> {
+    // Whatever was asked for, the answer is the stream.
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    let mut request = [0u8; 1024];
+    let _ = stream.read(&mut request);
+
+    write!(
+        stream,
+        "HTTP/1.0 200 OK\r\n\
+         Cache-Control: no-cache\r\n\
+         Content-Type: multipart/x-mixed-replace; boundary={}\r\n\r\n",
+        BOUNDARY
+    )?;
+
+    let mut sent = 0;
+    while shared.running.load(Ordering::Relaxed) {
+        let jpeg = {
+            let latest = shared.latest.lock().unwrap();
+            let (latest, _) = shared
+                .published
+                .wait_timeout_while(latest, POLL_INTERVAL, |latest| latest.sequence == sent)
+                .unwrap();
+            if latest.sequence == sent {
+                continue;
+            }
+            sent = latest.sequence;
+            latest.jpeg.clone()
+        };
+
+        if let Some(jpeg) = jpeg {
+            write!(
+                stream,
+                "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+                BOUNDARY,
+                jpeg.len()
+            )?;
+            stream.write_all(&jpeg)?;
+            stream.write_all(b"\r\n")?;
+            stream.flush()?;
+        }
+    }
+    Ok(())
+}
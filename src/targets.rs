@@ -0,0 +1,124 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The textures a frame is rendered into, kept from one paint to the next.
+
+use crate::gpu::{OUTPUT_FORMAT, SCENE_FORMAT};
+use crate::mask::STENCIL_FORMAT;
+
+/// The scene, its stencil when masked, and the presented output, all the
+/// same size.
+pub(crate) struct RenderTargets {
+    pub(crate) size: wgpu::Extent3d,
+    pub(crate) scene: wgpu::Texture,
+    pub(crate) scene_view: wgpu::TextureView,
+    pub(crate) stencil_view: Option<wgpu::TextureView>,
+    pub(crate) output: wgpu::Texture,
+    pub(crate) output_view: wgpu::TextureView,
+}
+
+impl RenderTargets {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        label: &str,
+        size: wgpu::Extent3d,
+        stencil: bool,
+    ) -> Self {
+        let create = |suffix: &str, format, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(&format!("{} {} Texture", label, suffix)),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+            })
+        };
+
+        let scene = create(
+            "Scene",
+            SCENE_FORMAT,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let stencil_view = stencil.then(|| {
+            create(
+                "Stencil",
+                STENCIL_FORMAT,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+            .create_view(&Default::default())
+        });
+        let output = create(
+            "Output",
+            OUTPUT_FORMAT,
+            wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+
+        Self {
+            size,
+            scene_view: scene.create_view(&Default::default()),
+            scene,
+            stencil_view,
+            output_view: output.create_view(&Default::default()),
+            output,
+        }
+    }
+
+    /// Whether these can be rendered into again for a frame of `size`.
+    pub(crate) fn matches(&self, size: wgpu::Extent3d, stencil: bool) -> bool {
+        self.size == size && self.stencil_view.is_some() == stencil
+    }
+}
+
+/// The two textures post effects ping-pong between, kept like
+/// [`RenderTargets`] and only created once an effect runs.
+pub(crate) struct PostTargets {
+    textures: Option<([wgpu::Texture; 2], wgpu::Extent3d)>,
+}
+
+impl PostTargets {
+    pub(crate) fn new() -> Self {
+        Self { textures: None }
+    }
+
+    /// The pair for a frame of `size`, recreated if it was another size.
+    pub(crate) fn get(
+        &mut self,
+        device: &wgpu::Device,
+        size: wgpu::Extent3d,
+    ) -> &[wgpu::Texture; 2] {
+        if !matches!(&self.textures, Some((_, targets_size)) if *targets_size == size) {
+            let create = || {
+                device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Post Target"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: SCENE_FORMAT,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                })
+            };
+            self.textures = Some(([create(), create()], size));
+        }
+        &self.textures.as_ref().unwrap().0
+    }
+
+    /// Forget the textures, when the device they were made on is gone.
+    pub(crate) fn clear(&mut self) {
+        self.textures = None;
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Env keys for the viewport's built-in visuals.
+//!
+//! The widget reads these with a fallback, so they only need to be set to
+//! override the defaults; [`configure_light`] and [`configure_dark`] set them
+//! all at once from `AppLauncher::configure_env`.
+
+use druid::{Color, Env, Key};
+
+/// The color the viewport is cleared to before the scene is drawn.
+pub const VIEWPORT_BACKGROUND: Key<Color> = Key::new("druid-wgpu.viewport-background");
+
+/// Render the scene in stark, flat colors on a black background.
+pub const HIGH_CONTRAST: Key<bool> = Key::new("druid-wgpu.high-contrast");
+
+/// The background of the pixel rulers, see `WgpuWidget::with_rulers`.
+pub const RULER_BACKGROUND: Key<Color> = Key::new("druid-wgpu.ruler-background");
+
+/// The rulers' ticks and labels.
+pub const RULER_TICKS: Key<Color> = Key::new("druid-wgpu.ruler-ticks");
+
+/// The guides dragged out of the rulers.
+pub const GUIDE_COLOR: Key<Color> = Key::new("druid-wgpu.guide-color");
+
+pub(crate) const HIGH_CONTRAST_BACKGROUND: Color = Color::BLACK;
+pub(crate) const DARK_BACKGROUND: Color = Color::rgb8(0x59, 0x7c, 0x95);
+const LIGHT_BACKGROUND: Color = Color::rgb8(0xe4, 0xea, 0xf0);
+pub(crate) const DARK_RULER_BACKGROUND: Color = Color::rgba8(0x20, 0x20, 0x20, 0xd0);
+pub(crate) const DARK_RULER_TICKS: Color = Color::rgb8(0xc0, 0xc0, 0xc0);
+pub(crate) const DARK_GUIDE_COLOR: Color = Color::rgb8(0x00, 0xc8, 0xff);
+const LIGHT_RULER_BACKGROUND: Color = Color::rgba8(0xf4, 0xf4, 0xf4, 0xd0);
+const LIGHT_RULER_TICKS: Color = Color::rgb8(0x40, 0x40, 0x40);
+const LIGHT_GUIDE_COLOR: Color = Color::rgb8(0x00, 0x78, 0xd4);
+
+pub fn configure_dark(env: &mut Env) {
+    env.set(VIEWPORT_BACKGROUND, DARK_BACKGROUND);
+    env.set(RULER_BACKGROUND, DARK_RULER_BACKGROUND);
+    env.set(RULER_TICKS, DARK_RULER_TICKS);
+    env.set(GUIDE_COLOR, DARK_GUIDE_COLOR);
+}
+
+pub fn configure_light(env: &mut Env) {
+    env.set(VIEWPORT_BACKGROUND, LIGHT_BACKGROUND);
+    env.set(RULER_BACKGROUND, LIGHT_RULER_BACKGROUND);
+    env.set(RULER_TICKS, LIGHT_RULER_TICKS);
+    env.set(GUIDE_COLOR, LIGHT_GUIDE_COLOR);
+}
+
+/// The sRGB `color` as a linear `wgpu::Color`, for clearing sRGB targets.
+pub(crate) fn to_linear(color: &Color) -> wgpu::Color {
+    fn linear(c: f64) -> f64 {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    let (r, g, b, a) = color.as_rgba();
+    wgpu::Color {
+        r: linear(r),
+        g: linear(g),
+        b: linear(b),
+        a,
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fixed-timestep simulation with render-time interpolation.
+//!
+//! Druid repaints whenever it likes, so simulations stepped once per paint
+//! run at whatever rate the app happens to be animating. Stepping a
+//! [`FixedTimestep`] instead keeps the simulation rate constant, and
+//! [`Interpolated`] values blend the last two steps so motion still looks
+//! smooth between them.
+
+/// The most steps taken for a single frame, so a long stall doesn't make
+/// the simulation spend the next frames catching up.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Accumulates frame time and hands it out in fixed-size steps.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    dt: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    /// A timestep running `hz` steps per second.
+    pub fn from_hz(hz: f64) -> Self {
+        Self {
+            dt: 1.0 / hz,
+            accumulator: 0.0,
+        }
+    }
+
+    /// The length of one step in seconds.
+    pub fn dt(&self) -> f64 {
+        self.dt
+    }
+
+    /// Feed `elapsed` seconds of frame time, calling `step` with the step
+    /// length once per fixed step that fits.
+    pub fn advance(&mut self, elapsed: f64, mut step: impl FnMut(f64)) {
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= self.dt {
+            if steps == MAX_STEPS_PER_FRAME {
+                // Drop the backlog rather than falling further behind.
+                self.accumulator = 0.0;
+                break;
+            }
+
+            step(self.dt);
+            self.accumulator -= self.dt;
+            steps += 1;
+        }
+    }
+
+    /// How far the render time is between the last step and the next one,
+    /// in `[0, 1)`.
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.dt
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+/// Linear interpolation between two values.
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f64) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t as f32
+    }
+}
+
+impl Lerp for f64 {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl<const N: usize> Lerp for [f32; N] {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        let mut out = self;
+        for (value, other) in out.iter_mut().zip(other) {
+            *value = value.lerp(other, t);
+        }
+        out
+    }
+}
+
+/// A simulated value that remembers its previous step.
+#[derive(Clone, Copy, Debug)]
+pub struct Interpolated<T> {
+    previous: T,
+    current: T,
+}
+
+impl<T: Lerp> Interpolated<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            previous: value,
+            current: value,
+        }
+    }
+
+    /// Record the value produced by a new step.
+    pub fn push(&mut self, value: T) {
+        self.previous = self.current;
+        self.current = value;
+    }
+
+    /// Replace the value without interpolating from the old one.
+    pub fn snap(&mut self, value: T) {
+        self.previous = value;
+        self.current = value;
+    }
+
+    pub fn current(&self) -> T {
+        self.current
+    }
+
+    /// The value at `alpha` between the previous and the current step.
+    pub fn get(&self, alpha: f64) -> T {
+        self.previous.lerp(self.current, alpha)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Steps of a quarter second, so the sums below are exact.
+    fn timestep() -> FixedTimestep {
+        FixedTimestep::from_hz(4.0)
+    }
+
+    #[test]
+    fn steps_whole_dts_and_keeps_the_rest() {
+        let mut timestep = timestep();
+        let mut steps = Vec::new();
+        timestep.advance(0.625, |dt| steps.push(dt));
+        assert_eq!(steps, [0.25, 0.25]);
+        assert_eq!(timestep.alpha(), 0.5);
+    }
+
+    #[test]
+    fn accumulates_short_frames() {
+        let mut timestep = timestep();
+        let mut steps = 0;
+        timestep.advance(0.125, |_| steps += 1);
+        assert_eq!(steps, 0);
+        timestep.advance(0.125, |_| steps += 1);
+        assert_eq!(steps, 1);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn drops_the_backlog_after_a_stall() {
+        let mut timestep = timestep();
+        let mut steps = 0;
+        timestep.advance(100.0, |_| steps += 1);
+        assert_eq!(steps, MAX_STEPS_PER_FRAME);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn reset_forgets_partial_steps() {
+        let mut timestep = timestep();
+        timestep.advance(0.125, |_| ());
+        timestep.reset();
+        let mut steps = 0;
+        timestep.advance(0.125, |_| steps += 1);
+        assert_eq!(steps, 0);
+    }
+
+    #[test]
+    fn interpolates_between_steps() {
+        let mut value = Interpolated::new(0.0_f64);
+        value.push(1.0);
+        assert_eq!(value.get(0.25), 0.25);
+        assert_eq!(value.current(), 1.0);
+        value.snap(4.0);
+        assert_eq!(value.get(0.5), 4.0);
+        assert_eq!([0.0_f32, 2.0].lerp([1.0, 4.0], 0.5), [0.5, 3.0]);
+    }
+}
@@ -0,0 +1,399 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Shader variants chosen by preprocessor defines.
+//!
+//! Rather than one shader branching on every option at run time, a source
+//! can wrap the optional parts in `#ifdef NAME`, `#ifndef NAME`, `#else`
+//! and `#endif` lines, and each set of [`Defines`] compiles to a module of
+//! its own:
+//!
+//! ```wgsl
+//! #ifdef HAS_NORMAL_MAP
+//!     let normal = textureSample(normal_map, normal_sampler, in.uv).xyz;
+//! #else
+//!     let normal = in.normal;
+//! #endif
+//! ```
+//!
+//! [`ShaderVariants`] compiles a variant the first time it's asked for and
+//! keeps it, so switching back and forth costs nothing after the first time.
+//! Drivers do most of their compiling when a pipeline is created though, so
+//! [`PipelineVariants`] keeps a pipeline per variant too, and can
+//! [`warm_up`](PipelineVariants::warm_up) the ones a scene is expected to
+//! need during a loading phase, instead of hitching when they first appear.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// The names defined for a variant.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Defines(BTreeSet<String>);
+
+impl Defines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>) -> Self {
+        self.0.insert(name.into());
+        self
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>) {
+        self.0.insert(name.into());
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for Defines {
+    fn from_iter<I: IntoIterator<Item = S>>(names: I) -> Self {
+        Self(names.into_iter().map(Into::into).collect())
+    }
+}
+
+impl fmt::Display for Defines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.iter().collect();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+/// Why a source couldn't be preprocessed.
+#[derive(Clone, Debug)]
+pub struct PreprocessError {
+    /// 1-based line of the problem.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "shader line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// Keep the lines of `source` that `defines` select, see the module docs.
+///
+/// Dropped lines and directives are left empty, so line numbers in shader
+/// compilation errors still match `source`.
+pub fn preprocess(source: &str, defines: &Defines) -> Result<String, PreprocessError> {
+    /// One open `#ifdef` or `#ifndef`.
+    struct Block {
+        line: usize,
+        /// Whether the enclosing block is kept.
+        outer: bool,
+        condition: bool,
+        in_else: bool,
+    }
+
+    let mut out = String::with_capacity(source.len());
+    let mut blocks: Vec<Block> = Vec::new();
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| PreprocessError { line, message };
+        let keeping = blocks.last().map_or(true, |block| {
+            block.outer && block.condition != block.in_else
+        });
+
+        let mut words = text.split_whitespace();
+        match words.next() {
+            Some(directive @ ("#ifdef" | "#ifndef")) => {
+                let name = words
+                    .next()
+                    .ok_or_else(|| error(format!("{} needs a name", directive)))?;
+                blocks.push(Block {
+                    line,
+                    outer: keeping,
+                    condition: defines.contains(name) == (directive == "#ifdef"),
+                    in_else: false,
+                });
+            }
+            Some("#else") => match blocks.last_mut() {
+                Some(block) if !block.in_else => block.in_else = true,
+                Some(_) => return Err(error("second #else in a block".to_string())),
+                None => return Err(error("#else without #ifdef".to_string())),
+            },
+            Some("#endif") => {
+                blocks
+                    .pop()
+                    .ok_or_else(|| error("#endif without #ifdef".to_string()))?;
+            }
+            Some(directive) if directive.starts_with('#') => {
+                return Err(error(format!("unknown directive {}", directive)));
+            }
+            _ if keeping => out.push_str(text),
+            _ => (),
+        }
+        out.push('\n');
+    }
+
+    match blocks.last() {
+        Some(block) => Err(PreprocessError {
+            line: block.line,
+            message: "#ifdef without #endif".to_string(),
+        }),
+        None => Ok(out),
+    }
+}
+
+/// The compiled variants of one shader source.
+///
+/// Modules belong to the device they were compiled on: after the device is
+/// lost, [`clear`](Self::clear) the variants or make new ones.
+pub struct ShaderVariants {
+    label: String,
+    source: String,
+    modules: HashMap<Defines, wgpu::ShaderModule>,
+}
+
+impl ShaderVariants {
+    pub fn new(label: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            source: source.into(),
+            modules: HashMap::new(),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The variant for `defines`, compiled now if it hasn't been yet.
+    pub fn get(
+        &mut self,
+        device: &wgpu::Device,
+        defines: &Defines,
+    ) -> Result<&wgpu::ShaderModule, PreprocessError> {
+        if !self.modules.contains_key(defines) {
+            let source = preprocess(&self.source, defines)?;
+            let label = if defines.is_empty() {
+                self.label.clone()
+            } else {
+                format!("{} [{}]", self.label, defines)
+            };
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(&label),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+            self.modules.insert(defines.clone(), module);
+        }
+        Ok(&self.modules[defines])
+    }
+
+    /// Whether the variant for `defines` is already compiled.
+    pub fn is_compiled(&self, defines: &Defines) -> bool {
+        self.modules.contains_key(defines)
+    }
+
+    /// How many variants are compiled.
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Drop every compiled variant, for example after the device was lost.
+    pub fn clear(&mut self) {
+        self.modules.clear();
+    }
+}
+
+impl fmt::Debug for ShaderVariants {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShaderVariants")
+            .field("label", &self.label)
+            .field("compiled", &self.modules.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// How far [`PipelineVariants::warm_up`] has got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WarmUpProgress {
+    pub compiled: usize,
+    pub total: usize,
+}
+
+impl WarmUpProgress {
+    /// From 0 to 1, for a progress bar.
+    pub fn fraction(&self) -> f64 {
+        match self.total {
+            0 => 1.0,
+            total => self.compiled as f64 / total as f64,
+        }
+    }
+}
+
+type BuildPipeline = Box<dyn Fn(&wgpu::Device, &wgpu::ShaderModule) -> wgpu::RenderPipeline + Send>;
+
+/// A render pipeline for each variant of a shader.
+///
+/// `build` makes the pipeline from a variant's module, and is the same for
+/// all of them. Devices are `Send`, so warming up can happen on a thread of
+/// its own, reporting progress to the UI with an `ExtEventSink`.
+pub struct PipelineVariants {
+    shaders: ShaderVariants,
+    build: BuildPipeline,
+    pipelines: HashMap<Defines, wgpu::RenderPipeline>,
+}
+
+impl PipelineVariants {
+    pub fn new(
+        shaders: ShaderVariants,
+        build: impl Fn(&wgpu::Device, &wgpu::ShaderModule) -> wgpu::RenderPipeline + Send + 'static,
+    ) -> Self {
+        Self {
+            shaders,
+            build: Box::new(build),
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// The pipeline for `defines`, created now if it hasn't been yet.
+    pub fn get(
+        &mut self,
+        device: &wgpu::Device,
+        defines: &Defines,
+    ) -> Result<&wgpu::RenderPipeline, PreprocessError> {
+        if !self.pipelines.contains_key(defines) {
+            let module = self.shaders.get(device, defines)?;
+            let pipeline = (self.build)(device, module);
+            self.pipelines.insert(defines.clone(), pipeline);
+        }
+        Ok(&self.pipelines[defines])
+    }
+
+    /// Create the pipelines for every set of `defines` now, calling
+    /// `progress` after each. Stops at the first source that can't be
+    /// preprocessed.
+    pub fn warm_up(
+        &mut self,
+        device: &wgpu::Device,
+        defines: impl IntoIterator<Item = Defines>,
+        mut progress: impl FnMut(WarmUpProgress),
+    ) -> Result<(), PreprocessError> {
+        let defines: Vec<_> = defines.into_iter().collect();
+        let total = defines.len();
+        for (index, defines) in defines.iter().enumerate() {
+            self.get(device, defines)?;
+            progress(WarmUpProgress {
+                compiled: index + 1,
+                total,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn is_compiled(&self, defines: &Defines) -> bool {
+        self.pipelines.contains_key(defines)
+    }
+
+    /// Drop every pipeline and module, for example after the device was
+    /// lost.
+    pub fn clear(&mut self) {
+        self.pipelines.clear();
+        self.shaders.clear();
+    }
+}
+
+impl fmt::Debug for PipelineVariants {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipelineVariants")
+            .field("shaders", &self.shaders)
+            .field("pipelines", &self.pipelines.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The non-empty lines `source` keeps with `names` defined.
+    fn kept(source: &str, names: &[&str]) -> Vec<String> {
+        let defines: Defines = names.iter().copied().collect();
+        preprocess(source, &defines)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    const BRANCHES: &str = "a\n#ifdef X\nb\n#else\nc\n#endif\nd";
+
+    #[test]
+    fn picks_a_branch() {
+        assert_eq!(kept(BRANCHES, &["X"]), ["a", "b", "d"]);
+        assert_eq!(kept(BRANCHES, &[]), ["a", "c", "d"]);
+        assert_eq!(kept("#ifndef X\nb\n#endif", &[]), ["b"]);
+        assert!(kept("#ifndef X\nb\n#endif", &["X"]).is_empty());
+    }
+
+    #[test]
+    fn keeps_line_numbers() {
+        let out = preprocess(BRANCHES, &Defines::new().with("X")).unwrap();
+        assert_eq!(out, "a\n\nb\n\n\n\nd\n");
+    }
+
+    #[test]
+    fn nests() {
+        let source = "#ifdef A\n#ifdef B\nab\n#else\na\n#endif\n#else\nnone\n#endif";
+        assert_eq!(kept(source, &["A", "B"]), ["ab"]);
+        assert_eq!(kept(source, &["A"]), ["a"]);
+        // An inner block never shows through an outer one that's dropped.
+        assert_eq!(kept(source, &["B"]), ["none"]);
+        assert_eq!(kept(source, &[]), ["none"]);
+    }
+
+    #[test]
+    fn rejects_unknown_directives() {
+        let err = preprocess("a\n#define X\n", &Defines::new()).unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(err.message.contains("#define"), "{}", err.message);
+    }
+
+    #[test]
+    fn rejects_unterminated_blocks() {
+        let err = preprocess("a\n#ifdef X\n#ifdef Y\n#endif\nb", &Defines::new()).unwrap_err();
+        assert_eq!(err.line, 2);
+        assert_eq!(err.message, "#ifdef without #endif");
+    }
+
+    #[test]
+    fn rejects_unbalanced_directives() {
+        let line = |source| preprocess(source, &Defines::new()).unwrap_err().line;
+        assert_eq!(line("a\n#endif"), 2);
+        assert_eq!(line("#else"), 1);
+        assert_eq!(line("#ifdef X\n#else\n#else\n#endif"), 3);
+        assert_eq!(line("#ifdef\n#endif"), 1);
+    }
+}
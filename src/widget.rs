@@ -0,0 +1,1791 @@
+// Copyright 2019 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The wgpu viewport widget.
+
+use std::any::Any;
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use druid::piet::ImageFormat;
+use druid::piet::InterpolationMode;
+use druid::piet::PietImage;
+use druid::piet::{FontFamily, Text, TextLayoutBuilder};
+use druid::widget::prelude::*;
+use druid::widget::FillStrat;
+use druid::{Affine, Color, Data, ExtEventSink, ImageBuf, LocalizedString, Selector, Target};
+use image::{imageops, RgbaImage};
+
+use crate::accumulate::Accumulator;
+#[cfg(feature = "audio")]
+use crate::audio::AudioInput;
+#[cfg(feature = "http")]
+use crate::bridge::HttpBridge;
+use crate::bridge::{PLAYBACK_TIME, SET_PARAMETER};
+use crate::color::ColorProfile;
+use crate::compat;
+use crate::errors::{GpuError, GpuErrorKind, GPU_ERROR};
+use crate::gpu::{self, Globals, Gpu, ParamUniforms, ReadbackError, IDENTITY_TILE};
+use crate::hdr::HdrReadback;
+use crate::interaction::{DragPolicy, INTERACTING};
+use crate::keymap::{Keymap, ViewportAction, VIEWPORT_ACTION};
+use crate::latency::{CursorPredictor, LatencyMeter, INPUT_LATENCY};
+use crate::mask::{Mask, MaskPass};
+use crate::post::{PostChain, PostEffect, SET_LUT, TOGGLE_EFFECT};
+use crate::recording::{Recording, EXPORT_GIF};
+use crate::resources::Resources;
+use crate::rulers::Rulers;
+use crate::scene::{CustomScene, EncodeContext, SceneContext, SceneFrame, WgpuScene};
+use crate::settings::{Persistence, SAVE_DELAY};
+use crate::sink::{FrameRef, FrameSink, SinkSlot};
+use crate::still::{
+    self, PrintExport, StillExport, StillReadback, Tile, EXPORT_PRINT, EXPORT_STILL,
+};
+use crate::targets::{PostTargets, RenderTargets};
+use crate::theme;
+use crate::timestep::FixedTimestep;
+use crate::{GpuCapabilities, GpuOptions, ViewportState};
+
+/// Rate of `WgpuScene::on_update` steps, independent of the repaint rate.
+const SIMULATION_HZ: f64 = 60.0;
+
+/// How far ahead to predict the cursor before latency has been measured.
+const DEFAULT_LATENCY: Duration = Duration::from_millis(16);
+
+/// Notification sent with each newly rendered frame.
+pub const FRAME_AVAILABLE: Selector<ImageBuf> = Selector::new("druid-wgpu.frame-available");
+
+/// Sent with global target, with the adapter's name, when the widget is
+/// added and the GPU turns out to be a software rasterizer, so apps can
+/// warn that rendering will be slow.
+pub const SOFTWARE_RENDERER: Selector<String> = Selector::new("druid-wgpu.software-renderer");
+
+/// Sent to ourselves from `paint`, which can't submit notifications.
+const FRAME_RENDERED: Selector = Selector::new("druid-wgpu.frame-rendered");
+
+/// Sent to ourselves from `paint` to retry a frame lost with the device.
+const REPAINT: Selector = Selector::new("druid-wgpu.repaint");
+
+/// Sent to the widget itself once it's added, to apply persisted settings.
+const RESTORE_SETTINGS: Selector = Selector::new("druid-wgpu.restore-settings");
+
+/// Sent to the widget itself once it's added, to fill in `ViewportState::gpu`.
+const PUBLISH_CAPABILITIES: Selector = Selector::new("druid-wgpu.publish-capabilities");
+
+/// Sent to ourselves from `paint` once a recorded frame is captured.
+const RECORD_NEXT: Selector = Selector::new("druid-wgpu.record-next");
+
+/// GPU resources whose contents are out of date with the app data.
+struct Dirty {
+    globals: bool,
+    params: bool,
+    color_profile: bool,
+}
+
+impl Dirty {
+    fn all() -> Self {
+        Self {
+            globals: true,
+            params: true,
+            color_profile: true,
+        }
+    }
+}
+
+/// The parts of the theme that reach the GPU.
+struct Look {
+    high_contrast: bool,
+    clear_color: wgpu::Color,
+}
+
+impl Look {
+    fn from_env(env: &Env) -> Self {
+        Self {
+            high_contrast: env.try_get(theme::HIGH_CONTRAST).unwrap_or(false),
+            clear_color: clear_color(env),
+        }
+    }
+}
+
+/// The dark theme, for rendering without a window.
+impl Default for Look {
+    fn default() -> Self {
+        Self {
+            high_contrast: false,
+            clear_color: theme::to_linear(&theme::DARK_BACKGROUND),
+        }
+    }
+}
+
+pub struct WgpuWidget {
+    gpu: Gpu,
+    post: PostChain,
+    accumulator: Option<Accumulator>,
+    mask: Option<MaskPass>,
+    /// Set by `with_scene` and `with_shader`, drawn instead of the triangle.
+    scene: Option<CustomScene>,
+    /// Kept across devices, unlike `scene`.
+    resources: Resources,
+    drag_policy: DragPolicy,
+    /// Between `INTERACTING` true and false.
+    interacting: bool,
+    /// Reused by every paint of the same size, see `RenderTargets`.
+    targets: Option<RenderTargets>,
+    post_targets: PostTargets,
+    options: GpuOptions,
+    wireframe: bool,
+    keymap: Keymap,
+    color_profile: ColorProfile,
+    #[cfg(feature = "audio")]
+    audio_input: Option<AudioInput>,
+    param_layout: Vec<String>,
+    /// Set by `with_instance_param`.
+    instance_param: Option<String>,
+    instances: u32,
+    /// Set by `with_cursor_input`.
+    cursor: Option<CursorPredictor>,
+    predict_cursor: bool,
+    latency: LatencyMeter,
+    /// Measured in `paint`, sent as `INPUT_LATENCY` with `FRAME_RENDERED`.
+    new_latency: Option<Duration>,
+    /// Set by `with_rulers`.
+    rulers: Option<Rulers>,
+    /// Set by `with_persistence`.
+    persistence: Option<Persistence>,
+    sinks: Vec<SinkSlot>,
+    #[cfg(feature = "http")]
+    http_bridge: Option<HttpBridge>,
+    fill: FillStrat,
+    /// Width over height of the content, when it isn't the widget's.
+    aspect_ratio: Option<f64>,
+    /// Fixed size to render at, in pixels, scaled to the widget.
+    resolution: Option<(u32, u32)>,
+    integer_scaling: bool,
+    dirty: Dirty,
+    /// Steps `WgpuScene::on_update`.
+    timestep: FixedTimestep,
+    /// `Playback::time` after the last animation frame, so other changes
+    /// to it can be told apart as seeks.
+    played_to: f64,
+    /// Set by `with_max_fps`; zero when frames aren't capped.
+    min_frame_interval: f64,
+    /// Time since the last frame rendered under the cap, in seconds.
+    frame_wait: f64,
+    last_frame: Option<ImageBuf>,
+    /// The uploaded `last_frame` and a hash of its pixels, reused while the
+    /// readback doesn't change.
+    cached_image: Option<(u64, PietImage)>,
+    /// Size of `cached_image` in the widget's units, and how it's scaled.
+    cached_layout: (Size, InterpolationMode),
+    event_sink: Option<(ExtEventSink, WidgetId)>,
+    /// Set by `EXPORT_GIF` while frames are being captured.
+    recording: Option<Recording>,
+    /// Set by `ViewportAction::HdrScreenshot` until the next paint.
+    hdr_screenshot: bool,
+    /// Set when rendering panicked; nothing is rendered until a retry.
+    render_error: Option<String>,
+    frame_index: u64,
+}
+
+impl WgpuWidget {
+    pub async fn new() -> Self {
+        Self::with_options(GpuOptions::default()).await
+    }
+
+    /// Panics when there's no usable GPU; see [`try_with_options`].
+    ///
+    /// [`try_with_options`]: WgpuWidget::try_with_options
+    pub async fn with_options(options: GpuOptions) -> Self {
+        match Self::try_with_options(options).await {
+            Ok(widget) => widget,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub async fn try_new() -> Result<Self, GpuError> {
+        Self::try_with_options(GpuOptions::default()).await
+    }
+
+    /// Fails with a [`GpuErrorKind::Unavailable`] error when no adapter can
+    /// render, on VMs and CI machines without GPUs for instance.
+    pub async fn try_with_options(options: GpuOptions) -> Result<Self, GpuError> {
+        let gpu = Gpu::try_new(&options).await?;
+        let post = PostChain::new(&gpu);
+
+        Ok(Self {
+            gpu,
+            post,
+            accumulator: None,
+            mask: None,
+            scene: None,
+            resources: Resources::new(),
+            drag_policy: DragPolicy::default(),
+            interacting: false,
+            targets: None,
+            post_targets: PostTargets::new(),
+            options,
+            wireframe: false,
+            keymap: Keymap::default(),
+            color_profile: ColorProfile::default(),
+            #[cfg(feature = "audio")]
+            audio_input: None,
+            param_layout: Vec::new(),
+            instance_param: None,
+            instances: 1,
+            cursor: None,
+            predict_cursor: false,
+            latency: LatencyMeter::new(),
+            new_latency: None,
+            rulers: None,
+            persistence: None,
+            sinks: Vec::new(),
+            #[cfg(feature = "http")]
+            http_bridge: None,
+            fill: FillStrat::Contain,
+            aspect_ratio: None,
+            resolution: None,
+            integer_scaling: false,
+            dirty: Dirty::all(),
+            timestep: FixedTimestep::from_hz(SIMULATION_HZ),
+            played_to: 0.0,
+            min_frame_interval: 0.0,
+            frame_wait: 0.0,
+            last_frame: None,
+            cached_image: None,
+            cached_layout: (Size::ZERO, InterpolationMode::Bilinear),
+            event_sink: None,
+            recording: None,
+            hdr_screenshot: false,
+            render_error: None,
+            frame_index: 0,
+        })
+    }
+
+    /// A widget drawing the scene `build` makes, see [`with_scene`].
+    ///
+    /// [`with_scene`]: Self::with_scene
+    pub async fn from_fn<S: WgpuScene + 'static>(
+        build: impl FnMut(&wgpu::Device, &wgpu::Queue) -> S + 'static,
+    ) -> Self {
+        Self::new().await.with_scene(build)
+    }
+
+    /// A widget drawing a fragment shader, see [`with_shader`].
+    ///
+    /// [`with_shader`]: Self::with_shader
+    pub async fn shader(source: impl Into<String>) -> Self {
+        Self::new().await.with_shader(source)
+    }
+
+    /// Draw the scene `build` makes instead of the built-in one. `build`
+    /// runs again whenever the device is replaced. Masks, the wireframe
+    /// view and instancing only apply to the built-in scene.
+    pub fn with_scene<S: WgpuScene + 'static>(
+        mut self,
+        build: impl FnMut(&wgpu::Device, &wgpu::Queue) -> S + 'static,
+    ) -> Self {
+        self.scene = Some(CustomScene::from_fn(&self.gpu, build));
+        self
+    }
+
+    /// Draw `source` over the whole frame instead of the built-in scene.
+    /// It's WGSL defining `fs_main`, appended to a prelude declaring
+    /// `globals`, `audio`, `params`, the [`rng`](crate::rng) functions and
+    /// `SceneVertex` with a `uv` going from 0 to 1 across the frame:
+    ///
+    /// ```wgsl
+    /// @fragment
+    /// fn fs_main(in: SceneVertex) -> @location(0) vec4<f32> {
+    ///     return vec4<f32>(in.uv, 0.5 + 0.5 * sin(globals.time), 1.0);
+    /// }
+    /// ```
+    pub fn with_shader(mut self, source: impl Into<String>) -> Self {
+        self.scene = Some(CustomScene::shader(&self.gpu, source.into()));
+        self
+    }
+
+    /// The resources custom scenes look up by handle, to set up before the
+    /// widget is shown.
+    pub fn resources_mut(&mut self) -> &mut Resources {
+        &mut self.resources
+    }
+
+    /// Replace the default keyboard shortcuts.
+    pub fn with_keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// Convert frames to `profile`, to match the display the window is on.
+    pub fn with_color_profile(mut self, profile: ColorProfile) -> Self {
+        self.color_profile = profile;
+        self.dirty.color_profile = true;
+        self
+    }
+
+    /// Choose which parameters the shader sees, in `params.slots` order.
+    pub fn with_param_layout<S: Into<String>>(
+        mut self,
+        names: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.param_layout = names.into_iter().map(Into::into).collect();
+        self.dirty.params = true;
+        self
+    }
+
+    /// Draw as many copies of the scene as parameter `name` says, in a
+    /// grid, with a single instanced draw call. For stress testing.
+    pub fn with_instance_param(mut self, name: impl Into<String>) -> Self {
+        self.instance_param = Some(name.into());
+        self.dirty.globals = true;
+        self
+    }
+
+    /// Pass the cursor to shaders as `globals.cursor`, from 0 to 1 across
+    /// the widget, and repaint as it moves. With `predict`, it's
+    /// extrapolated by the measured input latency, so what's drawn under
+    /// the cursor keeps up with it.
+    pub fn with_cursor_input(mut self, predict: bool) -> Self {
+        self.cursor = Some(CursorPredictor::new());
+        self.predict_cursor = predict;
+        self
+    }
+
+    /// Run `effect` on every frame, after the effects added before it.
+    pub fn with_post_effect(mut self, effect: PostEffect) -> Self {
+        self.post.push(&self.gpu, effect);
+        self.dirty.params = true;
+        self
+    }
+
+    /// What to render while the layout around the widget is dragged, see
+    /// `interaction::INTERACTING`.
+    pub fn with_drag_policy(mut self, policy: DragPolicy) -> Self {
+        self.drag_policy = policy;
+        self
+    }
+
+    /// Average up to `max_samples` frames while nothing changes, repainting
+    /// until they're taken. Shaders vary each sample with `globals.sample`.
+    pub fn with_accumulation(mut self, max_samples: u32) -> Self {
+        self.accumulator = Some(Accumulator::new(&self.gpu, max_samples));
+        self
+    }
+
+    /// Only draw the scene where `mask` is, or outside it when inverted.
+    pub fn with_mask(mut self, mask: Mask) -> Self {
+        self.mask = Some(MaskPass::new(&self.gpu, mask));
+        self
+    }
+
+    /// Throw away accumulated samples, because the scene changed.
+    fn reset_accumulation(&mut self) {
+        if let Some(accumulator) = &mut self.accumulator {
+            accumulator.reset();
+        }
+    }
+
+    /// Render at `ratio`, width over height, rather than the widget's own
+    /// aspect ratio. How the result fills the widget is up to
+    /// [`with_fill`](Self::with_fill).
+    pub fn with_aspect_ratio(mut self, ratio: f64) -> Self {
+        self.aspect_ratio = Some(ratio);
+        self
+    }
+
+    /// How content with a different aspect ratio than the widget fills it.
+    /// `Contain`, the default, letterboxes it; `Cover` crops it; `Fill`
+    /// stretches it.
+    pub fn with_fill(mut self, fill: FillStrat) -> Self {
+        self.fill = fill;
+        self
+    }
+
+    /// Always render at `width` by `height` pixels, scaled up or down to
+    /// the widget with nearest-neighbor filtering, for pixel art and
+    /// emulators. Takes precedence over [`with_aspect_ratio`].
+    ///
+    /// [`with_aspect_ratio`]: Self::with_aspect_ratio
+    pub fn with_resolution(mut self, width: u32, height: u32) -> Self {
+        self.resolution = Some((width.max(1), height.max(1)));
+        self
+    }
+
+    /// Scale a fixed resolution by the largest whole number of device
+    /// pixels that fits, so every pixel comes out the same size. Ignores
+    /// [`with_fill`](Self::with_fill).
+    pub fn with_integer_scaling(mut self, integer_scaling: bool) -> Self {
+        self.integer_scaling = integer_scaling;
+        self
+    }
+
+    /// The size to render at for a widget of `size`: the fixed resolution,
+    /// or else the largest of the content's aspect ratio that fits inside,
+    /// or for `Cover` the smallest that covers it.
+    fn content_size(&self, size: Size) -> Size {
+        if let Some((width, height)) = self.resolution {
+            return Size::new(width as f64, height as f64);
+        }
+
+        let ratio = match self.aspect_ratio {
+            Some(ratio) if ratio > 0.0 => ratio,
+            _ => return size,
+        };
+        let by_width = Size::new(size.width, size.width / ratio);
+        let by_height = Size::new(size.height * ratio, size.height);
+        let width_fits = by_width.height <= size.height;
+        let cover = matches!(self.fill, FillStrat::Cover);
+        if width_fits != cover {
+            by_width
+        } else {
+            by_height
+        }
+    }
+
+    /// Hand every new frame to `sink`, after the sinks added before it.
+    pub fn with_frame_sink(self, sink: impl FrameSink + 'static) -> Self {
+        self.with_frame_sink_every(1, sink)
+    }
+
+    /// Hand only every `n`th new frame to `sink`, starting with the first.
+    pub fn with_frame_sink_every(mut self, n: u32, sink: impl FrameSink + 'static) -> Self {
+        self.sinks.push(SinkSlot::new(Box::new(sink), n));
+        self
+    }
+
+    /// Keep `bridge` up to date with the parameters, for remote reads.
+    #[cfg(feature = "http")]
+    pub fn with_http_bridge(mut self, bridge: HttpBridge) -> Self {
+        self.http_bridge = Some(bridge);
+        self
+    }
+
+    /// Remember the wireframe toggle, the parameters and the guides in the
+    /// RON file at `path`, under `key`, and restore them when the widget is
+    /// added. Keys tell viewports sharing a file apart.
+    pub fn with_persistence(mut self, path: impl Into<PathBuf>, key: impl Into<String>) -> Self {
+        self.persistence = Some(Persistence::new(path.into(), key.into()));
+        self
+    }
+
+    /// Note that persisted settings changed, returning whether a save
+    /// timer should be started.
+    fn settings_changed(&mut self, data: &ViewportState) -> bool {
+        self.persistence.as_mut().map_or(false, |persistence| {
+            persistence.changed(data, self.wireframe)
+        })
+    }
+
+    /// Draw pixel rulers along the top and left edges, with guides that can
+    /// be dragged out of them into `ViewportState::guides`.
+    pub fn with_rulers(mut self) -> Self {
+        self.rulers = Some(Rulers::default());
+        self
+    }
+
+    /// Render at most `fps` frames a second while playing. Playback still
+    /// follows the clock, so a capped animation skips ahead rather than
+    /// slowing down.
+    pub fn with_max_fps(mut self, fps: f64) -> Self {
+        self.min_frame_interval = 1.0 / fps.max(0.001);
+        self
+    }
+
+    /// Feed captured audio to the shader's `audio` uniform every paint, and
+    /// keep painting while paused so it doesn't freeze.
+    #[cfg(feature = "audio")]
+    pub fn with_audio_input(mut self, input: AudioInput) -> Self {
+        self.audio_input = Some(input);
+        self
+    }
+
+    fn perform_action(
+        &mut self,
+        ctx: &mut EventCtx,
+        action: ViewportAction,
+        data: &mut ViewportState,
+    ) {
+        match action {
+            ViewportAction::TogglePlayback => data.playback.toggle(),
+            ViewportAction::SeekToStart => data.playback.seek(0.0),
+            ViewportAction::Screenshot => self.save_screenshot(),
+            ViewportAction::HdrScreenshot => {
+                self.hdr_screenshot = true;
+                ctx.request_paint();
+            }
+            ViewportAction::RetryRender => {
+                if self.render_error.take().is_some() {
+                    ctx.request_paint();
+                }
+            }
+            ViewportAction::ToggleWireframe => {
+                if self.gpu.wireframe_pipeline.is_some() {
+                    self.wireframe = !self.wireframe;
+                    self.reset_accumulation();
+                    ctx.request_paint();
+                    if self.settings_changed(data) {
+                        let token = ctx.request_timer(SAVE_DELAY);
+                        if let Some(persistence) = &mut self.persistence {
+                            persistence.set_timer(token);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn save_screenshot(&self) {
+        let frame = match &self.last_frame {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let path = screenshot_path("png");
+        if let Err(err) = image::save_buffer(
+            &path,
+            frame.raw_pixels(),
+            frame.width() as u32,
+            frame.height() as u32,
+            image::ColorType::Rgba8,
+        ) {
+            eprintln!("Failed to save screenshot to {}: {}", path, err);
+        }
+    }
+
+    /// Restart the scene's simulation at `time`, which playback jumped to
+    /// without playing through.
+    fn seek(&mut self, time: f64) {
+        self.played_to = time;
+        self.timestep.reset();
+        if let Some(scene) = &mut self.scene {
+            scene.scene.on_seek(time);
+        }
+    }
+
+    /// Whether frames keep coming without anything else changing: while
+    /// playing, and while audio input feeds the shader, paused or not.
+    fn animating(&self, data: &ViewportState) -> bool {
+        #[cfg(feature = "audio")]
+        if self.audio_input.is_some() {
+            return true;
+        }
+        data.playback.playing
+    }
+
+    /// Render `export` outside the interactive frame, at a multiple of the
+    /// widget's size.
+    fn export_still(
+        &mut self,
+        export: &StillExport,
+        data: &ViewportState,
+        env: &Env,
+        widget_size: Size,
+    ) -> Result<(), Box<dyn Error>> {
+        let (base_width, base_height) = match &self.last_frame {
+            Some(frame) => (frame.width() as u32, frame.height() as u32),
+            None => return Err("nothing has been rendered yet".into()),
+        };
+        // One scale for both sides, so the still keeps the frame's shape.
+        let max_size = self.gpu.device.limits().max_texture_dimension_2d;
+        let scale = export
+            .scale
+            .clamp(1, still::MAX_SCALE)
+            .min(max_size / base_width.max(base_height).max(1))
+            .max(1);
+        let (width, height) = (base_width * scale, base_height * scale);
+
+        let tile = Tile::whole(width, height);
+        let look = Look::from_env(env);
+        let image = self.render_still(data, &look, widget_size, &tile, export.samples, true)?;
+        image.save(&export.path)?;
+        Ok(())
+    }
+
+    /// Render `export` a tile at a time, each no larger than the device
+    /// allows, and stitch them together.
+    fn export_print(
+        &mut self,
+        export: &PrintExport,
+        data: &ViewportState,
+        env: &Env,
+        widget_size: Size,
+    ) -> Result<(), Box<dyn Error>> {
+        let max_size = self
+            .gpu
+            .device
+            .limits()
+            .max_texture_dimension_2d
+            .min(still::MAX_TILE_SIZE);
+        let look = Look::from_env(env);
+        let mut image = RgbaImage::new(export.width, export.height);
+        for tile in still::tiles(export.width, export.height, max_size) {
+            // Post effects work on whole frames, so prints go without.
+            let pixels =
+                self.render_still(data, &look, widget_size, &tile, export.samples, false)?;
+            imageops::replace(&mut image, &pixels, tile.x as i64, tile.y as i64);
+        }
+        image.save(&export.path)?;
+        Ok(())
+    }
+
+    /// Render `tile` of a still: every sample, averaged, then the post chain
+    /// once over the result if `post` is set. A readback that fails starts
+    /// over on a new device, like a failed frame does.
+    fn render_still(
+        &mut self,
+        data: &ViewportState,
+        look: &Look,
+        widget_size: Size,
+        tile: &Tile,
+        samples: u32,
+        post: bool,
+    ) -> Result<RgbaImage, Box<dyn Error>> {
+        let (width, height) = (tile.width, tile.height);
+        let samples = samples.max(1);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let targets = RenderTargets::new(
+            &self.gpu.device,
+            "Still",
+            size,
+            self.mask.is_some() && self.scene.is_none(),
+        );
+        // Separate from the widget's, which stay the size of the widget.
+        let mut post_targets = PostTargets::new();
+
+        let cursor = match self.cursor.as_ref().and_then(CursorPredictor::position) {
+            Some(pos) => [
+                (pos.x / widget_size.width.max(1.0)) as f32,
+                (pos.y / widget_size.height.max(1.0)) as f32,
+            ],
+            None => [0.0; 2],
+        };
+        let frame = SceneFrame {
+            width,
+            height,
+            time: data.playback.time,
+            index: self.frame_index,
+            alpha: self.timestep.alpha(),
+        };
+        let mut accumulator = Accumulator::new(&self.gpu, samples);
+        let mut readback = None;
+
+        for sample in 0..samples {
+            // From fractions of a pixel to clip space, where y points up.
+            let [x, y] = still::jitter(sample);
+            let globals = Globals {
+                time: data.playback.time as f32,
+                high_contrast: look.high_contrast as u8 as f32,
+                sample: sample as f32,
+                instances: self.instances as f32,
+                cursor,
+                jitter: [x * 2.0 / width as f32, -y * 2.0 / height as f32],
+                tile: tile.transform,
+            };
+            self.gpu
+                .queue
+                .write_buffer(&self.gpu.globals_buffer, 0, bytemuck::bytes_of(&globals));
+
+            // User code runs here, so a panic puts the widget in its error
+            // state, the same as in paint.
+            let encoded = panic::catch_unwind(AssertUnwindSafe(|| {
+                if let Some(scene) = &mut self.scene {
+                    scene.resize(width, height);
+                    self.resources.upload(&self.gpu.device, &self.gpu.queue);
+                    let mut scene_ctx = SceneContext {
+                        device: &self.gpu.device,
+                        queue: &self.gpu.queue,
+                        resources: &mut self.resources,
+                    };
+                    scene.scene.prepare(&mut scene_ctx, &frame);
+                    self.resources.upload(&self.gpu.device, &self.gpu.queue);
+                }
+
+                let mut encoder =
+                    self.gpu
+                        .device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Still Encoder"),
+                        });
+                self.encode_scene(
+                    &mut encoder,
+                    &targets.scene_view,
+                    targets.stencil_view.as_ref(),
+                    look.clear_color,
+                );
+                let mut command_buffers = vec![encoder.finish()];
+                if let Some(scene) = &self.scene {
+                    command_buffers.extend(scene.scene.encode(&EncodeContext {
+                        device: &self.gpu.device,
+                        target: &targets.scene_view,
+                        resources: &self.resources,
+                        frame,
+                    }));
+                }
+                command_buffers
+            }));
+            let mut command_buffers = match encoded {
+                Ok(command_buffers) => command_buffers,
+                Err(payload) => {
+                    let message = panic_message(&*payload);
+                    let err = format!("the scene panicked: {}", message);
+                    self.render_error = Some(message);
+                    return Err(err.into());
+                }
+            };
+
+            let mut encoder =
+                self.gpu
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Still Accumulate Encoder"),
+                    });
+            let average = accumulator.encode(&self.gpu, &mut encoder, &targets.scene, size);
+            if sample + 1 == samples {
+                let post_output = if post {
+                    self.post.encode(
+                        &self.gpu,
+                        &mut encoder,
+                        average,
+                        size,
+                        &data.params,
+                        &mut post_targets,
+                    )
+                } else {
+                    None
+                };
+                let present_input =
+                    post_output.unwrap_or_else(|| average.create_view(&Default::default()));
+                self.gpu
+                    .encode_present(&mut encoder, &present_input, &targets.output_view);
+                readback = Some(StillReadback::encode(
+                    &self.gpu,
+                    &mut encoder,
+                    &targets.output,
+                    width,
+                    height,
+                ));
+            }
+            command_buffers.push(encoder.finish());
+            self.gpu.queue.submit(command_buffers);
+        }
+
+        // The custom scene goes back to the widget's size on the next paint.
+        let readback = readback.ok_or("no samples were rendered")?;
+        match readback.read(&self.gpu, self.options.frame_timeout * samples) {
+            Ok(image) => Ok(image),
+            Err(err) => {
+                let message = match err {
+                    ReadbackError::Timeout => "timed out reading back the still",
+                    ReadbackError::MapFailed => "couldn't read back the still",
+                };
+                self.recover_from_readback(err);
+                Err(message.into())
+            }
+        }
+    }
+
+    /// Render one frame of `data` at `width` by `height` pixels, post effects
+    /// and all, without a window to paint into. The dark theme stands in for
+    /// the window's `Env`.
+    pub fn render_headless(
+        &mut self,
+        data: &ViewportState,
+        width: u32,
+        height: u32,
+        samples: u32,
+    ) -> Result<RgbaImage, Box<dyn Error>> {
+        let max_size = self.gpu.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width.max(height) > max_size {
+            return Err(format!(
+                "can't render {}x{}, the device allows up to {}x{}",
+                width, height, max_size, max_size
+            )
+            .into());
+        }
+
+        self.seek(data.playback.time);
+        self.instances = self
+            .instance_param
+            .as_deref()
+            .and_then(|name| data.params.float(name))
+            .map_or(1, |count| count.round().max(1.0) as u32);
+        let params = ParamUniforms {
+            slots: data.params.pack(self.param_layout.as_slice()),
+        };
+        self.gpu
+            .queue
+            .write_buffer(&self.gpu.params_buffer, 0, bytemuck::bytes_of(&params));
+        self.post.write_params(&self.gpu, &data.params);
+        self.gpu.set_color_profile(self.color_profile);
+
+        let size = Size::new(width as f64, height as f64);
+        let tile = Tile::whole(width, height);
+        let image = self.render_still(data, &Look::default(), size, &tile, samples, true);
+        // The next paint writes everything again for the window.
+        self.dirty = Dirty::all();
+        image
+    }
+
+    /// Record the scene's draw calls. A panic in here puts the widget in its
+    /// error state instead of taking down the app.
+    fn encode_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        stencil: Option<&wgpu::TextureView>,
+        clear_color: wgpu::Color,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: compat::clear(clear_color),
+            })],
+            depth_stencil_attachment: stencil.map(|view| wgpu::RenderPassDepthStencilAttachment {
+                view,
+                depth_ops: Some(compat::clear_and_discard(1.0)),
+                stencil_ops: Some(compat::clear_and_discard(0)),
+            }),
+        });
+
+        render_pass.set_bind_group(0, &self.gpu.globals_bind_group, &[]);
+        if let Some(scene) = &self.scene {
+            scene.scene.render(&mut render_pass, &self.resources);
+            return;
+        }
+
+        let (pipeline, wireframe_pipeline) = match (&self.mask, stencil) {
+            (Some(mask), Some(_)) => {
+                mask.encode(&mut render_pass, &self.gpu);
+                (&mask.scene_pipeline, &mask.wireframe_pipeline)
+            }
+            _ => (&self.gpu.render_pipeline, &self.gpu.wireframe_pipeline),
+        };
+        let pipeline = match wireframe_pipeline {
+            Some(wireframe_pipeline) if self.wireframe => wireframe_pipeline,
+            _ => pipeline,
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_vertex_buffer(0, self.gpu.vertex_buffer.slice(..));
+        render_pass.draw(0..self.gpu.num_vertices, 0..self.instances);
+    }
+
+    /// Draw `cached_image` into the widget, placed by the fill mode or the
+    /// integer scaling of `with_resolution`.
+    fn paint_cached(&self, ctx: &mut PaintCtx) {
+        let image = match &self.cached_image {
+            Some((_, image)) => image,
+            None => return,
+        };
+        let (image_size, interpolation) = self.cached_layout;
+
+        let widget_rect = ctx.size().to_rect();
+        let transform = match self.resolution {
+            Some(_) if self.integer_scaling => {
+                integer_fit(widget_rect.size(), image_size, ctx.scale().x())
+            }
+            _ => self.fill.affine_to_fill(widget_rect.size(), image_size),
+        };
+        if image_size != widget_rect.size() {
+            ctx.fill(widget_rect, &Color::BLACK);
+        }
+        ctx.with_save(|ctx| {
+            ctx.clip(widget_rect);
+            ctx.transform(transform);
+            ctx.draw_image(image, image_size.to_rect(), interpolation);
+        });
+    }
+
+    /// Draw the error state shown after a render panicked.
+    fn paint_error(&self, ctx: &mut PaintCtx, data: &ViewportState, env: &Env) {
+        let message = match &self.render_error {
+            Some(message) => message,
+            None => return,
+        };
+
+        let background = env
+            .try_get(theme::VIEWPORT_BACKGROUND)
+            .unwrap_or(theme::DARK_BACKGROUND);
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &background);
+
+        let mut title =
+            LocalizedString::new("druid-wgpu-render-failed").with_placeholder("Rendering failed");
+        title.resolve(data, env);
+        let mut hint = LocalizedString::new("druid-wgpu-render-failed-hint")
+            .with_placeholder("The viewport will try again when asked to retry.");
+        hint.resolve(data, env);
+
+        let text = format!(
+            "{}\n\n{}\n\n{}",
+            title.localized_str(),
+            message,
+            hint.localized_str()
+        );
+        let layout = ctx
+            .text()
+            .new_text_layout(text)
+            .font(FontFamily::SYSTEM_UI, 14.0)
+            .text_color(env.get(druid::theme::TEXT_COLOR))
+            .max_width(rect.width() - 32.0)
+            .build();
+        if let Ok(layout) = layout {
+            ctx.draw_text(&layout, (16.0, 16.0));
+        }
+    }
+
+    /// Report a frame that couldn't be read back and start over on a new
+    /// device. The old one is leaked rather than dropped, because dropping a
+    /// hung device waits for the submission that never finishes.
+    fn recover_from_readback(&mut self, err: ReadbackError) {
+        let (kind, message) = match err {
+            ReadbackError::Timeout => (
+                GpuErrorKind::Timeout,
+                format!(
+                    "frame didn't finish within {:?}, recreating the device",
+                    self.options.frame_timeout
+                ),
+            ),
+            ReadbackError::MapFailed => (
+                GpuErrorKind::DeviceLost,
+                "couldn't read back the frame, recreating the device".to_string(),
+            ),
+        };
+        self.report_error(GpuError {
+            kind,
+            message,
+            frame: Some(self.frame_index),
+        });
+
+        let gpu = match pollster::block_on(Gpu::try_new(&self.options)) {
+            Ok(gpu) => gpu,
+            Err(err) => {
+                // Stay on the lost device until `RetryRender` tries again.
+                self.render_error = Some(format!("couldn't recreate the device: {}", err));
+                self.report_error(err);
+                if let Some((sink, id)) = &self.event_sink {
+                    let _ = sink.submit_command(REPAINT, (), Target::Widget(*id));
+                }
+                return;
+            }
+        };
+        std::mem::forget(std::mem::replace(&mut self.gpu, gpu));
+        self.resources.reset();
+        self.targets = None;
+        self.post_targets.clear();
+        self.post = self.post.rebuild(&self.gpu);
+        self.mask = self.mask.as_ref().map(|mask| mask.rebuild(&self.gpu));
+        if let Some(scene) = &mut self.scene {
+            scene.rebuild(&self.gpu);
+        }
+        if let Some(accumulator) = &self.accumulator {
+            self.accumulator = Some(Accumulator::new(&self.gpu, accumulator.max_samples()));
+        }
+        self.dirty = Dirty::all();
+        self.cached_image = None;
+
+        if let Some((sink, id)) = &self.event_sink {
+            let _ = sink.submit_command(REPAINT, (), Target::Widget(*id));
+        }
+    }
+
+    fn report_error(&self, error: GpuError) {
+        match &self.event_sink {
+            Some((sink, _)) => {
+                let _ = sink.submit_command(GPU_ERROR, error, Target::Global);
+            }
+            None => eprintln!("{}", error),
+        }
+    }
+
+    /// The adapter and device the widget renders with.
+    pub fn capabilities(&self) -> &GpuCapabilities {
+        &self.gpu.capabilities
+    }
+
+    /// The time from input events until frames showing them are drawn,
+    /// averaged over recent frames. Also sent with every frame as
+    /// [`INPUT_LATENCY`].
+    pub fn input_latency(&self) -> Option<Duration> {
+        self.latency.average()
+    }
+
+    /// The most recently rendered frame, at the widget's size in pixels.
+    pub fn last_frame(&self) -> Option<ImageBuf> {
+        self.last_frame.clone()
+    }
+}
+
+impl Widget<ViewportState> for WgpuWidget {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut ViewportState, env: &Env) {
+        match event {
+            Event::MouseDown(_) | Event::MouseUp(_) | Event::Wheel(_) | Event::KeyDown(_) => {
+                self.latency.input()
+            }
+            Event::MouseMove(mouse) => {
+                self.latency.input();
+                if let Some(cursor) = &mut self.cursor {
+                    cursor.moved(mouse.pos);
+                    self.dirty.globals = true;
+                    ctx.request_paint();
+                }
+            }
+            _ => (),
+        }
+
+        if let Some(rulers) = &mut self.rulers {
+            if rulers.event(ctx, event, &mut data.guides) {
+                ctx.set_handled();
+                return;
+            }
+        }
+
+        if let Some(scene) = &mut self.scene {
+            if scene.scene.on_event(event) {
+                self.reset_accumulation();
+                ctx.set_handled();
+                ctx.request_paint();
+                return;
+            }
+        }
+
+        match event {
+            Event::WindowConnected => {
+                if self.animating(data) {
+                    ctx.request_anim_frame();
+                }
+            }
+            Event::AnimFrame(interval) => {
+                if self.animating(data) {
+                    // Under a cap, frames in between only add up time.
+                    self.frame_wait += *interval as f64 * 1e-9;
+                    if self.frame_wait >= self.min_frame_interval {
+                        let elapsed = std::mem::take(&mut self.frame_wait);
+                        if data.playback.playing {
+                            let before = data.playback.time;
+                            data.playback.advance(elapsed);
+                            if data.playback.time < before {
+                                // Looped around to the start.
+                                self.seek(data.playback.time);
+                            } else if let Some(scene) = &mut self.scene {
+                                let scene = &mut scene.scene;
+                                self.timestep
+                                    .advance(elapsed, |dt| scene.on_update(dt, data));
+                            }
+                            self.played_to = data.playback.time;
+                            self.dirty.globals = true;
+                        } else {
+                            // Only the audio moved on, but that's a new image.
+                            self.reset_accumulation();
+                        }
+                        ctx.request_paint();
+                    }
+
+                    ctx.request_anim_frame();
+                }
+            }
+            Event::MouseDown(_) => {
+                ctx.request_focus();
+            }
+            Event::KeyDown(key) => {
+                if let Some(action) = self.keymap.action_for(key) {
+                    self.perform_action(ctx, action, data);
+                    ctx.set_handled();
+                }
+            }
+            Event::Command(cmd) if cmd.is(VIEWPORT_ACTION) => {
+                self.perform_action(ctx, *cmd.get_unchecked(VIEWPORT_ACTION), data);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(SET_PARAMETER) => {
+                let change = cmd.get_unchecked(SET_PARAMETER);
+                if change.name == PLAYBACK_TIME {
+                    data.playback.seek(change.value.as_f64());
+                } else {
+                    data.params
+                        .set_coerced(change.name.clone(), change.value.clone());
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(SET_LUT) => {
+                let change = cmd.get_unchecked(SET_LUT);
+                if self
+                    .post
+                    .set_lut(&self.gpu, &change.effect, change.lut.clone())
+                {
+                    ctx.request_paint();
+                    ctx.set_handled();
+                }
+            }
+            Event::Command(cmd) if cmd.is(TOGGLE_EFFECT) => {
+                let name = cmd.get_unchecked(TOGGLE_EFFECT);
+                if let Some(toggle) = self.post.toggle(name) {
+                    let enabled = data.params.bool(toggle).unwrap_or(false);
+                    data.params.set(toggle, !enabled);
+                    ctx.set_handled();
+                }
+            }
+            Event::Command(cmd) if cmd.is(EXPORT_GIF) => {
+                let export = cmd.get_unchecked(EXPORT_GIF).clone();
+                let start = export.start;
+                self.recording = Some(Recording::new(export, &data.playback));
+                data.playback.pause();
+                data.playback.seek(start);
+                self.dirty.globals = true;
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(INTERACTING) => {
+                let interacting = *cmd.get_unchecked(INTERACTING);
+                if interacting != self.interacting {
+                    self.interacting = interacting;
+                    ctx.request_paint();
+                }
+            }
+            Event::Command(cmd) if cmd.is(EXPORT_STILL) => {
+                let export = cmd.get_unchecked(EXPORT_STILL).clone();
+                if let Err(err) = self.export_still(&export, data, env, ctx.size()) {
+                    eprintln!(
+                        "Failed to export still to {}: {}",
+                        export.path.display(),
+                        err
+                    );
+                }
+                // Put the interactive globals back.
+                self.dirty.globals = true;
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(EXPORT_PRINT) => {
+                let export = cmd.get_unchecked(EXPORT_PRINT).clone();
+                if let Err(err) = self.export_print(&export, data, env, ctx.size()) {
+                    eprintln!(
+                        "Failed to export print to {}: {}",
+                        export.path.display(),
+                        err
+                    );
+                }
+                self.dirty.globals = true;
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(RECORD_NEXT) => {
+                if let Some(recording) = &mut self.recording {
+                    if recording.advance() {
+                        data.playback.seek(recording.time());
+                        // Paint even if seeking was clamped to the end.
+                        self.dirty.globals = true;
+                        ctx.request_paint();
+                    } else if let Some(recording) = self.recording.take() {
+                        data.playback = recording.restore().clone();
+                        recording.finish();
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(RESTORE_SETTINGS) => {
+                let restored = self
+                    .persistence
+                    .as_mut()
+                    .and_then(|persistence| persistence.restore(data));
+                if let Some(wireframe) = restored {
+                    self.wireframe = wireframe && self.gpu.wireframe_pipeline.is_some();
+                    self.reset_accumulation();
+                    ctx.request_paint();
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(PUBLISH_CAPABILITIES) => {
+                data.gpu = Some(self.gpu.capabilities.clone());
+                ctx.set_handled();
+            }
+            Event::Timer(token) => {
+                if let Some(persistence) = &mut self.persistence {
+                    if persistence.timer(*token) {
+                        ctx.set_handled();
+                    }
+                }
+            }
+            Event::Command(cmd) if cmd.is(REPAINT) => {
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(FRAME_RENDERED) => {
+                if let Some(frame) = &self.last_frame {
+                    ctx.submit_notification(FRAME_AVAILABLE.with(frame.clone()));
+                }
+                if let Some(latency) = self.new_latency.take() {
+                    ctx.submit_notification(INPUT_LATENCY.with(latency));
+                }
+                ctx.set_handled();
+            }
+            _ => (),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &ViewportState,
+        env: &Env,
+    ) {
+        match event {
+            LifeCycle::WidgetAdded => {
+                self.event_sink = Some((ctx.get_external_handle(), ctx.widget_id()));
+                if self.persistence.is_some() {
+                    ctx.submit_command(RESTORE_SETTINGS.to(ctx.widget_id()));
+                }
+                ctx.submit_command(PUBLISH_CAPABILITIES.to(ctx.widget_id()));
+                self.seek(data.playback.time);
+                if gpu::is_software(&self.gpu.adapter) {
+                    ctx.submit_command(
+                        SOFTWARE_RENDERER
+                            .with(self.gpu.adapter.name.clone())
+                            .to(Target::Global),
+                    );
+                }
+                #[cfg(feature = "http")]
+                if let Some(bridge) = &self.http_bridge {
+                    bridge.attach(ctx.get_external_handle());
+                    bridge.publish(&data.params);
+                    bridge.publish_time(data.playback.time);
+                }
+            }
+            LifeCycle::BuildFocusChain => ctx.register_for_focus(),
+            _ => (),
+        }
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &ViewportState,
+        data: &ViewportState,
+        env: &Env,
+    ) {
+        if ctx.env_key_changed(&theme::VIEWPORT_BACKGROUND) {
+            self.reset_accumulation();
+            ctx.request_paint();
+        }
+
+        if self.rulers.is_some()
+            && (ctx.env_key_changed(&theme::RULER_BACKGROUND)
+                || ctx.env_key_changed(&theme::RULER_TICKS)
+                || ctx.env_key_changed(&theme::GUIDE_COLOR))
+        {
+            ctx.request_paint();
+        }
+
+        if ctx.env_key_changed(&theme::HIGH_CONTRAST) {
+            self.dirty.globals = true;
+            self.reset_accumulation();
+            ctx.request_paint();
+        }
+
+        if !old_data.params.same(&data.params) {
+            #[cfg(feature = "http")]
+            if let Some(bridge) = &self.http_bridge {
+                bridge.publish(&data.params);
+            }
+            self.dirty.params = true;
+            self.reset_accumulation();
+            ctx.request_paint();
+        }
+
+        if !old_data.guides.same(&data.guides) {
+            ctx.request_paint();
+        }
+
+        if (!old_data.params.same(&data.params) || !old_data.guides.same(&data.guides))
+            && self.settings_changed(data)
+        {
+            let token = ctx.request_timer(SAVE_DELAY);
+            if let Some(persistence) = &mut self.persistence {
+                persistence.set_timer(token);
+            }
+        }
+
+        if data.playback.time != self.played_to {
+            self.seek(data.playback.time);
+        }
+
+        if !old_data.playback.same(&data.playback) {
+            #[cfg(feature = "http")]
+            if let Some(bridge) = &self.http_bridge {
+                bridge.publish_time(data.playback.time);
+            }
+            // Seeks while paused still need a new frame.
+            self.dirty.globals = true;
+            self.reset_accumulation();
+            ctx.request_paint();
+
+            if data.playback.playing && !old_data.playback.playing {
+                ctx.request_anim_frame();
+            }
+        }
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &ViewportState,
+        env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &ViewportState, env: &Env) {
+        if self.render_error.is_some() {
+            self.paint_error(ctx, data, env);
+            return;
+        }
+
+        if self.interacting && self.drag_policy == DragPolicy::Pause {
+            if self.cached_image.is_some() {
+                self.paint_cached(ctx);
+                if let Some(rulers) = &self.rulers {
+                    rulers.paint(ctx, &data.guides, env);
+                }
+                return;
+            }
+        }
+
+        self.frame_index += 1;
+        if self.options.validation {
+            self.gpu
+                .device
+                .push_error_scope(wgpu::ErrorFilter::Validation);
+        }
+
+        // Render at a lower resolution when the widget is bigger than the
+        // device allows, and scale the result up.
+        let max_size = self.gpu.device.limits().max_texture_dimension_2d as f64;
+        let size = self.content_size(ctx.size());
+        let mut scale = (max_size / size.width.max(size.height)).min(1.0);
+        let preview = match self.drag_policy {
+            DragPolicy::Preview(fraction) if self.interacting => {
+                scale *= fraction.clamp(0.05, 1.0);
+                true
+            }
+            _ => false,
+        };
+
+        let texture_width = (size.width * scale).ceil() as u32;
+        let texture_height = (size.height * scale).ceil() as u32;
+
+        let (texture_width_padded, texture_height_padded) =
+            gpu::padded_output_size(texture_width, texture_height);
+
+        self.gpu
+            .resize_output_buffer(texture_width_padded, texture_height_padded);
+
+        let high_contrast = env.try_get(theme::HIGH_CONTRAST).unwrap_or(false);
+
+        // Every accumulated sample needs its own index.
+        let sample = self.accumulator.as_ref().map_or(0, Accumulator::sample);
+        if self.accumulator.is_some() {
+            self.dirty.globals = true;
+        }
+
+        let instances = self
+            .instance_param
+            .as_deref()
+            .and_then(|name| data.params.float(name))
+            .map_or(1, |count| count.round().max(1.0) as u32);
+        if instances != self.instances {
+            self.instances = instances;
+            self.dirty.globals = true;
+        }
+
+        // A predicted cursor keeps moving between events.
+        if self.predict_cursor {
+            self.dirty.globals = true;
+        }
+
+        if std::mem::take(&mut self.dirty.globals) {
+            let cursor = self.cursor.as_ref().and_then(|cursor| {
+                if self.predict_cursor {
+                    cursor.predict(self.latency.average().unwrap_or(DEFAULT_LATENCY))
+                } else {
+                    cursor.position()
+                }
+            });
+            let cursor = match cursor {
+                Some(pos) => [
+                    (pos.x / ctx.size().width.max(1.0)) as f32,
+                    (pos.y / ctx.size().height.max(1.0)) as f32,
+                ],
+                None => [0.0; 2],
+            };
+            let globals = Globals {
+                time: data.playback.time as f32,
+                high_contrast: high_contrast as u8 as f32,
+                sample: sample as f32,
+                instances: self.instances as f32,
+                cursor,
+                jitter: [0.0; 2],
+                tile: IDENTITY_TILE,
+            };
+            self.gpu
+                .queue
+                .write_buffer(&self.gpu.globals_buffer, 0, bytemuck::bytes_of(&globals));
+        }
+
+        if std::mem::take(&mut self.dirty.params) {
+            let params = ParamUniforms {
+                slots: data.params.pack(self.param_layout.as_slice()),
+            };
+            self.gpu
+                .queue
+                .write_buffer(&self.gpu.params_buffer, 0, bytemuck::bytes_of(&params));
+            self.post.write_params(&self.gpu, &data.params);
+        }
+
+        #[cfg(feature = "audio")]
+        if let Some(input) = &self.audio_input {
+            self.gpu.queue.write_buffer(
+                &self.gpu.audio_buffer,
+                0,
+                bytemuck::bytes_of(&input.frame()),
+            );
+        }
+
+        if std::mem::take(&mut self.dirty.color_profile) {
+            self.gpu.set_color_profile(self.color_profile);
+        }
+
+        // Kept from the last paint unless the size changed, or whether
+        // there's a stencil, like the output buffer.
+        let target_size = wgpu::Extent3d {
+            width: texture_width,
+            height: texture_height,
+            depth_or_array_layers: 1,
+        };
+        let stencil = self.mask.is_some() && self.scene.is_none();
+        let targets = match self.targets.take() {
+            Some(targets) if targets.matches(target_size, stencil) => targets,
+            _ => RenderTargets::new(&self.gpu.device, "Render", target_size, stencil),
+        };
+
+        // we need to store this for later
+        let u32_size = std::mem::size_of::<u32>() as u32;
+
+        let clear_color = clear_color(env);
+
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        let frame = SceneFrame {
+            width: texture_width,
+            height: texture_height,
+            time: data.playback.time,
+            index: self.frame_index,
+            alpha: self.timestep.alpha(),
+        };
+        let encoded = panic::catch_unwind(AssertUnwindSafe(|| {
+            if let Some(scene) = &mut self.scene {
+                scene.resize(texture_width, texture_height);
+                self.resources.upload(&self.gpu.device, &self.gpu.queue);
+                let mut scene_ctx = SceneContext {
+                    device: &self.gpu.device,
+                    queue: &self.gpu.queue,
+                    resources: &mut self.resources,
+                };
+                scene.scene.prepare(&mut scene_ctx, &frame);
+                self.resources.upload(&self.gpu.device, &self.gpu.queue);
+            }
+            self.encode_scene(
+                &mut encoder,
+                &targets.scene_view,
+                targets.stencil_view.as_ref(),
+                clear_color,
+            );
+            self.scene.as_ref().map_or_else(Vec::new, |scene| {
+                scene.scene.encode(&EncodeContext {
+                    device: &self.gpu.device,
+                    target: &targets.scene_view,
+                    resources: &self.resources,
+                    frame,
+                })
+            })
+        }));
+        let scene_buffers = match encoded {
+            Ok(scene_buffers) => scene_buffers,
+            Err(payload) => {
+                if self.options.validation {
+                    // Errors from a half-encoded frame would only be noise.
+                    let _ = pollster::block_on(self.gpu.device.pop_error_scope());
+                }
+                self.render_error = Some(panic_message(&*payload));
+                self.paint_error(ctx, data, env);
+                return;
+            }
+        };
+
+        // The scene's own command buffers go between the scene pass and
+        // everything reading the frame.
+        let mut command_buffers = Vec::new();
+        if !scene_buffers.is_empty() {
+            let scene_encoder = std::mem::replace(
+                &mut encoder,
+                self.gpu
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Render Encoder"),
+                    }),
+            );
+            command_buffers.push(scene_encoder.finish());
+            command_buffers.extend(scene_buffers);
+        }
+
+        // Software rasterizers, and previews, only get the scene itself.
+        let reduced_quality = preview || self.options.reduced_quality(&self.gpu.adapter);
+        let scene = match &mut self.accumulator {
+            Some(accumulator) if !reduced_quality => {
+                accumulator.encode(&self.gpu, &mut encoder, &targets.scene, targets.size)
+            }
+            _ => &targets.scene,
+        };
+
+        let post_output = if reduced_quality {
+            None
+        } else {
+            self.post.encode(
+                &self.gpu,
+                &mut encoder,
+                scene,
+                targets.size,
+                &data.params,
+                &mut self.post_targets,
+            )
+        };
+        let present_input = post_output.unwrap_or_else(|| scene.create_view(&Default::default()));
+        self.gpu
+            .encode_present(&mut encoder, &present_input, &targets.output_view);
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &targets.output,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.gpu.output_buffer,
+                layout: compat::image_layout(
+                    u32_size * texture_width_padded,
+                    texture_height_padded,
+                ),
+            },
+            targets.size,
+        );
+
+        let hdr_readback = std::mem::take(&mut self.hdr_screenshot).then(|| {
+            HdrReadback::encode(
+                &self.gpu,
+                &mut encoder,
+                scene,
+                texture_width,
+                texture_height,
+            )
+        });
+
+        command_buffers.push(encoder.finish());
+        self.gpu.queue.submit(command_buffers);
+        self.targets = Some(targets);
+
+        if self.options.validation {
+            if let Some(error) = pollster::block_on(self.gpu.device.pop_error_scope()) {
+                self.report_error(GpuError::new(error, Some(self.frame_index)));
+            }
+        }
+
+        if let Some(readback) = hdr_readback {
+            let path = screenshot_path("exr");
+            let saved = readback.save_exr(&self.gpu, self.options.frame_timeout, Path::new(&path));
+            if let Err(err) = saved {
+                eprintln!("Failed to save screenshot to {}: {}", path, err);
+            }
+        }
+
+        if let Err(err) = gpu::map_read(
+            &self.gpu.device,
+            &self.gpu.output_buffer,
+            self.options.frame_timeout,
+        ) {
+            self.recover_from_readback(err);
+            return;
+        }
+
+        let frame_changed;
+        {
+            let buffer_slice = self.gpu.output_buffer.slice(..);
+            let data = buffer_slice.get_mapped_range();
+
+            // Drop the row padding so the frame is exactly the widget's size.
+            let row_size = (u32_size * texture_width) as usize;
+            let padded_row_size = (u32_size * texture_width_padded) as usize;
+            let rows = || gpu::unpadded_rows(&data, row_size, padded_row_size, texture_height);
+
+            let hash = frame_hash(texture_width, texture_height, rows());
+            let unchanged = matches!(&self.cached_image, Some((cached, _)) if *cached == hash);
+
+            if !unchanged {
+                let mut pixels = Vec::with_capacity(row_size * texture_height as usize);
+                for row in rows() {
+                    pixels.extend_from_slice(row);
+                }
+
+                let image_buff = ImageBuf::from_raw(
+                    pixels,
+                    ImageFormat::RgbaPremul,
+                    texture_width as usize,
+                    texture_height as usize,
+                );
+
+                self.cached_image = Some((hash, image_buff.to_image(ctx.render_ctx)));
+                self.last_frame = Some(image_buff);
+            }
+
+            let image_size = Size::new(texture_width as f64, texture_height as f64) / scale;
+            let interpolation = if scale < 1.0 {
+                InterpolationMode::Bilinear
+            } else {
+                InterpolationMode::NearestNeighbor
+            };
+            self.cached_layout = (image_size, interpolation);
+            self.paint_cached(ctx);
+
+            frame_changed = !unchanged;
+        };
+        self.gpu.output_buffer.unmap();
+
+        if let Some(rulers) = &self.rulers {
+            rulers.paint(ctx, &data.guides, env);
+        }
+
+        while let Ok(error) = self.gpu.errors.try_recv() {
+            self.report_error(error);
+        }
+
+        if let Some(latency) = self.latency.frame(frame_changed) {
+            self.new_latency = Some(latency);
+        }
+
+        if let (Some(image), true) = (&self.last_frame, frame_changed) {
+            let frame = FrameRef {
+                image,
+                index: self.frame_index,
+                time: data.playback.time,
+            };
+            for sink in &mut self.sinks {
+                sink.submit(frame);
+            }
+        }
+
+        let captured = match (&mut self.recording, &self.last_frame) {
+            (Some(recording), Some(frame)) => recording.capture(frame),
+            _ => false,
+        };
+
+        if let Some((sink, id)) = &self.event_sink {
+            if captured {
+                let _ = sink.submit_command(RECORD_NEXT, (), Target::Widget(*id));
+            }
+            if frame_changed {
+                let _ = sink.submit_command(FRAME_RENDERED, (), Target::Widget(*id));
+            }
+            if !reduced_quality
+                && self
+                    .accumulator
+                    .as_ref()
+                    .map_or(false, Accumulator::converging)
+            {
+                let _ = sink.submit_command(REPAINT, (), Target::Widget(*id));
+            }
+        }
+    }
+}
+
+/// Center `content` in `parent`, scaled by the largest whole number of
+/// device pixels per content pixel that fits, or 1 if none does.
+fn integer_fit(parent: Size, content: Size, device_scale: f64) -> Affine {
+    let fit = (parent.width / content.width).min(parent.height / content.height);
+    let factor = (fit * device_scale).floor().max(1.0) / device_scale;
+    let origin = (parent.to_vec2() - content.to_vec2() * factor) / 2.0;
+    let snap = |value: f64| (value * device_scale).round() / device_scale;
+    Affine::translate((snap(origin.x), snap(origin.y))) * Affine::scale(factor)
+}
+
+/// What the scene is cleared to, in linear color.
+fn clear_color(env: &Env) -> wgpu::Color {
+    let background = if env.try_get(theme::HIGH_CONTRAST).unwrap_or(false) {
+        theme::HIGH_CONTRAST_BACKGROUND
+    } else {
+        env.try_get(theme::VIEWPORT_BACKGROUND)
+            .unwrap_or(theme::DARK_BACKGROUND)
+    };
+    theme::to_linear(&background)
+}
+
+fn screenshot_path(extension: &str) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("screenshot-{}.{}", timestamp, extension)
+}
+
+/// A cheap hash of a frame's rows, used only to spot unchanged frames.
+fn frame_hash<'a>(width: u32, height: u32, rows: impl Iterator<Item = &'a [u8]>) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ (((width as u64) << 32) | height as u64);
+    for row in rows {
+        let mut words = row.chunks_exact(8);
+        for word in &mut words {
+            let word = u64::from_le_bytes(word.try_into().unwrap());
+            hash = (hash ^ word).wrapping_mul(PRIME).rotate_left(23);
+        }
+        for &byte in words.remainder() {
+            hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+        }
+    }
+    hash
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}